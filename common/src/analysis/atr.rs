@@ -0,0 +1,143 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::Candle;
+
+/// Average true range at one candle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AtrPoint {
+    pub at: u64,
+    /// This candle's true range.
+    pub true_range: f64,
+    pub atr: f64,
+    /// `atr` as a percentage of the close.
+    pub atr_pct: f64,
+}
+
+impl AtrPoint {
+    /// How many ATRs this candle's range spans, above 1.5 is a volatility spike.
+    pub fn range_to_atr(&self) -> f64 {
+        if self.atr > 0.0 {
+            self.true_range / self.atr
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The larger of the candle's own range and its gap from the previous close.
+pub fn true_range(candle: &Candle, previous_close: Option<f64>) -> f64 {
+    let range = candle.high - candle.low;
+    match previous_close {
+        Some(previous_close) => range
+            .max((candle.high - previous_close).abs())
+            .max((candle.low - previous_close).abs()),
+        None => range,
+    }
+}
+
+/// Wilder's average true range over `period` candles, from the first candle it's defined on.
+pub fn calculate_atr(candles: &[Candle], period: usize) -> Result<Vec<AtrPoint>> {
+    if period == 0 {
+        bail!("ATR period must be positive")
+    }
+    if candles.len() <= period {
+        bail!("Insufficient data for ATR({period}) calculation")
+    }
+
+    let true_ranges: Vec<f64> = candles
+        .iter()
+        .enumerate()
+        .map(|(i, candle)| true_range(candle, i.checked_sub(1).map(|i| candles[i].close)))
+        .collect();
+
+    // Seeded with the mean of the first `period` ranges after the first candle, which has no
+    // previous close to gap from.
+    let mut atr = true_ranges[1..=period].iter().sum::<f64>() / period as f64;
+    let mut points = Vec::with_capacity(candles.len() - period);
+    for i in period..candles.len() {
+        if i > period {
+            atr = (atr * (period - 1) as f64 + true_ranges[i]) / period as f64;
+        }
+        let close = candles[i].close;
+        points.push(AtrPoint {
+            at: candles[i].open_time as u64,
+            true_range: true_ranges[i],
+            atr,
+            atr_pct: if close > 0.0 {
+                atr / close * 100.0
+            } else {
+                0.0
+            },
+        });
+    }
+    Ok(points)
+}
+
+pub fn parse_atr_csv(points: &[AtrPoint]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("at,true_range,atr,atr_pct,range_to_atr\n");
+
+    for point in points {
+        csv_string.push_str(&format!(
+            "{},{:.4},{:.4},{:.2},{:.2}\n",
+            point.at,
+            point.true_range,
+            point.atr,
+            point.atr_pct,
+            point.range_to_atr()
+        ));
+    }
+    csv_string
+}
+
+/// The stop buffers the instructions ask for, 1.5x and 2x the latest ATR.
+pub fn format_atr_stop_buffers(latest: &AtrPoint) -> String {
+    format!(
+        "latest_atr={:.4}, stop_buffer_1_5x={:.4}, stop_buffer_2x={:.4}\n",
+        latest.atr,
+        latest.atr * 1.5,
+        latest.atr * 2.0
+    )
+}
+
+/// The ATR table followed by the stop buffers of the latest candle.
+pub fn get_atr_csv(candles: &[Candle], period: usize) -> Result<String> {
+    let points = calculate_atr(candles, period)?;
+    let mut output = parse_atr_csv(&points);
+    if let Some(latest) = points.last() {
+        output.push_str(&format_atr_stop_buffers(latest));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    #[test]
+    fn test_atr() {
+        // Every candle spans 2, the close steps by 1, then a 10 point gap.
+        let mut closes: Vec<f64> = (0..20).map(|i| 100.0 + i as f64).collect();
+        closes.push(129.0);
+        let points = calculate_atr(&candles(&closes), 14).unwrap();
+        assert_eq!(points.len(), 21 - 14);
+        assert_eq!(points[0].at, 14 * 60_000);
+        assert_eq!(points[0].atr, 2.0);
+        assert_eq!(points[5].atr, 2.0);
+
+        let spike = points.last().unwrap();
+        assert_eq!(spike.true_range, 11.0);
+        assert!((spike.atr - (2.0 * 13.0 + 11.0) / 14.0).abs() < 1e-12);
+        assert!(spike.range_to_atr() > 1.5);
+
+        let csv = get_atr_csv(&candles(&closes), 14).unwrap();
+        assert!(csv.starts_with("at,true_range,atr,atr_pct,range_to_atr\n"));
+        assert!(
+            csv.ends_with("latest_atr=2.6429, stop_buffer_1_5x=3.9643, stop_buffer_2x=5.2857\n")
+        );
+
+        assert!(calculate_atr(&candles(&closes[..14]), 14).is_err());
+    }
+}
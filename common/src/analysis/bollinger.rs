@@ -0,0 +1,134 @@
+use anyhow::{bail, Result};
+use m4rs::{bolinger_band, Candlestick};
+use serde::{Deserialize, Serialize};
+
+use super::m4rs::candle_to_m4rs_candlestick;
+use crate::Candle;
+
+/// Bollinger Bands around the SMA of the closes at one candle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BollingerPoint {
+    pub at: u64,
+    pub close: f64,
+    pub middle: f64,
+    pub upper: f64,
+    pub lower: f64,
+}
+
+impl BollingerPoint {
+    /// Where the close sits in the band, 0 at the lower band and 1 at the upper one.
+    /// `None` when the band has no width.
+    pub fn percent_b(&self) -> Option<f64> {
+        let width = self.upper - self.lower;
+        (width > 0.0).then(|| (self.close - self.lower) / width)
+    }
+
+    /// Band width as a percentage of the middle band, low readings are a squeeze.
+    pub fn bandwidth_pct(&self) -> f64 {
+        if self.middle > 0.0 {
+            (self.upper - self.lower) / self.middle * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Bands `std_dev` standard deviations around the `period` SMA, from the first candle
+/// they're defined on.
+pub fn calculate_bollinger_bands(
+    candles: &[Candle],
+    period: usize,
+    std_dev: f64,
+) -> Result<Vec<BollingerPoint>> {
+    if period < 2 {
+        bail!("Bollinger Band period must be at least 2")
+    }
+    if !(std_dev.is_finite() && std_dev > 0.0) {
+        bail!("Bollinger Band width must be a positive number of standard deviations")
+    }
+    if candles.len() < period {
+        bail!("Insufficient data for Bollinger Band({period}) calculation")
+    }
+
+    let m4rs_candlesticks: Vec<Candlestick> =
+        candles.iter().map(candle_to_m4rs_candlestick).collect();
+    let bands = bolinger_band(&m4rs_candlesticks, period)?;
+    // The bands end on the latest candle.
+    let offset = candles.len() - bands.len();
+
+    Ok(bands
+        .iter()
+        .zip(&candles[offset..])
+        .map(|(band, candle)| BollingerPoint {
+            at: band.at,
+            close: candle.close,
+            middle: band.avg,
+            upper: band.avg + std_dev * band.sigma,
+            lower: band.avg - std_dev * band.sigma,
+        })
+        .collect())
+}
+
+pub fn parse_bollinger_csv(points: &[BollingerPoint]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("at,close,avg,upper,lower,percent_b,bandwidth_pct\n");
+
+    for point in points {
+        csv_string.push_str(&format!(
+            "{},{:.4},{:.4},{:.4},{:.4},{},{:.2}\n",
+            point.at,
+            point.close,
+            point.middle,
+            point.upper,
+            point.lower,
+            point
+                .percent_b()
+                .map(|percent_b| format!("{percent_b:.2}"))
+                .unwrap_or_default(),
+            point.bandwidth_pct()
+        ));
+    }
+    csv_string
+}
+
+pub fn get_bollinger_csv(candles: &[Candle], period: usize, std_dev: f64) -> Result<String> {
+    let points = calculate_bollinger_bands(candles, period, std_dev)?;
+    Ok(parse_bollinger_csv(&points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    #[test]
+    fn test_bollinger_bands() {
+        // Flat, then a close two standard deviations above the last four.
+        let closes = [10.0, 10.0, 10.0, 10.0, 9.0, 11.0, 9.0, 11.0];
+        let points = calculate_bollinger_bands(&candles(&closes), 4, 2.0).unwrap();
+        assert_eq!(points.len(), 8 - 3);
+        assert_eq!(points[0].at, 3 * 60_000);
+        assert_eq!(points[0].percent_b(), None);
+        assert_eq!(points[0].bandwidth_pct(), 0.0);
+
+        let latest = points.last().unwrap();
+        assert_eq!(latest.middle, 10.0);
+        assert_eq!(latest.upper, 12.0);
+        assert_eq!(latest.lower, 8.0);
+        assert_eq!(latest.percent_b(), Some(0.75));
+        assert_eq!(latest.bandwidth_pct(), 40.0);
+
+        let csv = parse_bollinger_csv(&points);
+        assert!(csv.starts_with(
+            "at,close,avg,upper,lower,percent_b,bandwidth_pct\n180000,10.0000,10.0000,10.0000,10.0000,,0.00\n"
+        ));
+        assert!(csv.ends_with("\n420000,11.0000,10.0000,12.0000,8.0000,0.75,40.00\n"));
+
+        // Wider bands for the same candles.
+        let wide = calculate_bollinger_bands(&candles(&closes), 4, 3.0).unwrap();
+        assert_eq!(wide.last().unwrap().upper, 13.0);
+
+        assert!(calculate_bollinger_bands(&candles(&closes[..3]), 4, 2.0).is_err());
+        assert!(calculate_bollinger_bands(&candles(&closes), 4, 0.0).is_err());
+    }
+}
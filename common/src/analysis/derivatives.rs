@@ -0,0 +1,263 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+use strum::Display;
+
+use crate::{FundingRate, FuturesMetrics};
+
+// 0.05% per settlement, five times the usual 0.01% baseline.
+const CROWDED_FUNDING_RATE: f64 = 0.0005;
+// Top traders holding twice as much long as short, or the reverse.
+const CROWDED_LONG_SHORT_RATIO: f64 = 2.0;
+
+/// Which side of the perpetual market is overextended, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Crowding {
+    Longs,
+    Shorts,
+    Neutral,
+}
+
+impl Crowding {
+    /// Crowded when the funding rate or the top traders' positioning leans hard one way
+    /// and the other doesn't lean the opposite way.
+    pub fn from_positioning(funding_rate: f64, long_short_ratio: f64) -> Self {
+        let longs =
+            funding_rate >= CROWDED_FUNDING_RATE || long_short_ratio >= CROWDED_LONG_SHORT_RATIO;
+        let shorts = funding_rate <= -CROWDED_FUNDING_RATE
+            || long_short_ratio <= 1.0 / CROWDED_LONG_SHORT_RATIO;
+        match (longs, shorts) {
+            (true, false) => Crowding::Longs,
+            (false, true) => Crowding::Shorts,
+            _ => Crowding::Neutral,
+        }
+    }
+}
+
+/// Latest perpetual futures positioning of a pair, summarised from `FuturesMetrics`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DerivativesSnapshot {
+    /// Period of the open interest and long/short ratio readings, e.g. "1h".
+    pub period: String,
+    /// Time of the latest reading (ms).
+    pub timestamp: i64,
+    /// Last settled funding rate, e.g. 0.0001 for 0.01%.
+    pub funding_rate: f64,
+    /// Mean of the funding rates settled over the window.
+    pub average_funding_rate: f64,
+    /// In contracts of the base asset.
+    pub open_interest: f64,
+    /// In the quote asset.
+    pub open_interest_value: f64,
+    /// Open interest change over the window, in percent.
+    pub open_interest_change_pct: f64,
+    /// Top traders' long/short position ratio.
+    pub long_short_ratio: f64,
+    /// Share of the top traders' positions that are long, 0 to 1.
+    pub long_share: f64,
+    pub crowding: Crowding,
+}
+
+fn parse(field: &str, value: &str) -> Result<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .with_context(|| format!("Invalid {field} {value:?} in futures metrics"))
+}
+
+impl DerivativesSnapshot {
+    /// Fails when any of the histories is empty or holds a malformed number.
+    pub fn from_metrics(metrics: &FuturesMetrics, period: &str) -> Result<Self> {
+        let latest_funding = metrics
+            .funding_rates
+            .iter()
+            .max_by_key(|rate| rate.funding_time)
+            .context("No funding rates in futures metrics")?;
+        let first_open_interest = metrics
+            .open_interest
+            .iter()
+            .min_by_key(|oi| oi.timestamp)
+            .context("No open interest in futures metrics")?;
+        let latest_open_interest = metrics
+            .open_interest
+            .iter()
+            .max_by_key(|oi| oi.timestamp)
+            .context("No open interest in futures metrics")?;
+        let latest_ratio = metrics
+            .long_short_ratios
+            .iter()
+            .max_by_key(|ratio| ratio.timestamp)
+            .context("No long/short ratios in futures metrics")?;
+
+        let funding_rates = metrics
+            .funding_rates
+            .iter()
+            .map(|rate| parse("funding rate", &rate.funding_rate))
+            .collect::<Result<Vec<_>>>()?;
+        let average_funding_rate = funding_rates.iter().sum::<f64>() / funding_rates.len() as f64;
+        let funding_rate = parse("funding rate", &latest_funding.funding_rate)?;
+
+        let first_value = parse("open interest", &first_open_interest.sum_open_interest)?;
+        let open_interest = parse("open interest", &latest_open_interest.sum_open_interest)?;
+        let open_interest_change_pct = if first_value > 0.0 {
+            (open_interest - first_value) / first_value * 100.0
+        } else {
+            0.0
+        };
+        let long_short_ratio = parse("long/short ratio", &latest_ratio.long_short_ratio)?;
+
+        Ok(DerivativesSnapshot {
+            period: period.to_string(),
+            timestamp: latest_open_interest.timestamp.max(latest_ratio.timestamp),
+            funding_rate,
+            average_funding_rate,
+            open_interest,
+            open_interest_value: parse(
+                "open interest value",
+                &latest_open_interest.sum_open_interest_value,
+            )?,
+            open_interest_change_pct,
+            long_short_ratio,
+            long_share: parse("long account", &latest_ratio.long_account)?,
+            crowding: Crowding::from_positioning(funding_rate, long_short_ratio),
+        })
+    }
+}
+
+impl fmt::Display for DerivativesSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "funding_rate={:.4}% (avg {:.4}%), open_interest={:.2} ({:+.2}% over the window, {}), top_trader_long_short_ratio={:.2} ({:.0}% long), crowding={}",
+            self.funding_rate * 100.0,
+            self.average_funding_rate * 100.0,
+            self.open_interest,
+            self.open_interest_change_pct,
+            self.period,
+            self.long_short_ratio,
+            self.long_share * 100.0,
+            self.crowding
+        )
+    }
+}
+
+pub fn funding_rates_to_csv(funding_rates: &[FundingRate]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("funding_time,funding_rate,mark_price\n");
+
+    for rate in funding_rates {
+        csv_string.push_str(&format!(
+            "{},{},{}\n",
+            rate.funding_time, rate.funding_rate, rate.mark_price
+        ));
+    }
+    csv_string
+}
+
+/// Open interest and the top traders' long/short ratio side by side, one row per period.
+/// A reading missing from either history is left empty.
+pub fn positioning_to_csv(metrics: &FuturesMetrics) -> String {
+    let mut rows: BTreeMap<i64, [&str; 4]> = BTreeMap::new();
+    for oi in &metrics.open_interest {
+        let row = rows.entry(oi.timestamp).or_default();
+        row[0] = &oi.sum_open_interest;
+        row[1] = &oi.sum_open_interest_value;
+    }
+    for ratio in &metrics.long_short_ratios {
+        let row = rows.entry(ratio.timestamp).or_default();
+        row[2] = &ratio.long_short_ratio;
+        row[3] = &ratio.long_account;
+    }
+
+    let mut csv_string = String::new();
+    csv_string
+        .push_str("timestamp,open_interest,open_interest_value,long_short_ratio,long_account\n");
+    for (timestamp, [open_interest, value, ratio, long_account]) in rows {
+        csv_string.push_str(&format!(
+            "{timestamp},{open_interest},{value},{ratio},{long_account}\n"
+        ));
+    }
+    csv_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LongShortRatio, OpenInterest};
+
+    const HOUR: i64 = 3_600_000;
+    // 2025-04-30T12:00:00Z
+    const START: i64 = 1_746_014_400_000;
+
+    fn metrics() -> FuturesMetrics {
+        let funding_rate = |hours: i64, rate: &str| FundingRate {
+            symbol: "SOLUSDT".to_string(),
+            funding_rate: rate.to_string(),
+            funding_time: START + hours * HOUR,
+            mark_price: "146.5".to_string(),
+        };
+        FuturesMetrics {
+            funding_rates: vec![funding_rate(0, "0.0004"), funding_rate(8, "0.0008")],
+            open_interest: (0..3)
+                .map(|i| OpenInterest {
+                    symbol: "SOLUSDT".to_string(),
+                    sum_open_interest: (1000 + i * 50).to_string(),
+                    sum_open_interest_value: (146_500 + i * 7325).to_string(),
+                    timestamp: START + i * HOUR,
+                })
+                .collect(),
+            long_short_ratios: (1..4)
+                .map(|i| LongShortRatio {
+                    symbol: "SOLUSDT".to_string(),
+                    long_short_ratio: "1.5".to_string(),
+                    long_account: "0.6".to_string(),
+                    short_account: "0.4".to_string(),
+                    timestamp: START + i * HOUR,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_derivatives_snapshot() {
+        let snapshot = DerivativesSnapshot::from_metrics(&metrics(), "1h").unwrap();
+        assert_eq!(snapshot.funding_rate, 0.0008);
+        assert!((snapshot.average_funding_rate - 0.0006).abs() < 1e-12);
+        assert_eq!(snapshot.open_interest, 1100.0);
+        assert_eq!(snapshot.open_interest_change_pct, 10.0);
+        assert_eq!(snapshot.timestamp, START + 3 * HOUR);
+        assert_eq!(snapshot.crowding, Crowding::Longs);
+        assert_eq!(
+            snapshot.to_string(),
+            "funding_rate=0.0800% (avg 0.0600%), open_interest=1100.00 (+10.00% over the window, 1h), top_trader_long_short_ratio=1.50 (60% long), crowding=longs"
+        );
+
+        let empty = FuturesMetrics {
+            long_short_ratios: Vec::new(),
+            ..metrics()
+        };
+        assert!(DerivativesSnapshot::from_metrics(&empty, "1h").is_err());
+    }
+
+    #[test]
+    fn test_crowding() {
+        assert_eq!(Crowding::from_positioning(0.0001, 1.2), Crowding::Neutral);
+        assert_eq!(Crowding::from_positioning(-0.0007, 0.9), Crowding::Shorts);
+        assert_eq!(Crowding::from_positioning(0.0001, 0.4), Crowding::Shorts);
+        // Shorts paying up while top traders are heavily long is mixed.
+        assert_eq!(Crowding::from_positioning(-0.0007, 2.5), Crowding::Neutral);
+    }
+
+    #[test]
+    fn test_positioning_to_csv() {
+        let csv = positioning_to_csv(&metrics());
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[1], "1746014400000,1000,146500,,");
+        assert_eq!(lines[2], "1746018000000,1050,153825,1.5,0.6");
+        assert_eq!(lines[4], "1746025200000,,,1.5,0.6");
+    }
+}
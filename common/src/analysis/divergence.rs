@@ -0,0 +1,387 @@
+use anyhow::{bail, Result};
+use m4rs::Candlestick;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum::Display;
+
+use super::{
+    indicator_config::IndicatorConfig,
+    m4rs::candle_to_m4rs_candlestick,
+    macd::calculate_macd,
+    rsi::{calculate_rsi, calculate_stoch_rsi},
+    swings::{find_swings, Swing, SwingKind},
+};
+use crate::Candle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DivergenceSource {
+    Rsi,
+    /// The smoothed %K line.
+    StochRsi,
+    MacdHistogram,
+    Volume,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DivergenceKind {
+    /// Price makes a new extreme the oscillator doesn't confirm, a possible reversal.
+    Regular,
+    /// The oscillator makes a new extreme price doesn't, a possible continuation.
+    Hidden,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DivergenceDirection {
+    Bullish,
+    Bearish,
+}
+
+/// Price and an oscillator disagreeing between two swing lows, or two swing highs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Divergence {
+    pub source: DivergenceSource,
+    pub kind: DivergenceKind,
+    pub direction: DivergenceDirection,
+    /// Open times of the two swings.
+    pub start: i64,
+    pub end: i64,
+    /// Candles from the first swing to the second.
+    pub candles: usize,
+    pub start_price: f64,
+    pub end_price: f64,
+    pub start_value: f64,
+    pub end_value: f64,
+    /// 0 to 1, how far the oscillator moved against price relative to its range over the candles.
+    pub strength: f64,
+    /// False while the second swing is the unconfirmed extreme of the current leg.
+    pub confirmed: bool,
+}
+
+// One reading per candle, `None` until the indicator has warmed up.
+fn source_values(
+    candles: &[Candle],
+    config: &IndicatorConfig,
+) -> Result<Vec<(DivergenceSource, Vec<Option<f64>>)>> {
+    let stoch = &config.stoch_rsi;
+    let closes: Vec<f64> = candles.iter().map(|candle| candle.close).collect();
+
+    let rsi = calculate_rsi(&closes, stoch.rsi_period)
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| (i >= stoch.rsi_period).then_some(value))
+        .collect();
+
+    let m4rs_candlesticks: Vec<Candlestick> =
+        candles.iter().map(candle_to_m4rs_candlestick).collect();
+    let (_, smoothed_k, _) = calculate_stoch_rsi(
+        &m4rs_candlesticks,
+        stoch.rsi_period,
+        stoch.stoch_period,
+        stoch.smooth_k,
+        stoch.smooth_d,
+    )?;
+    let stoch_warm_up = stoch.rsi_period + stoch.stoch_period + stoch.smooth_k;
+    let stoch_rsi = smoothed_k
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| (i >= stoch_warm_up).then_some(value))
+        .collect();
+
+    let histograms: HashMap<i64, f64> = calculate_macd(
+        candles,
+        config.macd.fast,
+        config.macd.slow,
+        config.macd.signal,
+    )?
+    .into_iter()
+    .map(|point| (point.at as i64, point.histogram))
+    .collect();
+    let macd_histogram = candles
+        .iter()
+        .map(|candle| histograms.get(&candle.open_time).copied())
+        .collect();
+
+    let volume = candles.iter().map(|candle| Some(candle.volume)).collect();
+
+    Ok(vec![
+        (DivergenceSource::Rsi, rsi),
+        (DivergenceSource::StochRsi, stoch_rsi),
+        (DivergenceSource::MacdHistogram, macd_histogram),
+        (DivergenceSource::Volume, volume),
+    ])
+}
+
+// Which divergence two same kind swings and the oscillator readings at them form, if any.
+// Volume only has regular divergences: a new extreme on falling volume.
+fn classify(
+    source: DivergenceSource,
+    kind: SwingKind,
+    (start_price, end_price): (f64, f64),
+    (start_value, end_value): (f64, f64),
+) -> Option<(DivergenceKind, DivergenceDirection)> {
+    let price_rises = end_price > start_price;
+    let price_falls = end_price < start_price;
+    let value_rises = end_value > start_value;
+    let value_falls = end_value < start_value;
+
+    if source == DivergenceSource::Volume {
+        return match kind {
+            SwingKind::Low if price_falls && value_falls => {
+                Some((DivergenceKind::Regular, DivergenceDirection::Bullish))
+            }
+            SwingKind::High if price_rises && value_falls => {
+                Some((DivergenceKind::Regular, DivergenceDirection::Bearish))
+            }
+            _ => None,
+        };
+    }
+    match kind {
+        SwingKind::Low if price_falls && value_rises => {
+            Some((DivergenceKind::Regular, DivergenceDirection::Bullish))
+        }
+        SwingKind::Low if price_rises && value_falls => {
+            Some((DivergenceKind::Hidden, DivergenceDirection::Bullish))
+        }
+        SwingKind::High if price_rises && value_falls => {
+            Some((DivergenceKind::Regular, DivergenceDirection::Bearish))
+        }
+        SwingKind::High if price_falls && value_rises => {
+            Some((DivergenceKind::Hidden, DivergenceDirection::Bearish))
+        }
+        _ => None,
+    }
+}
+
+/// Regular and hidden divergences between consecutive swing lows and consecutive swing highs
+/// of `candles` (see `find_swings`, with `divergence_swing_pct`) and RSI, Stoch RSI %K, the
+/// MACD histogram and volume, ordered by the second swing.
+///
+/// Swings before an indicator has warmed up are skipped for that indicator. Fails on fewer
+/// candles than `IndicatorConfig::divergence_warm_up`.
+pub fn find_divergences(candles: &[Candle], config: &IndicatorConfig) -> Result<Vec<Divergence>> {
+    if candles.len() < config.divergence_warm_up() {
+        bail!("Insufficient data for divergence detection")
+    }
+    let sources = source_values(candles, config)?;
+    let index_of: HashMap<i64, usize> = candles
+        .iter()
+        .enumerate()
+        .map(|(i, candle)| (candle.open_time, i))
+        .collect();
+
+    let swings = find_swings(candles, config.divergence_swing_pct);
+    // Swings alternate, so the previous swing of the same kind is two back.
+    let pairs: Vec<(&Swing, &Swing)> = swings
+        .iter()
+        .zip(swings.iter().skip(2))
+        .filter(|(start, end)| start.kind == end.kind)
+        .collect();
+
+    let mut divergences = Vec::new();
+    for (start, end) in pairs {
+        let (Some(&from), Some(&to)) =
+            (index_of.get(&start.open_time), index_of.get(&end.open_time))
+        else {
+            continue;
+        };
+        for (source, values) in &sources {
+            let (Some(start_value), Some(end_value)) = (values[from], values[to]) else {
+                continue;
+            };
+            let Some((kind, direction)) = classify(
+                *source,
+                start.kind,
+                (start.price, end.price),
+                (start_value, end_value),
+            ) else {
+                continue;
+            };
+
+            let (min, max) = values
+                .iter()
+                .flatten()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+                    (min.min(value), max.max(value))
+                });
+            let strength = if max > min {
+                ((end_value - start_value).abs() / (max - min)).min(1.0)
+            } else {
+                0.0
+            };
+
+            divergences.push(Divergence {
+                source: *source,
+                kind,
+                direction,
+                start: start.open_time,
+                end: end.open_time,
+                candles: to - from,
+                start_price: start.price,
+                end_price: end.price,
+                start_value,
+                end_value,
+                strength,
+                confirmed: end.confirmed,
+            });
+        }
+    }
+    Ok(divergences)
+}
+
+pub fn divergences_to_csv(divergences: &[Divergence]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("start,end,candles,source,kind,direction,start_price,end_price,start_value,end_value,strength,confirmed\n");
+
+    for divergence in divergences {
+        csv_string.push_str(&format!(
+            "{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.4},{:.2},{}\n",
+            divergence.start,
+            divergence.end,
+            divergence.candles,
+            divergence.source,
+            divergence.kind,
+            divergence.direction,
+            divergence.start_price,
+            divergence.end_price,
+            divergence.start_value,
+            divergence.end_value,
+            divergence.strength,
+            divergence.confirmed
+        ));
+    }
+    csv_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    fn config() -> IndicatorConfig {
+        IndicatorConfig::default()
+            .with_overrides([
+                ("stoch_rsi", "3,3,1,1"),
+                ("macd", "2,4,2"),
+                ("divergence", "5"),
+            ])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_classify() {
+        use DivergenceDirection::*;
+        use DivergenceKind::*;
+        use DivergenceSource::*;
+
+        let cases = [
+            (
+                Rsi,
+                SwingKind::Low,
+                (100.0, 95.0),
+                (30.0, 35.0),
+                Some((Regular, Bullish)),
+            ),
+            (
+                Rsi,
+                SwingKind::Low,
+                (100.0, 105.0),
+                (30.0, 25.0),
+                Some((Hidden, Bullish)),
+            ),
+            (
+                Rsi,
+                SwingKind::High,
+                (100.0, 105.0),
+                (70.0, 65.0),
+                Some((Regular, Bearish)),
+            ),
+            (
+                Rsi,
+                SwingKind::High,
+                (100.0, 95.0),
+                (70.0, 75.0),
+                Some((Hidden, Bearish)),
+            ),
+            (Rsi, SwingKind::High, (100.0, 105.0), (70.0, 75.0), None),
+            (
+                Volume,
+                SwingKind::Low,
+                (100.0, 95.0),
+                (10.0, 5.0),
+                Some((Regular, Bullish)),
+            ),
+            (
+                Volume,
+                SwingKind::High,
+                (100.0, 105.0),
+                (10.0, 5.0),
+                Some((Regular, Bearish)),
+            ),
+            (Volume, SwingKind::High, (100.0, 95.0), (10.0, 15.0), None),
+        ];
+        for (source, kind, prices, values, expected) in cases {
+            assert_eq!(
+                classify(source, kind, prices, values),
+                expected,
+                "{source} {kind} {prices:?} {values:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_divergences() {
+        // A steady climb to 120, a pullback, then a slower push to a higher high on less volume.
+        let mut closes: Vec<f64> = (0..10).map(|i| 100.0 + 2.0 * i as f64).collect();
+        closes.extend([
+            118.0, 112.0, 106.0, 108.0, 110.0, 112.0, 114.0, 116.0, 118.0, 120.0,
+        ]);
+        closes.extend([121.0, 122.0, 114.0, 110.0]);
+        let mut candles = candles(&closes);
+        for candle in &mut candles[20..] {
+            candle.volume = 0.5;
+        }
+
+        let divergences = find_divergences(&candles, &config()).unwrap();
+        let highs: Vec<_> = divergences
+            .iter()
+            .filter(|divergence| divergence.end == 21 * 60_000)
+            .collect();
+        assert!(!highs.is_empty(), "{divergences:?}");
+        for divergence in &highs {
+            assert_eq!(divergence.start, 9 * 60_000);
+            assert_eq!(divergence.candles, 12);
+            assert_eq!(
+                (divergence.start_price, divergence.end_price),
+                (119.0, 123.0)
+            );
+            assert_eq!(divergence.kind, DivergenceKind::Regular);
+            assert_eq!(divergence.direction, DivergenceDirection::Bearish);
+            assert!(divergence.end_value < divergence.start_value);
+            assert!((0.0..=1.0).contains(&divergence.strength));
+            assert!(divergence.confirmed);
+        }
+        let volume = highs
+            .iter()
+            .find(|divergence| divergence.source == DivergenceSource::Volume)
+            .unwrap();
+        assert_eq!((volume.start_value, volume.end_value), (1.0, 0.5));
+        assert_eq!(volume.strength, 1.0);
+        assert!(highs
+            .iter()
+            .any(|divergence| divergence.source == DivergenceSource::Rsi));
+
+        let csv = divergences_to_csv(&[**volume]);
+        assert_eq!(
+            csv,
+            "start,end,candles,source,kind,direction,start_price,end_price,start_value,end_value,strength,confirmed\n540000,1260000,12,volume,regular,bearish,119.0000,123.0000,1.0000,0.5000,1.00,true\n"
+        );
+
+        assert!(find_divergences(&candles[..5], &config()).is_err());
+    }
+}
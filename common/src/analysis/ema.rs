@@ -0,0 +1,123 @@
+use anyhow::{bail, Result};
+use m4rs::{ema, Candlestick};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use super::m4rs::candle_to_m4rs_candlestick;
+use crate::Candle;
+
+/// Direction a fast line crossed a slow one, e.g. EMA(9) over EMA(21) or MACD over its signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Cross {
+    Up,
+    Down,
+}
+
+impl Cross {
+    /// How `fast - slow` changed sign between two readings, if it did.
+    pub fn between(previous_spread: f64, spread: f64) -> Option<Cross> {
+        if previous_spread <= 0.0 && spread > 0.0 {
+            Some(Cross::Up)
+        } else if previous_spread >= 0.0 && spread < 0.0 {
+            Some(Cross::Down)
+        } else {
+            None
+        }
+    }
+}
+
+/// A fast and a slow EMA of the closes at one candle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EmaPoint {
+    pub at: u64,
+    pub fast: f64,
+    pub slow: f64,
+    /// Set on the candle where the fast EMA crossed the slow one.
+    pub cross: Option<Cross>,
+}
+
+/// Fast and slow EMAs of the closes, from the first candle both are defined on.
+pub fn calculate_ema_cross(candles: &[Candle], fast: usize, slow: usize) -> Result<Vec<EmaPoint>> {
+    if fast >= slow {
+        bail!("EMA fast period {fast} must be shorter than slow period {slow}")
+    }
+    if candles.len() < slow {
+        bail!("Insufficient data for EMA({slow}) calculation")
+    }
+
+    let m4rs_candlesticks: Vec<Candlestick> =
+        candles.iter().map(candle_to_m4rs_candlestick).collect();
+    let fast_line = ema(&m4rs_candlesticks, fast)?;
+    let slow_line = ema(&m4rs_candlesticks, slow)?;
+    // Both lines end on the latest candle, the slow one starts later.
+    let offset = fast_line.len().saturating_sub(slow_line.len());
+
+    let mut points: Vec<EmaPoint> = Vec::with_capacity(slow_line.len());
+    for (fast_entry, slow_entry) in fast_line[offset..].iter().zip(&slow_line) {
+        let spread = fast_entry.value - slow_entry.value;
+        let cross = points
+            .last()
+            .and_then(|previous| Cross::between(previous.fast - previous.slow, spread));
+        points.push(EmaPoint {
+            at: slow_entry.at,
+            fast: fast_entry.value,
+            slow: slow_entry.value,
+            cross,
+        });
+    }
+    Ok(points)
+}
+
+pub fn parse_ema_csv(points: &[EmaPoint], fast: usize, slow: usize) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str(&format!("at,ema_{fast},ema_{slow},cross\n"));
+
+    for point in points {
+        csv_string.push_str(&format!(
+            "{},{:.4},{:.4},{}\n",
+            point.at,
+            point.fast,
+            point.slow,
+            point
+                .cross
+                .map(|cross| cross.to_string())
+                .unwrap_or_default()
+        ));
+    }
+    csv_string
+}
+
+pub fn get_ema_csv(candles: &[Candle], fast: usize, slow: usize) -> Result<String> {
+    let points = calculate_ema_cross(candles, fast, slow)?;
+    Ok(parse_ema_csv(&points, fast, slow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    #[test]
+    fn test_ema_cross() {
+        // Falling, then a sharp rally.
+        let closes: Vec<f64> = (0..30)
+            .map(|i| 100.0 - i as f64)
+            .chain((0..10).map(|i| 75.0 + 5.0 * i as f64))
+            .collect();
+        let points = calculate_ema_cross(&candles(&closes), 9, 21).unwrap();
+        assert_eq!(points.len(), 40 - 20);
+        assert!(points[0].fast < points[0].slow);
+
+        let crosses: Vec<_> = points.iter().filter_map(|point| point.cross).collect();
+        assert_eq!(crosses, [Cross::Up]);
+
+        let csv = parse_ema_csv(&points, 9, 21);
+        assert!(csv.starts_with("at,ema_9,ema_21,cross\n"));
+        assert_eq!(csv.matches(",up\n").count(), 1);
+
+        assert!(calculate_ema_cross(&candles(&closes[..10]), 9, 21).is_err());
+        assert!(calculate_ema_cross(&candles(&closes), 21, 9).is_err());
+    }
+}
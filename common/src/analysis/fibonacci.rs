@@ -0,0 +1,164 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use strum::Display;
+
+use super::swings::{find_swings, swings_to_csv, Swing, SwingKind};
+use crate::Candle;
+
+/// Retracements of the latest leg, then how far the next leg may run.
+pub const FIB_RETRACEMENTS: [f64; 3] = [0.382, 0.618, 1.0];
+pub const FIB_EXTENSIONS: [f64; 1] = [1.618];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum FibKind {
+    Retracement,
+    Extension,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FibLevel {
+    pub ratio: f64,
+    pub kind: FibKind,
+    pub price: f64,
+}
+
+/// Fibonacci levels of the leg between two swings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FibonacciLevels {
+    pub start: Swing,
+    pub end: Swing,
+    pub levels: Vec<FibLevel>,
+}
+
+impl FibonacciLevels {
+    /// Retracements measured back from `end` towards `start` (1.0 is `start` itself), and
+    /// extensions projected from `start` past `end`.
+    pub fn from_leg(start: Swing, end: Swing) -> Result<Self> {
+        if start.kind == end.kind {
+            bail!("A Fibonacci leg runs between a swing high and a swing low")
+        }
+        let range = end.price - start.price;
+        let retracements = FIB_RETRACEMENTS.iter().map(|&ratio| FibLevel {
+            ratio,
+            kind: FibKind::Retracement,
+            price: end.price - range * ratio,
+        });
+        let extensions = FIB_EXTENSIONS.iter().map(|&ratio| FibLevel {
+            ratio,
+            kind: FibKind::Extension,
+            price: start.price + range * ratio,
+        });
+        Ok(FibonacciLevels {
+            start,
+            end,
+            levels: retracements.chain(extensions).collect(),
+        })
+    }
+
+    /// Levels of the last leg in `swings`, e.g. from `find_swings`.
+    pub fn latest(swings: &[Swing]) -> Result<Self> {
+        match swings {
+            [.., start, end] => Self::from_leg(*start, *end),
+            _ => bail!("Not enough swings for Fibonacci levels, need at least two"),
+        }
+    }
+
+    pub fn is_up_leg(&self) -> bool {
+        self.end.kind == SwingKind::High
+    }
+}
+
+/// e.g. "up leg from low 99.0000 (0) to high 121.0000 (300000)", timestamps are open times.
+impl fmt::Display for FibonacciLevels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} leg from {} {:.4} ({}) to {} {:.4} ({}{})",
+            if self.is_up_leg() { "up" } else { "down" },
+            self.start.kind,
+            self.start.price,
+            self.start.open_time,
+            self.end.kind,
+            self.end.price,
+            self.end.open_time,
+            if self.end.confirmed {
+                ""
+            } else {
+                ", unconfirmed"
+            }
+        )
+    }
+}
+
+/// The leg and its levels, e.g. "fib_0.382=161.8000, fib_0.618=138.2000, ...".
+pub fn format_fibonacci_levels(fibonacci: &FibonacciLevels) -> String {
+    let levels: Vec<String> = fibonacci
+        .levels
+        .iter()
+        .map(|level| format!("fib_{:.3}={:.4}", level.ratio, level.price))
+        .collect();
+    format!("fib_leg={}\n{}\n", fibonacci, levels.join(", "))
+}
+
+/// The swings over `candles` followed by the Fibonacci levels of the latest leg.
+pub fn get_fibonacci_csv(candles: &[Candle], threshold_pct: f64) -> Result<String> {
+    let swings = find_swings(candles, threshold_pct);
+    let fibonacci = FibonacciLevels::latest(&swings)?;
+    let mut output = swings_to_csv(&swings);
+    output.push_str(&format_fibonacci_levels(&fibonacci));
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    fn swing(open_time: i64, price: f64, kind: SwingKind) -> Swing {
+        Swing {
+            open_time,
+            price,
+            kind,
+            confirmed: true,
+        }
+    }
+
+    #[test]
+    fn test_fibonacci_levels() {
+        let swings = [
+            swing(0, 120.0, SwingKind::High),
+            swing(1, 100.0, SwingKind::Low),
+            swing(2, 200.0, SwingKind::High),
+        ];
+        let up = FibonacciLevels::latest(&swings).unwrap();
+        assert!(up.is_up_leg());
+        let prices: Vec<f64> = up.levels.iter().map(|level| level.price).collect();
+        assert_eq!(prices, [161.8, 138.2, 100.0, 261.8]);
+        assert_eq!(
+            format_fibonacci_levels(&up),
+            "fib_leg=up leg from low 100.0000 (1) to high 200.0000 (2)\nfib_0.382=161.8000, fib_0.618=138.2000, fib_1.000=100.0000, fib_1.618=261.8000\n"
+        );
+
+        // A down leg retraces upwards and extends below its low.
+        let down = FibonacciLevels::latest(&swings[..2]).unwrap();
+        assert!(!down.is_up_leg());
+        assert!((down.levels[0].price - 107.64).abs() < 1e-9);
+        assert!((down.levels[3].price - 87.64).abs() < 1e-9);
+
+        assert!(FibonacciLevels::latest(&swings[..1]).is_err());
+        assert!(FibonacciLevels::from_leg(swings[0], swings[2]).is_err());
+
+        // The pending extreme of the current leg is flagged.
+        let csv = get_fibonacci_csv(&candles(&[100.0, 110.0, 120.0]), 5.0).unwrap();
+        assert!(csv.starts_with(
+            "open_time,kind,price,confirmed\n0,low,99.0000,true\n120000,high,121.0000,false\n"
+        ));
+        assert!(csv.contains(
+            "\nfib_leg=up leg from low 99.0000 (0) to high 121.0000 (120000, unconfirmed)\n"
+        ));
+        assert!(get_fibonacci_csv(&candles(&[100.0, 101.0]), 5.0).is_err());
+    }
+}
@@ -0,0 +1,415 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::moving_average::MovingAverage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StochRsiConfig {
+    pub rsi_period: usize,
+    pub stoch_period: usize,
+    pub smooth_k: usize,
+    pub smooth_d: usize,
+}
+
+impl Default for StochRsiConfig {
+    fn default() -> Self {
+        StochRsiConfig {
+            rsi_period: 14,
+            stoch_period: 14,
+            smooth_k: 3,
+            smooth_d: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BollingerConfig {
+    pub period: usize,
+    /// Band width in standard deviations.
+    pub std_dev: f64,
+}
+
+impl Default for BollingerConfig {
+    fn default() -> Self {
+        BollingerConfig {
+            period: 20,
+            std_dev: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmaCrossConfig {
+    pub fast: usize,
+    pub slow: usize,
+}
+
+impl Default for EmaCrossConfig {
+    fn default() -> Self {
+        EmaCrossConfig { fast: 9, slow: 21 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacdConfig {
+    pub fast: usize,
+    pub slow: usize,
+    pub signal: usize,
+}
+
+impl Default for MacdConfig {
+    fn default() -> Self {
+        MacdConfig {
+            fast: 12,
+            slow: 26,
+            signal: 9,
+        }
+    }
+}
+
+/// Parameters of every indicator the prompt reports and the chart draws. Build one per
+/// request and hand the same config to `PriceHistoryBuilder` and the feeder's `Chart` so the
+/// numbers in the prompt match the picture.
+///
+/// The defaults are the settings the prompt instructions are written against.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndicatorConfig {
+    pub stoch_rsi: StochRsiConfig,
+    pub bollinger: BollingerConfig,
+    /// Lines of the moving average section and the chart's latest MA readout.
+    pub moving_averages: Vec<MovingAverage>,
+    pub ema: EmaCrossConfig,
+    pub macd: MacdConfig,
+    pub atr_period: usize,
+    /// Reversal in percent that confirms a swing high or low, see `find_swings`.
+    pub swing_threshold_pct: f64,
+    /// Width in percent within which pivots and order book clusters form one support or
+    /// resistance zone, see `find_zones`.
+    pub zone_tolerance_pct: f64,
+    /// Reversal in percent that confirms the swings divergences are looked for between,
+    /// smaller than `swing_threshold_pct` so short intervals have some.
+    pub divergence_swing_pct: f64,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        IndicatorConfig {
+            stoch_rsi: StochRsiConfig::default(),
+            bollinger: BollingerConfig::default(),
+            moving_averages: vec![
+                MovingAverage::sma(7),
+                MovingAverage::sma(25),
+                MovingAverage::sma(99),
+            ],
+            ema: EmaCrossConfig::default(),
+            macd: MacdConfig::default(),
+            atr_period: 14,
+            swing_threshold_pct: 5.0,
+            zone_tolerance_pct: 1.0,
+            divergence_swing_pct: 2.0,
+        }
+    }
+}
+
+fn parse_periods<const N: usize>(key: &str, value: &str) -> Result<[usize; N]> {
+    let periods = value
+        .split(',')
+        .map(|period| {
+            period
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|period| *period > 0)
+                .ok_or_else(|| anyhow!("Invalid {key} period {period:?}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    periods
+        .try_into()
+        .map_err(|_| anyhow!("Expected {N} comma separated periods for {key}, got {value:?}"))
+}
+
+impl IndicatorConfig {
+    /// Applies per-request overrides such as URL query pairs, other keys are ignored:
+    ///
+    /// - `stoch_rsi=14,14,3,3`: RSI period, stochastic period, %K and %D smoothing.
+    /// - `bb=20,2`: SMA period and band width in standard deviations.
+    /// - `ma=sma:7,sma:25,ema:99`: moving average lines.
+    /// - `ema=9,21`: fast and slow EMA.
+    /// - `macd=12,26,9`: fast, slow and signal periods.
+    /// - `atr=14`: ATR period.
+    /// - `swing=5`: reversal in percent that confirms a swing, for the Fibonacci levels and
+    ///   support/resistance pivots.
+    /// - `zones=1`: width in percent of a support/resistance zone.
+    /// - `divergence=2`: reversal in percent that confirms a swing, for divergences.
+    ///
+    /// Fails on a malformed or inconsistent value, e.g. a fast period that isn't shorter than the slow one.
+    pub fn with_overrides<K, V>(mut self, pairs: impl IntoIterator<Item = (K, V)>) -> Result<Self>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (key, value) in pairs {
+            let (key, value) = (key.as_ref(), value.as_ref());
+            match key {
+                "stoch_rsi" => {
+                    let [rsi_period, stoch_period, smooth_k, smooth_d] = parse_periods(key, value)?;
+                    self.stoch_rsi = StochRsiConfig {
+                        rsi_period,
+                        stoch_period,
+                        smooth_k,
+                        smooth_d,
+                    };
+                }
+                "bb" => {
+                    let (period, std_dev) = value.split_once(',').with_context(|| {
+                        format!("Expected period,std_dev for bb, got {value:?}")
+                    })?;
+                    let [period] = parse_periods(key, period)?;
+                    let std_dev = std_dev
+                        .trim()
+                        .parse::<f64>()
+                        .with_context(|| format!("Invalid bb std_dev {std_dev:?}"))?;
+                    self.bollinger = BollingerConfig { period, std_dev };
+                }
+                "ma" => {
+                    self.moving_averages = value
+                        .split(',')
+                        .map(str::parse::<MovingAverage>)
+                        .collect::<Result<Vec<_>>>()?;
+                }
+                "ema" => {
+                    let [fast, slow] = parse_periods(key, value)?;
+                    self.ema = EmaCrossConfig { fast, slow };
+                }
+                "macd" => {
+                    let [fast, slow, signal] = parse_periods(key, value)?;
+                    self.macd = MacdConfig { fast, slow, signal };
+                }
+                "atr" => {
+                    let [period] = parse_periods(key, value)?;
+                    self.atr_period = period;
+                }
+                "swing" => {
+                    self.swing_threshold_pct = value
+                        .trim()
+                        .parse::<f64>()
+                        .with_context(|| format!("Invalid swing threshold {value:?}"))?;
+                }
+                "zones" => {
+                    self.zone_tolerance_pct = value
+                        .trim()
+                        .parse::<f64>()
+                        .with_context(|| format!("Invalid zone width {value:?}"))?;
+                }
+                "divergence" => {
+                    self.divergence_swing_pct = value
+                        .trim()
+                        .parse::<f64>()
+                        .with_context(|| format!("Invalid divergence swing threshold {value:?}"))?;
+                }
+                _ => {}
+            }
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let StochRsiConfig {
+            rsi_period,
+            stoch_period,
+            smooth_k,
+            smooth_d,
+        } = self.stoch_rsi;
+        if [rsi_period, stoch_period, smooth_k, smooth_d].contains(&0) {
+            bail!("Stoch RSI periods must be positive")
+        }
+        if self.bollinger.period < 2 {
+            bail!("Bollinger Band period must be at least 2")
+        }
+        if !(self.bollinger.std_dev.is_finite() && self.bollinger.std_dev > 0.0) {
+            bail!("Bollinger Band width must be a positive number of standard deviations")
+        }
+        if self.moving_averages.is_empty() {
+            bail!("At least one moving average is required")
+        }
+        if self.moving_averages.iter().any(|line| line.period == 0) {
+            bail!("Moving average periods must be positive")
+        }
+        if self.ema.fast == 0 || self.ema.fast >= self.ema.slow {
+            bail!(
+                "EMA fast period {} must be positive and shorter than slow period {}",
+                self.ema.fast,
+                self.ema.slow
+            )
+        }
+        if self.macd.fast == 0 || self.macd.fast >= self.macd.slow || self.macd.signal == 0 {
+            bail!(
+                "MACD fast period {} must be positive and shorter than slow period {}, with a positive signal period",
+                self.macd.fast,
+                self.macd.slow
+            )
+        }
+        if self.atr_period == 0 {
+            bail!("ATR period must be positive")
+        }
+        if !(self.swing_threshold_pct.is_finite() && self.swing_threshold_pct > 0.0) {
+            bail!("Swing threshold must be a positive percentage")
+        }
+        if !(self.zone_tolerance_pct.is_finite() && self.zone_tolerance_pct > 0.0) {
+            bail!("Support/resistance zone width must be a positive percentage")
+        }
+        if !(self.divergence_swing_pct.is_finite() && self.divergence_swing_pct > 0.0) {
+            bail!("Divergence swing threshold must be a positive percentage")
+        }
+        Ok(())
+    }
+
+    /// Candles needed before a Bollinger Band reading.
+    pub fn bollinger_warm_up(&self) -> usize {
+        self.bollinger.period.saturating_sub(1)
+    }
+
+    /// Candles needed before every moving average line has a reading.
+    pub fn moving_average_warm_up(&self) -> usize {
+        self.moving_averages
+            .iter()
+            .map(|line| line.period)
+            .max()
+            .unwrap_or(1)
+            .saturating_sub(1)
+    }
+
+    /// Candles needed before the slow EMA has a reading.
+    pub fn ema_warm_up(&self) -> usize {
+        self.ema.slow.saturating_sub(1)
+    }
+
+    /// Candles needed before the MACD signal line has a reading.
+    pub fn macd_warm_up(&self) -> usize {
+        (self.macd.slow + self.macd.signal).saturating_sub(2)
+    }
+
+    /// Candles needed before the first ATR reading.
+    pub fn atr_warm_up(&self) -> usize {
+        self.atr_period
+    }
+
+    /// Candles needed before Stoch RSI and the MACD histogram can both be calculated.
+    pub fn divergence_warm_up(&self) -> usize {
+        let StochRsiConfig {
+            rsi_period,
+            stoch_period,
+            smooth_k,
+            smooth_d,
+        } = self.stoch_rsi;
+        (rsi_period + stoch_period + smooth_k + smooth_d).max(self.macd.slow + self.macd.signal)
+    }
+}
+
+/// The same parameters `with_overrides` reads, e.g. "stoch_rsi=14,14,3,3 bb=20,2 ...".
+impl fmt::Display for IndicatorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let StochRsiConfig {
+            rsi_period,
+            stoch_period,
+            smooth_k,
+            smooth_d,
+        } = self.stoch_rsi;
+        let lines: Vec<String> = self
+            .moving_averages
+            .iter()
+            .map(MovingAverage::to_string)
+            .collect();
+        write!(
+            f,
+            "stoch_rsi={rsi_period},{stoch_period},{smooth_k},{smooth_d} bb={},{} ma={} ema={},{} macd={},{},{} atr={} swing={} zones={} divergence={}",
+            self.bollinger.period,
+            self.bollinger.std_dev,
+            lines.join(","),
+            self.ema.fast,
+            self.ema.slow,
+            self.macd.fast,
+            self.macd.slow,
+            self.macd.signal,
+            self.atr_period,
+            self.swing_threshold_pct,
+            self.zone_tolerance_pct,
+            self.divergence_swing_pct
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indicator_overrides() {
+        let config = IndicatorConfig::default()
+            .with_overrides([
+                ("macd", "8,21,5"),
+                ("bb", "20, 2.5"),
+                ("ma", "ema:50,sma:200"),
+                ("interval", "1h"),
+            ])
+            .unwrap();
+        assert_eq!(
+            config.macd,
+            MacdConfig {
+                fast: 8,
+                slow: 21,
+                signal: 5
+            }
+        );
+        assert_eq!(config.bollinger.std_dev, 2.5);
+        assert_eq!(
+            config.moving_averages,
+            [MovingAverage::ema(50), MovingAverage::sma(200)]
+        );
+        // Untouched settings keep their defaults.
+        assert_eq!(config.stoch_rsi, StochRsiConfig::default());
+        assert_eq!(config.macd_warm_up(), 24);
+        assert_eq!(config.moving_average_warm_up(), 199);
+        assert_eq!(config.divergence_warm_up(), 34);
+        assert_eq!(
+            config.to_string(),
+            "stoch_rsi=14,14,3,3 bb=20,2.5 ma=ema:50,sma:200 ema=9,21 macd=8,21,5 atr=14 swing=5 zones=1 divergence=2"
+        );
+
+        // The displayed form reads back to the same config.
+        let pairs: Vec<(String, String)> = config
+            .to_string()
+            .split(' ')
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap();
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+        assert_eq!(
+            IndicatorConfig::default().with_overrides(pairs).unwrap(),
+            config
+        );
+
+        for bad in [
+            ("macd", "26,12,9"),
+            ("macd", "12,26"),
+            ("ema", "9,x"),
+            ("bb", "20"),
+            ("bb", "20,0"),
+            ("stoch_rsi", "14,0,3,3"),
+            ("ma", ""),
+            ("atr", "0"),
+            ("swing", "-1"),
+            ("zones", "0"),
+            ("divergence", "x"),
+        ] {
+            assert!(
+                IndicatorConfig::default().with_overrides([bad]).is_err(),
+                "{bad:?} should be refused"
+            );
+        }
+    }
+}
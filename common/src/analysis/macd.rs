@@ -0,0 +1,101 @@
+use anyhow::{bail, Result};
+use m4rs::{macd, Candlestick};
+use serde::{Deserialize, Serialize};
+
+use super::{ema::Cross, m4rs::candle_to_m4rs_candlestick};
+use crate::Candle;
+
+/// MACD line, signal line and histogram at one candle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MacdPoint {
+    pub at: u64,
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+    /// Set on the candle where the MACD line crossed its signal line.
+    pub cross: Option<Cross>,
+}
+
+/// MACD of the closes, from the first candle the signal line is defined on.
+pub fn calculate_macd(
+    candles: &[Candle],
+    fast: usize,
+    slow: usize,
+    signal: usize,
+) -> Result<Vec<MacdPoint>> {
+    if fast >= slow {
+        bail!("MACD fast period {fast} must be shorter than slow period {slow}")
+    }
+    if candles.len() < slow + signal {
+        bail!("Insufficient data for MACD({fast},{slow},{signal}) calculation")
+    }
+
+    let m4rs_candlesticks: Vec<Candlestick> =
+        candles.iter().map(candle_to_m4rs_candlestick).collect();
+    let mut points: Vec<MacdPoint> = Vec::new();
+    for entry in macd(&m4rs_candlesticks, fast, slow, signal)? {
+        let cross = points
+            .last()
+            .and_then(|previous| Cross::between(previous.histogram, entry.histogram));
+        points.push(MacdPoint {
+            at: entry.at,
+            macd: entry.macd,
+            signal: entry.signal,
+            histogram: entry.histogram,
+            cross,
+        });
+    }
+    Ok(points)
+}
+
+pub fn parse_macd_csv(points: &[MacdPoint]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("at,macd,signal,histogram,cross\n");
+
+    for point in points {
+        csv_string.push_str(&format!(
+            "{},{:.4},{:.4},{:.4},{}\n",
+            point.at,
+            point.macd,
+            point.signal,
+            point.histogram,
+            point
+                .cross
+                .map(|cross| cross.to_string())
+                .unwrap_or_default()
+        ));
+    }
+    csv_string
+}
+
+pub fn get_macd_csv(candles: &[Candle], fast: usize, slow: usize, signal: usize) -> Result<String> {
+    let points = calculate_macd(candles, fast, slow, signal)?;
+    Ok(parse_macd_csv(&points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    #[test]
+    fn test_macd() {
+        // A steady decline, then a rally strong enough to turn momentum.
+        let closes: Vec<f64> = (0..50)
+            .map(|i| 200.0 - i as f64)
+            .chain((0..20).map(|i| 150.0 + 3.0 * i as f64))
+            .collect();
+        let points = calculate_macd(&candles(&closes), 12, 26, 9).unwrap();
+        assert!(!points.is_empty());
+        assert_eq!(points.last().unwrap().at, 69 * 60_000);
+        assert!(points
+            .iter()
+            .all(|point| (point.macd - point.signal - point.histogram).abs() < 1e-9));
+
+        let crosses: Vec<_> = points.iter().filter_map(|point| point.cross).collect();
+        assert_eq!(crosses, [Cross::Up]);
+        assert!(parse_macd_csv(&points).starts_with("at,macd,signal,histogram,cross\n"));
+
+        assert!(calculate_macd(&candles(&closes[..30]), 12, 26, 9).is_err());
+    }
+}
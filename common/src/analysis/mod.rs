@@ -1,2 +1,16 @@
+pub mod atr;
+pub mod bollinger;
+pub mod derivatives;
+pub mod divergence;
+pub mod ema;
+pub mod fibonacci;
+pub mod indicator_config;
 pub mod m4rs;
+pub mod macd;
+pub mod moving_average;
+pub mod order_flow;
+pub mod patterns;
 pub mod rsi;
+pub mod support_resistance;
+pub mod swings;
+pub mod taker_pressure;
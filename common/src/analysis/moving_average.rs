@@ -0,0 +1,196 @@
+use anyhow::{anyhow, bail, Result};
+use m4rs::{ema, sma, Candlestick, IndexEntry};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use strum::{Display, EnumString};
+
+use super::m4rs::candle_to_m4rs_candlestick;
+use crate::Candle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[serde(rename_all = "lowercase")]
+pub enum MaKind {
+    Sma,
+    Ema,
+}
+
+/// One moving average line of the closes, e.g. SMA(25).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MovingAverage {
+    pub kind: MaKind,
+    pub period: usize,
+}
+
+impl MovingAverage {
+    pub const fn sma(period: usize) -> Self {
+        MovingAverage {
+            kind: MaKind::Sma,
+            period,
+        }
+    }
+
+    pub const fn ema(period: usize) -> Self {
+        MovingAverage {
+            kind: MaKind::Ema,
+            period,
+        }
+    }
+
+    /// CSV column of this line, e.g. "sma_25".
+    pub fn column_name(&self) -> String {
+        format!("{}_{}", self.kind, self.period)
+    }
+}
+
+/// Parses "sma:25" or "ema:9".
+impl FromStr for MovingAverage {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let (kind, period) = spec
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid moving average {spec:?}, expected e.g. sma:25"))?;
+        let kind = kind
+            .parse::<MaKind>()
+            .map_err(|_| anyhow!("Unsupported moving average {kind:?}, expected sma or ema"))?;
+        let period = period
+            .parse::<usize>()
+            .ok()
+            .filter(|period| *period > 0)
+            .ok_or_else(|| anyhow!("Invalid moving average period {period:?} in {spec:?}"))?;
+        Ok(MovingAverage { kind, period })
+    }
+}
+
+impl fmt::Display for MovingAverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.kind, self.period)
+    }
+}
+
+/// The close and every requested line at one candle, `None` where a line isn't defined yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaPoint {
+    pub at: u64,
+    pub close: f64,
+    /// In the order the lines were requested.
+    pub values: Vec<Option<f64>>,
+}
+
+/// Moving averages of the closes, from the first candle the shortest line is defined on.
+pub fn calculate_moving_averages(
+    candles: &[Candle],
+    lines: &[MovingAverage],
+) -> Result<Vec<MaPoint>> {
+    let Some(shortest) = lines.iter().map(|line| line.period).min() else {
+        bail!("No moving averages requested")
+    };
+    if shortest == 0 {
+        bail!("Moving average period must be positive")
+    }
+    if candles.len() < shortest {
+        bail!("Insufficient data for moving average({shortest}) calculation")
+    }
+
+    let m4rs_candlesticks: Vec<Candlestick> =
+        candles.iter().map(candle_to_m4rs_candlestick).collect();
+    let line_values = lines
+        .iter()
+        .map(|line| match line.kind {
+            MaKind::Sma => sma(&m4rs_candlesticks, line.period),
+            MaKind::Ema => ema(&m4rs_candlesticks, line.period),
+        })
+        .collect::<Result<Vec<Vec<IndexEntry>>>>()?;
+
+    // Every line ends on the latest candle, longer ones start later.
+    Ok((shortest - 1..candles.len())
+        .map(|i| {
+            let values = line_values
+                .iter()
+                .map(|entries| {
+                    let offset = candles.len() - entries.len();
+                    i.checked_sub(offset).map(|j| entries[j].value)
+                })
+                .collect();
+            MaPoint {
+                at: candles[i].open_time as u64,
+                close: candles[i].close,
+                values,
+            }
+        })
+        .collect())
+}
+
+pub fn parse_ma_csv(points: &[MaPoint], lines: &[MovingAverage]) -> String {
+    let mut csv_string = String::new();
+    let columns: Vec<String> = lines.iter().map(MovingAverage::column_name).collect();
+    csv_string.push_str(&format!("at,close,{}\n", columns.join(",")));
+
+    for point in points {
+        let cells: Vec<String> = point
+            .values
+            .iter()
+            .map(|value| value.map(|value| format!("{value:.4}")).unwrap_or_default())
+            .collect();
+        csv_string.push_str(&format!(
+            "{},{:.4},{}\n",
+            point.at,
+            point.close,
+            cells.join(",")
+        ));
+    }
+    csv_string
+}
+
+pub fn get_ma_csv(candles: &[Candle], lines: &[MovingAverage]) -> Result<String> {
+    let points = calculate_moving_averages(candles, lines)?;
+    Ok(parse_ma_csv(&points, lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    #[test]
+    fn test_moving_averages() {
+        let closes: Vec<f64> = (1..=10).map(f64::from).collect();
+        let lines = [MovingAverage::sma(3), MovingAverage::ema(5)];
+        let points = calculate_moving_averages(&candles(&closes), &lines).unwrap();
+        assert_eq!(points.len(), 10 - 2);
+        assert_eq!(points[0].at, 2 * 60_000);
+        assert_eq!(points[0].values[0], Some(2.0));
+        assert_eq!(points[2].values[0], Some(4.0));
+        assert!(points[2].values[1].is_some());
+        // Both lag a rising close.
+        let latest = points.last().unwrap();
+        assert_eq!(latest.values[0], Some(9.0));
+        assert!(latest.values[1].unwrap() < latest.close);
+
+        let csv = parse_ma_csv(&points, &lines);
+        assert!(csv.starts_with("at,close,sma_3,ema_5\n120000,3.0000,2.0000,"));
+        assert!(csv.contains("\n540000,10.0000,9.0000,"));
+
+        assert!(calculate_moving_averages(&candles(&closes[..2]), &lines).is_err());
+        assert!(calculate_moving_averages(&candles(&closes), &[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_moving_average() {
+        assert_eq!(
+            "sma:25".parse::<MovingAverage>().unwrap(),
+            MovingAverage::sma(25)
+        );
+        assert_eq!(
+            " EMA:9".parse::<MovingAverage>().unwrap(),
+            MovingAverage::ema(9)
+        );
+        assert_eq!(MovingAverage::ema(9).to_string(), "ema:9");
+        assert_eq!(MovingAverage::sma(99).column_name(), "sma_99");
+        assert!("wma:10".parse::<MovingAverage>().is_err());
+        assert!("sma:0".parse::<MovingAverage>().is_err());
+        assert!("sma".parse::<MovingAverage>().is_err());
+    }
+}
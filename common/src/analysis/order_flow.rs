@@ -0,0 +1,241 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{AggTrade, Interval};
+
+// Trades this many times the median notional count as large prints by default.
+const LARGE_TRADE_MEDIAN_MULTIPLE: f64 = 10.0;
+
+/// An aggregate trade with its price and quantity parsed, sided by the taker.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub time: i64,
+    pub price: f64,
+    pub quantity: f64,
+    /// A market buy, the buyer was the taker.
+    pub is_buy: bool,
+}
+
+impl Trade {
+    /// Quote value of the trade, e.g. USDT.
+    pub fn notional(&self) -> f64 {
+        self.price * self.quantity
+    }
+
+    pub fn from_agg_trades(trades: &[AggTrade]) -> Result<Vec<Trade>> {
+        trades.iter().map(Trade::try_from).collect()
+    }
+}
+
+impl TryFrom<&AggTrade> for Trade {
+    type Error = anyhow::Error;
+
+    fn try_from(trade: &AggTrade) -> Result<Self> {
+        let parse = |field: &str, value: &str| {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && *value >= 0.0)
+                .with_context(|| format!("Invalid {field} {value:?} in trade {}", trade.id))
+        };
+        Ok(Trade {
+            time: trade.time,
+            price: parse("price", &trade.price)?,
+            quantity: parse("quantity", &trade.quantity)?,
+            is_buy: !trade.is_buyer_maker,
+        })
+    }
+}
+
+/// Taker buy and sell volume within one candle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OrderFlowBar {
+    pub open_time: i64,
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+    /// `buy_volume - sell_volume`.
+    pub delta: f64,
+    /// Cumulative volume delta since the first bar.
+    pub cvd: f64,
+    pub trades: usize,
+    /// Volume of the large prints, see `large_trade_threshold`.
+    pub large_buy_volume: f64,
+    pub large_sell_volume: f64,
+}
+
+/// Buckets `trades` into `interval` candles, oldest first. Candles without trades are skipped
+/// and the cumulative volume delta starts at the first bar.
+///
+/// Trades with a notional of at least `large_trade_notional` also count as large prints.
+pub fn order_flow(
+    trades: &[Trade],
+    interval: Interval,
+    large_trade_notional: f64,
+) -> Vec<OrderFlowBar> {
+    let mut buckets: BTreeMap<i64, Vec<&Trade>> = BTreeMap::new();
+    for trade in trades {
+        let open_time = interval.align(trade.time.div_euclid(1000)) * 1000;
+        buckets.entry(open_time).or_default().push(trade);
+    }
+
+    let mut cvd = 0.0;
+    buckets
+        .into_iter()
+        .map(|(open_time, trades)| {
+            let mut bar = OrderFlowBar {
+                open_time,
+                buy_volume: 0.0,
+                sell_volume: 0.0,
+                delta: 0.0,
+                cvd: 0.0,
+                trades: trades.len(),
+                large_buy_volume: 0.0,
+                large_sell_volume: 0.0,
+            };
+            for trade in trades {
+                let is_large = trade.notional() >= large_trade_notional;
+                match (trade.is_buy, is_large) {
+                    (true, true) => {
+                        bar.buy_volume += trade.quantity;
+                        bar.large_buy_volume += trade.quantity;
+                    }
+                    (true, false) => bar.buy_volume += trade.quantity,
+                    (false, true) => {
+                        bar.sell_volume += trade.quantity;
+                        bar.large_sell_volume += trade.quantity;
+                    }
+                    (false, false) => bar.sell_volume += trade.quantity,
+                }
+            }
+            bar.delta = bar.buy_volume - bar.sell_volume;
+            cvd += bar.delta;
+            bar.cvd = cvd;
+            bar
+        })
+        .collect()
+}
+
+/// Ten times the median notional of `trades`, infinite when there are none.
+pub fn large_trade_threshold(trades: &[Trade]) -> f64 {
+    let mut notionals: Vec<f64> = trades.iter().map(Trade::notional).collect();
+    if notionals.is_empty() {
+        return f64::INFINITY;
+    }
+    notionals.sort_by(f64::total_cmp);
+    notionals[notionals.len() / 2] * LARGE_TRADE_MEDIAN_MULTIPLE
+}
+
+/// Trades with a notional of at least `min_notional`, oldest first.
+pub fn large_trades(trades: &[Trade], min_notional: f64) -> Vec<Trade> {
+    trades
+        .iter()
+        .filter(|trade| trade.notional() >= min_notional)
+        .copied()
+        .collect()
+}
+
+pub fn order_flow_to_csv(bars: &[OrderFlowBar]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str(
+        "open_time,buy_volume,sell_volume,delta,cvd,trades,large_buy_volume,large_sell_volume\n",
+    );
+
+    for bar in bars {
+        csv_string.push_str(&format!(
+            "{},{:.4},{:.4},{:.4},{:.4},{},{:.4},{:.4}\n",
+            bar.open_time,
+            bar.buy_volume,
+            bar.sell_volume,
+            bar.delta,
+            bar.cvd,
+            bar.trades,
+            bar.large_buy_volume,
+            bar.large_sell_volume
+        ));
+    }
+    csv_string
+}
+
+pub fn large_trades_to_csv(trades: &[Trade]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("time,side,price,quantity,notional\n");
+
+    for trade in trades {
+        csv_string.push_str(&format!(
+            "{},{},{},{},{:.2}\n",
+            trade.time,
+            if trade.is_buy { "buy" } else { "sell" },
+            trade.price,
+            trade.quantity,
+            trade.notional()
+        ));
+    }
+    csv_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3_600_000;
+    // 2025-04-30T12:00:00Z
+    const START: i64 = 1_746_014_400_000;
+
+    fn trade(time: i64, price: f64, quantity: f64, is_buy: bool) -> Trade {
+        Trade {
+            time,
+            price,
+            quantity,
+            is_buy,
+        }
+    }
+
+    #[test]
+    fn test_order_flow_bars() {
+        let trades = vec![
+            trade(START + 1_000, 100.0, 2.0, true),
+            trade(START + 2_000, 100.0, 1.0, false),
+            trade(START + 3_000, 101.0, 50.0, true),
+            // Nothing traded in the second hour.
+            trade(START + 2 * HOUR, 99.0, 4.0, false),
+            trade(START + 2 * HOUR + 5, 99.0, 1.5, true),
+        ];
+
+        let bars = order_flow(&trades, Interval::Hour1, 1000.0);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].open_time, START);
+        assert_eq!(bars[0].buy_volume, 52.0);
+        assert_eq!(bars[0].sell_volume, 1.0);
+        assert_eq!(bars[0].delta, 51.0);
+        assert_eq!(bars[0].trades, 3);
+        assert_eq!(bars[0].large_buy_volume, 50.0);
+        assert_eq!(bars[1].open_time, START + 2 * HOUR);
+        assert_eq!(bars[1].delta, -2.5);
+        assert_eq!(bars[1].cvd, 48.5);
+
+        let csv = order_flow_to_csv(&bars);
+        assert!(csv.ends_with("1746021600000,1.5000,4.0000,-2.5000,48.5000,2,0.0000,0.0000\n"));
+
+        let large = large_trades(&trades, large_trade_threshold(&trades));
+        assert_eq!(large, vec![trades[2]]);
+        assert_eq!(large_trade_threshold(&[]), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_trade_from_agg_trade() {
+        let agg_trade: AggTrade = serde_json::from_str(
+            r#"{"a":26129,"p":"146.51","q":"4.7","f":27781,"l":27783,"T":1746014401000,"m":true,"M":true}"#,
+        )
+        .unwrap();
+        let trade = Trade::try_from(&agg_trade).unwrap();
+        assert_eq!(trade.price, 146.51);
+        assert!(!trade.is_buy);
+
+        let broken = AggTrade {
+            quantity: "abc".to_string(),
+            ..agg_trade
+        };
+        assert!(Trade::from_agg_trades(&[broken]).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::{Candle, CandleError, Kline};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    BullishEngulfing,
+    BearishEngulfing,
+    Hammer,
+    ShootingStar,
+    Doji,
+    MorningStar,
+    EveningStar,
+    InsideBar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PatternDirection {
+    Bullish,
+    Bearish,
+    /// Indecision or consolidation, e.g. a doji or an inside bar.
+    Neutral,
+}
+
+impl PatternKind {
+    pub fn direction(&self) -> PatternDirection {
+        match self {
+            PatternKind::BullishEngulfing | PatternKind::Hammer | PatternKind::MorningStar => {
+                PatternDirection::Bullish
+            }
+            PatternKind::BearishEngulfing
+            | PatternKind::ShootingStar
+            | PatternKind::EveningStar => PatternDirection::Bearish,
+            PatternKind::Doji | PatternKind::InsideBar => PatternDirection::Neutral,
+        }
+    }
+
+    /// How many candles the pattern spans, ending on the candle it's reported at.
+    pub fn candles(&self) -> usize {
+        match self {
+            PatternKind::Hammer | PatternKind::ShootingStar | PatternKind::Doji => 1,
+            PatternKind::BullishEngulfing
+            | PatternKind::BearishEngulfing
+            | PatternKind::InsideBar => 2,
+            PatternKind::MorningStar | PatternKind::EveningStar => 3,
+        }
+    }
+}
+
+/// A pattern completed by the candle opened at `open_time`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PatternEvent {
+    pub open_time: i64,
+    pub kind: PatternKind,
+    pub direction: PatternDirection,
+    /// Close of the completing candle.
+    pub close: f64,
+}
+
+// A body at most this share of the range is a doji.
+const DOJI_BODY_RATIO: f64 = 0.1;
+// Candles before a hammer or shooting star that set the trend it reverses.
+const TREND_CANDLES: usize = 3;
+/// Candles a pattern looks back on before the one completing it.
+pub const PATTERN_LOOKBACK: usize = TREND_CANDLES + 1;
+
+fn body(candle: &Candle) -> f64 {
+    (candle.close - candle.open).abs()
+}
+
+fn range(candle: &Candle) -> f64 {
+    candle.high - candle.low
+}
+
+fn upper_shadow(candle: &Candle) -> f64 {
+    candle.high - candle.open.max(candle.close)
+}
+
+fn lower_shadow(candle: &Candle) -> f64 {
+    candle.open.min(candle.close) - candle.low
+}
+
+fn is_doji(candle: &Candle) -> bool {
+    range(candle) > 0.0 && body(candle) <= range(candle) * DOJI_BODY_RATIO
+}
+
+fn is_bearish(candle: &Candle) -> bool {
+    candle.close < candle.open
+}
+
+// Whether the closes fell (or rose) over the `TREND_CANDLES` before `i`.
+fn trend_before(candles: &[Candle], i: usize) -> Option<PatternDirection> {
+    let start = i.checked_sub(TREND_CANDLES + 1)?;
+    let (first, last) = (candles[start].close, candles[i - 1].close);
+    if last < first {
+        Some(PatternDirection::Bearish)
+    } else if last > first {
+        Some(PatternDirection::Bullish)
+    } else {
+        None
+    }
+}
+
+fn patterns_at(candles: &[Candle], i: usize) -> Vec<PatternKind> {
+    let current = &candles[i];
+    let mut kinds = Vec::new();
+
+    if is_doji(current) {
+        kinds.push(PatternKind::Doji);
+    } else {
+        // A long shadow twice the body on one side and little on the other, against the trend.
+        let (upper, lower, body) = (upper_shadow(current), lower_shadow(current), body(current));
+        match trend_before(candles, i) {
+            Some(PatternDirection::Bearish) if lower >= 2.0 * body && upper <= body => {
+                kinds.push(PatternKind::Hammer)
+            }
+            Some(PatternDirection::Bullish) if upper >= 2.0 * body && lower <= body => {
+                kinds.push(PatternKind::ShootingStar)
+            }
+            _ => {}
+        }
+    }
+
+    let Some(previous) = i.checked_sub(1).map(|j| &candles[j]) else {
+        return kinds;
+    };
+    if is_bearish(previous)
+        && current.is_bullish()
+        && current.open <= previous.close
+        && current.close >= previous.open
+        && body(current) > body(previous)
+    {
+        kinds.push(PatternKind::BullishEngulfing);
+    }
+    if previous.is_bullish()
+        && is_bearish(current)
+        && current.open >= previous.close
+        && current.close <= previous.open
+        && body(current) > body(previous)
+    {
+        kinds.push(PatternKind::BearishEngulfing);
+    }
+    if current.high < previous.high && current.low > previous.low {
+        kinds.push(PatternKind::InsideBar);
+    }
+
+    // A strong candle, a small one, then a strong one back past the middle of the first.
+    if let Some(first) = i.checked_sub(2).map(|j| &candles[j]) {
+        let star = previous;
+        let is_strong = |candle: &Candle| body(candle) >= range(candle) * 0.5;
+        let first_middle = (first.open + first.close) / 2.0;
+        if is_strong(first) && body(star) <= body(first) * 0.3 && is_strong(current) {
+            if is_bearish(first) && current.is_bullish() && current.close > first_middle {
+                kinds.push(PatternKind::MorningStar);
+            }
+            if first.is_bullish() && is_bearish(current) && current.close < first_middle {
+                kinds.push(PatternKind::EveningStar);
+            }
+        }
+    }
+    kinds
+}
+
+/// Every pattern completed within `candles`, oldest first. Hammers and shooting stars
+/// need the three candles before them to have fallen or risen.
+pub fn find_patterns(candles: &[Candle]) -> Vec<PatternEvent> {
+    (0..candles.len())
+        .flat_map(|i| {
+            let candle = candles[i];
+            patterns_at(candles, i)
+                .into_iter()
+                .map(move |kind| PatternEvent {
+                    open_time: candle.open_time,
+                    kind,
+                    direction: kind.direction(),
+                    close: candle.close,
+                })
+        })
+        .collect()
+}
+
+/// `find_patterns` on raw klines, failing on the first malformed one.
+pub fn find_kline_patterns(klines: &[Kline]) -> Result<Vec<PatternEvent>, CandleError> {
+    Ok(find_patterns(&Candle::from_klines(klines)?))
+}
+
+pub fn patterns_to_csv(events: &[PatternEvent]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("open_time,pattern,direction,close\n");
+
+    for event in events {
+        csv_string.push_str(&format!(
+            "{},{},{},{:.4}\n",
+            event.open_time, event.kind, event.direction, event.close
+        ));
+    }
+    csv_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(i: i64, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            open_time: i * 60_000,
+            open,
+            high,
+            low,
+            close,
+            volume: 1.0,
+            close_time: i * 60_000 + 59_999,
+        }
+    }
+
+    fn kinds(candles: &[Candle]) -> Vec<(i64, PatternKind)> {
+        find_patterns(candles)
+            .iter()
+            .map(|event| (event.open_time / 60_000, event.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_and_two_candle_patterns() {
+        // Falling candles, a hammer, an inside bar, then a bullish engulfing of the inside bar.
+        let candles = [
+            candle(0, 110.0, 111.0, 107.0, 108.0),
+            candle(1, 108.0, 109.0, 105.0, 106.0),
+            candle(2, 106.0, 107.0, 103.0, 104.0),
+            candle(3, 104.0, 104.0, 102.0, 103.0),
+            candle(4, 103.0, 104.5, 97.0, 104.0),
+            candle(5, 104.0, 104.2, 102.8, 103.0),
+            candle(6, 102.9, 106.0, 102.5, 105.5),
+        ];
+        assert_eq!(
+            kinds(&candles),
+            [
+                (4, PatternKind::Hammer),
+                (5, PatternKind::InsideBar),
+                (6, PatternKind::BullishEngulfing),
+            ]
+        );
+
+        // After a rise a long upper shadow is a shooting star.
+        let rising = [
+            candle(0, 100.0, 102.0, 99.0, 101.0),
+            candle(1, 101.0, 103.0, 100.0, 102.0),
+            candle(2, 102.0, 104.0, 101.0, 103.0),
+            candle(3, 103.0, 105.0, 102.0, 104.0),
+            candle(4, 104.0, 110.0, 103.8, 105.0),
+            candle(5, 104.5, 104.7, 103.5, 104.52),
+        ];
+        assert_eq!(
+            kinds(&rising),
+            [(4, PatternKind::ShootingStar), (5, PatternKind::Doji)]
+        );
+        let event = find_patterns(&rising)[1];
+        assert_eq!(event.direction, PatternDirection::Neutral);
+        assert_eq!(
+            patterns_to_csv(&[event]),
+            "open_time,pattern,direction,close\n300000,doji,neutral,104.5200\n"
+        );
+    }
+
+    #[test]
+    fn test_star_patterns() {
+        let morning = [
+            candle(0, 110.0, 110.5, 99.5, 100.0),
+            candle(1, 99.0, 100.0, 98.0, 99.5),
+            candle(2, 100.0, 108.5, 99.8, 108.0),
+        ];
+        assert!(kinds(&morning).contains(&(2, PatternKind::MorningStar)));
+
+        let evening = [
+            candle(0, 100.0, 110.5, 99.5, 110.0),
+            candle(1, 111.0, 112.0, 110.0, 110.5),
+            candle(2, 110.0, 110.2, 101.5, 102.0),
+        ];
+        let found = find_patterns(&evening);
+        let star = found
+            .iter()
+            .find(|event| event.kind == PatternKind::EveningStar)
+            .unwrap();
+        assert_eq!(star.open_time, 2 * 60_000);
+        assert_eq!(star.direction, PatternDirection::Bearish);
+        assert_eq!(star.kind.candles(), 3);
+    }
+}
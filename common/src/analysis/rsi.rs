@@ -1,26 +1,16 @@
-use super::m4rs::kline_to_m4rs_candlestick;
-use crate::Kline;
+use super::indicator_config::{BollingerConfig, IndicatorConfig, StochRsiConfig};
+use super::m4rs::candle_to_m4rs_candlestick;
+use super::moving_average::{calculate_moving_averages, MaKind};
+use crate::Candle;
 use anyhow::bail;
 use m4rs::{bolinger_band, Candlestick};
 
-pub fn calculate_stoch_rsi(
-    candles: &[Candlestick],
-    rsi_period: usize,
-    stoch_period: usize,
-    smooth_k: usize,
-    smooth_d: usize,
-) -> anyhow::Result<(Vec<u64>, Vec<f64>, Vec<f64>)> {
-    // Step 1: Extract closing prices from M4rsCandlestick
-    let closing_prices: Vec<f64> = candles.iter().map(|c| c.close).collect();
-    let closing_at: Vec<u64> = candles.iter().map(|c| c.at).collect();
-
-    // Ensure there are enough candles for calculation
-    if closing_prices.len() < rsi_period + stoch_period + smooth_k + smooth_d {
-        bail!("Insufficient data for Stoch RSI calculation")
-    }
-
-    // Step 2: Calculate RSI (14 periods)
+/// Wilder's RSI of `closing_prices`, one value per price, 0 before the first `rsi_period`.
+pub fn calculate_rsi(closing_prices: &[f64], rsi_period: usize) -> Vec<f64> {
     let mut rsi = vec![0.0; closing_prices.len()];
+    if rsi_period == 0 || closing_prices.len() < rsi_period {
+        return rsi;
+    }
     let mut avg_gain = 0.0;
     let mut avg_loss = 0.0;
 
@@ -53,6 +43,28 @@ pub fn calculate_stoch_rsi(
         rsi[i] = 100.0 - (100.0 / (1.0 + rs));
     }
 
+    rsi
+}
+
+pub fn calculate_stoch_rsi(
+    candles: &[Candlestick],
+    rsi_period: usize,
+    stoch_period: usize,
+    smooth_k: usize,
+    smooth_d: usize,
+) -> anyhow::Result<(Vec<u64>, Vec<f64>, Vec<f64>)> {
+    // Step 1: Extract closing prices from M4rsCandlestick
+    let closing_prices: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    let closing_at: Vec<u64> = candles.iter().map(|c| c.at).collect();
+
+    // Ensure there are enough candles for calculation
+    if closing_prices.len() < rsi_period + stoch_period + smooth_k + smooth_d {
+        bail!("Insufficient data for Stoch RSI calculation")
+    }
+
+    // Step 2: Calculate RSI (14 periods)
+    let rsi = calculate_rsi(&closing_prices, rsi_period);
+
     // Step 3: Calculate Stochastic RSI (14-period lookback)
     let mut stoch_rsi = vec![0.0; closing_prices.len()];
     for i in stoch_period..closing_prices.len() {
@@ -105,13 +117,18 @@ pub fn parse_stoch_rsi_csv(closing_at: &[u64], smoothed_k: &[f64], d: &[f64]) ->
     csv_string
 }
 
-pub fn get_stoch_rsi_csv(klines: &[Kline]) -> anyhow::Result<String> {
-    let m4rs_candlesticks = klines
+pub fn get_stoch_rsi_csv(candles: &[Candle], config: &StochRsiConfig) -> anyhow::Result<String> {
+    let m4rs_candlesticks = candles
         .iter()
-        .map(kline_to_m4rs_candlestick)
+        .map(candle_to_m4rs_candlestick)
         .collect::<Vec<_>>();
-    let (closing_at, stoch_rsi_k, stoch_rsi_d) =
-        calculate_stoch_rsi(&m4rs_candlesticks, 14, 14, 3, 3)?;
+    let (closing_at, stoch_rsi_k, stoch_rsi_d) = calculate_stoch_rsi(
+        &m4rs_candlesticks,
+        config.rsi_period,
+        config.stoch_period,
+        config.smooth_k,
+        config.smooth_d,
+    )?;
     let csv_string = parse_stoch_rsi_csv(&closing_at, &stoch_rsi_k, &stoch_rsi_d);
     Ok(csv_string)
 }
@@ -127,17 +144,17 @@ pub fn parse_bb_csv(past_bb_lines: &Vec<(u64, f32, f32, f32)>) -> String {
     csv_string
 }
 
-pub fn get_bb_csv(klines: &[Kline]) -> anyhow::Result<String> {
+pub fn get_bb_csv(candles: &[Candle], config: &BollingerConfig) -> anyhow::Result<String> {
     let past_m4rs_candles: Vec<Candlestick> =
-        klines.iter().map(kline_to_m4rs_candlestick).collect();
-    let bb_result = bolinger_band(&past_m4rs_candles, 20)?;
+        candles.iter().map(candle_to_m4rs_candlestick).collect();
+    let bb_result = bolinger_band(&past_m4rs_candles, config.period)?;
     let bb_lines: Vec<(u64, f32, f32, f32)> = bb_result
         .iter()
         .map(|entry| {
             let t = entry.at;
             let avg = entry.avg as f32;
-            let upper = (entry.avg + 2.0 * entry.sigma) as f32;
-            let lower = (entry.avg - 2.0 * entry.sigma) as f32;
+            let upper = (entry.avg + config.std_dev * entry.sigma) as f32;
+            let lower = (entry.avg - config.std_dev * entry.sigma) as f32;
             (t, avg, upper, lower)
         })
         .collect();
@@ -145,35 +162,37 @@ pub fn get_bb_csv(klines: &[Kline]) -> anyhow::Result<String> {
     Ok(csv_string)
 }
 
-pub fn get_latest_bb_ma(klines: &[Kline]) -> anyhow::Result<String> {
+pub fn get_latest_bb_ma(candles: &[Candle], config: &IndicatorConfig) -> anyhow::Result<String> {
     let past_m4rs_candles: Vec<Candlestick> =
-        klines.iter().map(kline_to_m4rs_candlestick).collect();
-    let bb_result = bolinger_band(&past_m4rs_candles, 20)?;
-    let latest_bb = bb_result.last().unwrap();
-    let ma_7 = past_m4rs_candles
-        .iter()
-        .rev()
-        .take(7)
-        .map(|c| c.close)
-        .sum::<f64>()
-        / 7.0;
-    let ma_25 = past_m4rs_candles
-        .iter()
-        .rev()
-        .take(25)
-        .map(|c| c.close)
-        .sum::<f64>()
-        / 25.0;
-    let ma_99 = past_m4rs_candles
+        candles.iter().map(candle_to_m4rs_candlestick).collect();
+    let BollingerConfig { period, std_dev } = config.bollinger;
+    let bb_result = bolinger_band(&past_m4rs_candles, period)?;
+    let Some(latest_bb) = bb_result.last() else {
+        bail!("Insufficient data for Bollinger Band({period}) calculation")
+    };
+    let latest_ma = calculate_moving_averages(candles, &config.moving_averages)?
+        .pop()
+        .map(|point| point.values)
+        .unwrap_or_default();
+
+    let mut lines: Vec<String> = config
+        .moving_averages
         .iter()
-        .rev()
-        .take(99)
-        .map(|c| c.close)
-        .sum::<f64>()
-        / 99.0;
-
-    Ok(format!(
-        "MA 7 close 0 SMA 9 {:.2}\nMA 25 close 0 SMA 9 {:.2}\nMA 99 close 0 SMA 9 {:.2}\nBB 20 2 {:.2} {:.2} {:.2}",
-        ma_7, ma_25, ma_99, latest_bb.avg, latest_bb.avg + 2.0 * latest_bb.sigma, latest_bb.avg - 2.0 * latest_bb.sigma
-    ))
+        .zip(latest_ma)
+        .map(|(line, value)| {
+            let name = match line.kind {
+                MaKind::Sma => "MA",
+                MaKind::Ema => "EMA",
+            };
+            let value = value.map_or("n/a".to_string(), |value| format!("{value:.2}"));
+            format!("{name} {} close 0 SMA 9 {value}", line.period)
+        })
+        .collect();
+    lines.push(format!(
+        "BB {period} {std_dev} {:.2} {:.2} {:.2}",
+        latest_bb.avg,
+        latest_bb.avg + std_dev * latest_bb.sigma,
+        latest_bb.avg - std_dev * latest_bb.sigma
+    ));
+    Ok(lines.join("\n"))
 }
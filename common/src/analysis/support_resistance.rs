@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use super::swings::find_swings;
+use crate::{
+    numbers::{group_by_fractional_part, FractionalPart},
+    Candle, OrderBook,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ZoneKind {
+    Support,
+    Resistance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+/// A swing high or low price touched, with the volume of its candle relative to the
+/// average candle of its interval so pivots of different intervals compare.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pivot {
+    pub open_time: i64,
+    pub price: f64,
+    pub relative_volume: f64,
+}
+
+/// A grouped order book bucket holding an unusually large quantity.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BookCluster {
+    pub price: f64,
+    pub quantity: f64,
+    pub side: BookSide,
+}
+
+/// A price band where pivots and large resting orders meet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SrZone {
+    pub low: f64,
+    pub high: f64,
+    /// Support below the current price, resistance above it.
+    pub kind: ZoneKind,
+    /// Pivots inside the zone.
+    pub touches: usize,
+    /// Sum of the pivots' relative volume.
+    pub relative_volume: f64,
+    /// Quantity of the order book clusters inside the zone.
+    pub book_quantity: f64,
+    /// Open time of the latest pivot, `None` for a zone made of resting orders only.
+    pub last_touch: Option<i64>,
+    /// 0 to 1, see `find_zones`.
+    pub score: f64,
+}
+
+impl SrZone {
+    pub fn mid(&self) -> f64 {
+        (self.low + self.high) / 2.0
+    }
+}
+
+/// Confirmed swing highs and lows of `candles` as pivots, see `find_swings`.
+///
+/// The last swing is left out until price moves away from it, it's no touch yet.
+pub fn pivots_from_candles(candles: &[Candle], threshold_pct: f64) -> Vec<Pivot> {
+    if candles.is_empty() {
+        return Vec::new();
+    }
+    let average_volume =
+        candles.iter().map(|candle| candle.volume).sum::<f64>() / candles.len() as f64;
+
+    find_swings(candles, threshold_pct)
+        .into_iter()
+        .filter(|swing| swing.confirmed)
+        .filter_map(|swing| {
+            let candle = candles
+                .iter()
+                .find(|candle| candle.open_time == swing.open_time)?;
+            let relative_volume = if average_volume > 0.0 {
+                candle.volume / average_volume
+            } else {
+                0.0
+            };
+            Some(Pivot {
+                open_time: swing.open_time,
+                price: swing.price,
+                relative_volume,
+            })
+        })
+        .collect()
+}
+
+/// The `top_n` largest buckets of each side of the book, grouped by `fractional_part`.
+pub fn book_clusters(
+    orderbook: &OrderBook,
+    fractional_part: FractionalPart,
+    top_n: usize,
+) -> Vec<BookCluster> {
+    let (grouped_bids, grouped_asks) = group_by_fractional_part(orderbook, fractional_part);
+
+    let mut clusters = Vec::new();
+    for (grouped, side) in [(grouped_bids, BookSide::Bid), (grouped_asks, BookSide::Ask)] {
+        let mut buckets: Vec<BookCluster> = grouped
+            .iter()
+            .filter_map(|(price, quantity)| {
+                Some(BookCluster {
+                    price: price.parse().ok()?,
+                    quantity: *quantity,
+                    side,
+                })
+            })
+            .collect();
+        buckets.sort_by(|a, b| b.quantity.total_cmp(&a.quantity));
+        clusters.extend(buckets.into_iter().take(top_n));
+    }
+    clusters
+}
+
+// A pivot or a book cluster at one price, before clustering.
+enum Touch {
+    Pivot(Pivot),
+    Book(BookCluster),
+}
+
+impl Touch {
+    fn price(&self) -> f64 {
+        match self {
+            Touch::Pivot(pivot) => pivot.price,
+            Touch::Book(cluster) => cluster.price,
+        }
+    }
+}
+
+/// Clusters pivots (of any number of intervals) and order book clusters whose prices are
+/// within `tolerance_pct` of the lowest price of their zone, then keeps the `max_zones`
+/// best scored zones, highest price first.
+///
+/// The score weighs touches 40%, relative volume 20%, resting quantity 20% and recency 20%,
+/// each against the best zone. Recency runs from 0 at the oldest pivot to 1 at the latest.
+pub fn find_zones(
+    pivots: &[Pivot],
+    clusters: &[BookCluster],
+    current_price: f64,
+    tolerance_pct: f64,
+    max_zones: usize,
+) -> Vec<SrZone> {
+    let mut touches: Vec<Touch> = pivots
+        .iter()
+        .copied()
+        .map(Touch::Pivot)
+        .chain(clusters.iter().copied().map(Touch::Book))
+        .collect();
+    touches.sort_by(|a, b| a.price().total_cmp(&b.price()));
+
+    let mut groups: Vec<Vec<Touch>> = Vec::new();
+    for touch in touches {
+        match groups.last_mut() {
+            Some(group) if touch.price() <= group[0].price() * (1.0 + tolerance_pct / 100.0) => {
+                group.push(touch)
+            }
+            _ => groups.push(vec![touch]),
+        }
+    }
+
+    let mut zones: Vec<SrZone> = groups
+        .iter()
+        .map(|group| {
+            let low = group[0].price();
+            let high = group[group.len() - 1].price();
+            let mut zone = SrZone {
+                low,
+                high,
+                kind: if (low + high) / 2.0 <= current_price {
+                    ZoneKind::Support
+                } else {
+                    ZoneKind::Resistance
+                },
+                touches: 0,
+                relative_volume: 0.0,
+                book_quantity: 0.0,
+                last_touch: None,
+                score: 0.0,
+            };
+            for touch in group {
+                match touch {
+                    Touch::Pivot(pivot) => {
+                        zone.touches += 1;
+                        zone.relative_volume += pivot.relative_volume;
+                        zone.last_touch = zone.last_touch.max(Some(pivot.open_time));
+                    }
+                    Touch::Book(cluster) => zone.book_quantity += cluster.quantity,
+                }
+            }
+            zone
+        })
+        .collect();
+
+    let max_touches = zones.iter().map(|zone| zone.touches).max().unwrap_or(0) as f64;
+    let max_volume = zones
+        .iter()
+        .map(|zone| zone.relative_volume)
+        .fold(0.0, f64::max);
+    let max_quantity = zones
+        .iter()
+        .map(|zone| zone.book_quantity)
+        .fold(0.0, f64::max);
+    let oldest = pivots.iter().map(|pivot| pivot.open_time).min();
+    let latest = pivots.iter().map(|pivot| pivot.open_time).max();
+    let share = |value: f64, max: f64| if max > 0.0 { value / max } else { 0.0 };
+
+    for zone in &mut zones {
+        let recency = match (zone.last_touch, oldest, latest) {
+            (Some(touch), Some(oldest), Some(latest)) if latest > oldest => {
+                (touch - oldest) as f64 / (latest - oldest) as f64
+            }
+            (Some(_), _, _) => 1.0,
+            _ => 0.0,
+        };
+        zone.score = 0.4 * share(zone.touches as f64, max_touches)
+            + 0.2 * share(zone.relative_volume, max_volume)
+            + 0.2 * share(zone.book_quantity, max_quantity)
+            + 0.2 * recency;
+    }
+
+    zones.sort_by(|a, b| b.score.total_cmp(&a.score));
+    zones.truncate(max_zones);
+    zones.sort_by(|a, b| b.low.total_cmp(&a.low));
+    zones
+}
+
+pub fn zones_to_csv(zones: &[SrZone]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("low,high,kind,touches,relative_volume,book_quantity,last_touch,score\n");
+
+    for zone in zones {
+        csv_string.push_str(&format!(
+            "{:.4},{:.4},{},{},{:.2},{:.3},{},{:.2}\n",
+            zone.low,
+            zone.high,
+            zone.kind,
+            zone.touches,
+            zone.relative_volume,
+            zone.book_quantity,
+            zone.last_touch
+                .map(|time| time.to_string())
+                .unwrap_or_default(),
+            zone.score
+        ));
+    }
+    csv_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    fn pivot(open_time: i64, price: f64) -> Pivot {
+        Pivot {
+            open_time,
+            price,
+            relative_volume: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_find_zones() {
+        // Two lows near 100, a fresh high at 120, a big bid just under 100 and a small ask.
+        let pivots = [pivot(0, 100.0), pivot(1, 120.0), pivot(2, 100.4)];
+        let clusters = [
+            BookCluster {
+                price: 99.8,
+                quantity: 500.0,
+                side: BookSide::Bid,
+            },
+            BookCluster {
+                price: 130.0,
+                quantity: 50.0,
+                side: BookSide::Ask,
+            },
+        ];
+        let zones = find_zones(&pivots, &clusters, 110.0, 1.0, 10);
+        assert_eq!(zones.len(), 3);
+
+        let [resting, high, support] = &zones[..] else {
+            panic!("expected three zones, got {zones:?}")
+        };
+        assert_eq!(
+            (resting.kind, resting.touches, resting.last_touch),
+            (ZoneKind::Resistance, 0, None)
+        );
+        assert_eq!((high.kind, high.touches), (ZoneKind::Resistance, 1));
+        assert_eq!((support.low, support.high), (99.8, 100.4));
+        assert_eq!((support.kind, support.touches), (ZoneKind::Support, 2));
+        assert_eq!(support.book_quantity, 500.0);
+        assert_eq!(support.last_touch, Some(2));
+        // Most touches, volume and resting quantity, and touched last.
+        assert!((support.score - 1.0).abs() < 1e-9);
+        assert!((high.score - 0.4).abs() < 1e-9);
+        assert!((resting.score - 0.02).abs() < 1e-9);
+
+        // Only the best zones are kept, still highest first.
+        let zones = find_zones(&pivots, &clusters, 110.0, 1.0, 2);
+        assert_eq!(zones.len(), 2);
+        assert_eq!(zones[0].low, 120.0);
+
+        let csv = zones_to_csv(&zones);
+        assert!(csv.starts_with("low,high,kind,touches,relative_volume,book_quantity,last_touch,score\n120.0000,120.0000,resistance,1,1.00,0.000,1,0.40\n"));
+    }
+
+    #[test]
+    fn test_pivots_and_book_clusters() {
+        let mut candles = candles(&[100.0, 110.0, 120.0, 100.0, 105.0]);
+        candles[2].volume = 3.0;
+        let pivots = pivots_from_candles(&candles, 5.0);
+        let prices: Vec<f64> = pivots.iter().map(|pivot| pivot.price).collect();
+        // The 106 high of the last candle isn't confirmed yet.
+        assert_eq!(prices, [99.0, 121.0, 99.0]);
+        // The high formed on three times the average volume of the five candles.
+        assert!((pivots[1].relative_volume - 3.0 / 1.4).abs() < 1e-9);
+
+        let orderbook = OrderBook {
+            last_update_id: 1,
+            bids: [["99.5", "10"], ["99.2", "30"], ["98.1", "5"]]
+                .iter()
+                .map(|level| level.iter().map(|value| value.to_string()).collect())
+                .collect(),
+            asks: [["100.5", "2"], ["101.5", "1"]]
+                .iter()
+                .map(|level| level.iter().map(|value| value.to_string()).collect())
+                .collect(),
+        };
+        let clusters = book_clusters(&orderbook, FractionalPart::One, 1);
+        assert_eq!(
+            clusters,
+            [
+                BookCluster {
+                    price: 99.0,
+                    quantity: 40.0,
+                    side: BookSide::Bid
+                },
+                BookCluster {
+                    price: 101.0,
+                    quantity: 2.0,
+                    side: BookSide::Ask
+                },
+            ]
+        );
+    }
+}
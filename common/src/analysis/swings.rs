@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::Candle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SwingKind {
+    High,
+    Low,
+}
+
+/// A turning point of the price, the high or low of the candle it happened on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Swing {
+    pub open_time: i64,
+    pub price: f64,
+    pub kind: SwingKind,
+    /// False for the extreme of the leg still in progress, which can move until price
+    /// reverses by the threshold.
+    pub confirmed: bool,
+}
+
+/// ZigZag swing highs and lows: a swing is confirmed once price reverses from it by
+/// `threshold_pct` percent. Swings alternate between highs and lows, and the last one is the
+/// unconfirmed extreme of the current leg.
+pub fn find_swings(candles: &[Candle], threshold_pct: f64) -> Vec<Swing> {
+    let Some(first) = candles.first() else {
+        return Vec::new();
+    };
+    let threshold = threshold_pct / 100.0;
+    let high = |candle: &Candle| Swing {
+        open_time: candle.open_time,
+        price: candle.high,
+        kind: SwingKind::High,
+        confirmed: false,
+    };
+    let low = |candle: &Candle| Swing {
+        open_time: candle.open_time,
+        price: candle.low,
+        kind: SwingKind::Low,
+        confirmed: false,
+    };
+
+    let mut swings = Vec::new();
+    // Until the first reversal either extreme can become the first swing.
+    let (mut highest, mut lowest) = (high(first), low(first));
+    let mut pending: Option<Swing> = None;
+
+    for candle in &candles[1..] {
+        match pending {
+            None => {
+                if candle.high > highest.price {
+                    highest = high(candle);
+                }
+                if candle.low < lowest.price {
+                    lowest = low(candle);
+                }
+                if highest.price >= lowest.price * (1.0 + threshold)
+                    && highest.open_time > lowest.open_time
+                {
+                    swings.push(Swing {
+                        confirmed: true,
+                        ..lowest
+                    });
+                    pending = Some(highest);
+                } else if lowest.price <= highest.price * (1.0 - threshold)
+                    && lowest.open_time > highest.open_time
+                {
+                    swings.push(Swing {
+                        confirmed: true,
+                        ..highest
+                    });
+                    pending = Some(lowest);
+                }
+            }
+            Some(extreme) if extreme.kind == SwingKind::High => {
+                if candle.high > extreme.price {
+                    pending = Some(high(candle));
+                } else if candle.low <= extreme.price * (1.0 - threshold) {
+                    swings.push(Swing {
+                        confirmed: true,
+                        ..extreme
+                    });
+                    pending = Some(low(candle));
+                }
+            }
+            Some(extreme) => {
+                if candle.low < extreme.price {
+                    pending = Some(low(candle));
+                } else if candle.high >= extreme.price * (1.0 + threshold) {
+                    swings.push(Swing {
+                        confirmed: true,
+                        ..extreme
+                    });
+                    pending = Some(high(candle));
+                }
+            }
+        }
+    }
+
+    swings.extend(pending);
+    swings
+}
+
+pub fn swings_to_csv(swings: &[Swing]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str("open_time,kind,price,confirmed\n");
+
+    for swing in swings {
+        csv_string.push_str(&format!(
+            "{},{},{:.4},{}\n",
+            swing.open_time, swing.kind, swing.price, swing.confirmed
+        ));
+    }
+    csv_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::candles_from_closes as candles;
+
+    #[test]
+    fn test_find_swings() {
+        // Up 20, down 15, up 5, a 2 point wobble along the way.
+        let closes = [
+            100.0, 105.0, 110.0, 108.0, 115.0, 120.0, 114.0, 110.0, 105.0, 108.0, 110.0,
+        ];
+        let swings = find_swings(&candles(&closes), 5.0);
+        let summary: Vec<_> = swings
+            .iter()
+            .map(|swing| {
+                (
+                    swing.open_time / 60_000,
+                    swing.kind,
+                    swing.price,
+                    swing.confirmed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (0, SwingKind::Low, 99.0, true),
+                (5, SwingKind::High, 121.0, true),
+                (8, SwingKind::Low, 104.0, true),
+                (10, SwingKind::High, 111.0, false),
+            ]
+        );
+
+        // A wider threshold ignores the last bounce.
+        let swings = find_swings(&candles(&closes), 10.0);
+        assert_eq!(swings.len(), 3);
+        assert_eq!(swings[2].kind, SwingKind::Low);
+        assert!(!swings[2].confirmed);
+
+        assert!(find_swings(&[], 5.0).is_empty());
+        let csv = swings_to_csv(&swings);
+        assert!(csv.starts_with("open_time,kind,price,confirmed\n0,low,99.0000,true\n"));
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::Kline;
+
+/// Trade counts are compared against this many candles, the current one included.
+pub const TRADE_COUNT_ZSCORE_WINDOW: usize = 20;
+
+/// Who was aggressing within one candle, from the kline's own taker and trade count fields.
+///
+/// Fields a venue doesn't report (empty strings) are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TakerPressure {
+    pub open_time: i64,
+    /// Volume in the quote asset, e.g. USDT.
+    pub quote_volume: Option<f64>,
+    /// Share of the base volume bought by takers, 0 to 1. Above 0.5 means buyers were lifting offers.
+    pub taker_buy_ratio: Option<f64>,
+    pub trades: i64,
+    /// How unusual the trade count is against the trailing `TRADE_COUNT_ZSCORE_WINDOW` candles,
+    /// in standard deviations. `None` until there are two candles or when counts don't vary.
+    pub trade_count_zscore: Option<f64>,
+}
+
+// Empty means the venue doesn't report the field.
+fn parse_optional(kline: &Kline, field: &str, value: &str) -> Result<Option<f64>> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+        .map(Some)
+        .with_context(|| format!("Invalid {field} {value:?} in kline {}", kline.open_time))
+}
+
+fn zscore(window: &[i64]) -> Option<f64> {
+    let (&current, _) = window.split_last()?;
+    if window.len() < 2 {
+        return None;
+    }
+    let n = window.len() as f64;
+    let mean = window.iter().sum::<i64>() as f64 / n;
+    let variance = window
+        .iter()
+        .map(|&count| (count as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    let std_dev = variance.sqrt();
+    (std_dev > 0.0).then(|| (current as f64 - mean) / std_dev)
+}
+
+/// Taker pressure of every kline, in the same order.
+///
+/// Fails on a taker or quote volume that's set but isn't a non-negative number.
+pub fn taker_pressure(klines: &[Kline]) -> Result<Vec<TakerPressure>> {
+    let trade_counts: Vec<i64> = klines.iter().map(|kline| kline.number_of_trades).collect();
+
+    klines
+        .iter()
+        .enumerate()
+        .map(|(i, kline)| {
+            let volume = parse_optional(kline, "volume", &kline.volume)?;
+            let taker_buy_volume = parse_optional(
+                kline,
+                "taker buy volume",
+                &kline.taker_buy_base_asset_volume,
+            )?;
+            let taker_buy_ratio = match (taker_buy_volume, volume) {
+                (Some(taker_buy_volume), Some(volume)) if volume > 0.0 => {
+                    Some((taker_buy_volume / volume).min(1.0))
+                }
+                _ => None,
+            };
+            let window_start = (i + 1).saturating_sub(TRADE_COUNT_ZSCORE_WINDOW);
+
+            Ok(TakerPressure {
+                open_time: kline.open_time,
+                quote_volume: parse_optional(kline, "quote volume", &kline.quote_asset_volume)?,
+                taker_buy_ratio,
+                trades: kline.number_of_trades,
+                trade_count_zscore: zscore(&trade_counts[window_start..=i]),
+            })
+        })
+        .collect()
+}
+
+fn optional_cell(value: Option<f64>, precision: usize) -> String {
+    value
+        .map(|value| format!("{value:.precision$}"))
+        .unwrap_or_default()
+}
+
+impl TakerPressure {
+    /// The `quote_volume,taker_buy_ratio,trades,trade_count_zscore` cells, unreported ones empty.
+    pub fn csv_cells(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            optional_cell(self.quote_volume, 2),
+            optional_cell(self.taker_buy_ratio, 4),
+            self.trades,
+            optional_cell(self.trade_count_zscore, 2)
+        )
+    }
+}
+
+pub const TAKER_PRESSURE_CSV_COLUMNS: &str =
+    "quote_volume,taker_buy_ratio,trades,trade_count_zscore";
+
+pub fn taker_pressure_to_csv(pressure: &[TakerPressure]) -> String {
+    let mut csv_string = String::new();
+    csv_string.push_str(&format!("open_time,{TAKER_PRESSURE_CSV_COLUMNS}\n"));
+
+    for row in pressure {
+        csv_string.push_str(&format!("{},{}\n", row.open_time, row.csv_cells()));
+    }
+    csv_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline(open_time: i64, volume: &str, taker_buy: &str, trades: i64) -> Kline {
+        Kline {
+            open_time,
+            open_price: "1".to_string(),
+            high_price: "1".to_string(),
+            low_price: "1".to_string(),
+            close_price: "1".to_string(),
+            volume: volume.to_string(),
+            close_time: open_time + 59_999,
+            quote_asset_volume: "150".to_string(),
+            number_of_trades: trades,
+            taker_buy_base_asset_volume: taker_buy.to_string(),
+            taker_buy_quote_asset_volume: String::new(),
+            ignore: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_taker_pressure() {
+        let mut klines: Vec<Kline> = (0..10)
+            .map(|i| kline(i * 60_000, "10", "5", 100 + i % 2 * 10))
+            .collect();
+        // A burst of aggressive buying.
+        klines.push(kline(600_000, "40", "36", 400));
+        klines.push(kline(660_000, "0", "", 100));
+
+        let pressure = taker_pressure(&klines).unwrap();
+        assert_eq!(pressure.len(), 12);
+        assert_eq!(pressure[0].taker_buy_ratio, Some(0.5));
+        assert_eq!(pressure[0].trade_count_zscore, None);
+        assert_eq!(pressure[1].trade_count_zscore, Some(1.0));
+        assert_eq!(pressure[10].taker_buy_ratio, Some(0.9));
+        assert!(pressure[10].trade_count_zscore.unwrap() > 3.0);
+        assert_eq!(pressure[11].taker_buy_ratio, None);
+
+        let csv = taker_pressure_to_csv(&pressure);
+        assert!(
+            csv.starts_with("open_time,quote_volume,taker_buy_ratio,trades,trade_count_zscore\n")
+        );
+        assert!(csv.contains("\n600000,150.00,0.9000,400,3.16\n"));
+        assert!(csv.ends_with("\n660000,150.00,,100,-0.36\n"));
+
+        klines[3].taker_buy_base_asset_volume = "lots".to_string();
+        assert!(taker_pressure(&klines).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::Kline;
+
+/// A `Kline` with its prices and volume parsed and checked.
+///
+/// Exchanges send prices as strings, so every conversion goes through `TryFrom<Kline>`
+/// which rejects unparseable, non-finite, non-positive or inconsistent values instead
+/// of letting them turn into panics or zero prices further down.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub open_time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub close_time: i64,
+}
+
+/// Why a `Kline` couldn't be turned into a `Candle`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CandleError {
+    /// The field isn't a number.
+    InvalidNumber {
+        open_time: i64,
+        field: &'static str,
+        value: String,
+    },
+    /// A price that isn't finite and positive, or a volume that isn't finite and non-negative.
+    OutOfRange {
+        open_time: i64,
+        field: &'static str,
+        value: f64,
+    },
+    /// High and low don't bound the open and close.
+    InconsistentRange {
+        open_time: i64,
+        open: f64,
+        high: f64,
+        low: f64,
+        close: f64,
+    },
+}
+
+impl fmt::Display for CandleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandleError::InvalidNumber {
+                open_time,
+                field,
+                value,
+            } => write!(
+                f,
+                "Invalid {field} {value:?} in kline opened at {open_time}"
+            ),
+            CandleError::OutOfRange {
+                open_time,
+                field,
+                value,
+            } => write!(
+                f,
+                "Out of range {field} {value} in kline opened at {open_time}"
+            ),
+            CandleError::InconsistentRange {
+                open_time,
+                open,
+                high,
+                low,
+                close,
+            } => write!(
+                f,
+                "Inconsistent kline opened at {open_time}: O {open} H {high} L {low} C {close}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CandleError {}
+
+fn parse_field(open_time: i64, field: &'static str, value: &str) -> Result<f64, CandleError> {
+    let parsed = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| CandleError::InvalidNumber {
+            open_time,
+            field,
+            value: value.to_string(),
+        })?;
+    if !parsed.is_finite() {
+        return Err(CandleError::OutOfRange {
+            open_time,
+            field,
+            value: parsed,
+        });
+    }
+    Ok(parsed)
+}
+
+fn parse_price(open_time: i64, field: &'static str, value: &str) -> Result<f64, CandleError> {
+    let price = parse_field(open_time, field, value)?;
+    if price <= 0.0 {
+        return Err(CandleError::OutOfRange {
+            open_time,
+            field,
+            value: price,
+        });
+    }
+    Ok(price)
+}
+
+impl Candle {
+    /// Converts a whole series, failing on the first malformed kline.
+    pub fn from_klines(klines: &[Kline]) -> Result<Vec<Candle>, CandleError> {
+        klines.iter().map(Candle::try_from).collect()
+    }
+
+    pub fn is_bullish(&self) -> bool {
+        self.close >= self.open
+    }
+}
+
+impl TryFrom<&Kline> for Candle {
+    type Error = CandleError;
+
+    fn try_from(kline: &Kline) -> Result<Self, Self::Error> {
+        let open_time = kline.open_time;
+        let open = parse_price(open_time, "open_price", &kline.open_price)?;
+        let high = parse_price(open_time, "high_price", &kline.high_price)?;
+        let low = parse_price(open_time, "low_price", &kline.low_price)?;
+        let close = parse_price(open_time, "close_price", &kline.close_price)?;
+        let volume = parse_field(open_time, "volume", &kline.volume)?;
+        if volume < 0.0 {
+            return Err(CandleError::OutOfRange {
+                open_time,
+                field: "volume",
+                value: volume,
+            });
+        }
+        if high < low || open.max(close) > high || open.min(close) < low {
+            return Err(CandleError::InconsistentRange {
+                open_time,
+                open,
+                high,
+                low,
+                close,
+            });
+        }
+
+        Ok(Candle {
+            open_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            close_time: kline.close_time,
+        })
+    }
+}
+
+impl TryFrom<Kline> for Candle {
+    type Error = CandleError;
+
+    fn try_from(kline: Kline) -> Result<Self, Self::Error> {
+        Candle::try_from(&kline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline(open: &str, high: &str, low: &str, close: &str, volume: &str) -> Kline {
+        Kline {
+            open_time: 1_700_000_000_000,
+            open_price: open.to_string(),
+            high_price: high.to_string(),
+            low_price: low.to_string(),
+            close_price: close.to_string(),
+            volume: volume.to_string(),
+            close_time: 1_700_003_599_999,
+            quote_asset_volume: String::new(),
+            number_of_trades: 0,
+            taker_buy_base_asset_volume: String::new(),
+            taker_buy_quote_asset_volume: String::new(),
+            ignore: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_candle_from_kline() {
+        let candle = Candle::try_from(kline(
+            "150.10000000",
+            "152.00000000",
+            "149.50000000",
+            "151.25000000",
+            "1234.5",
+        ))
+        .unwrap();
+        assert_eq!(candle.open, 150.1);
+        assert_eq!(candle.high, 152.0);
+        assert_eq!(candle.low, 149.5);
+        assert_eq!(candle.close, 151.25);
+        assert_eq!(candle.volume, 1234.5);
+        assert_eq!(candle.close_time, 1_700_003_599_999);
+        assert!(candle.is_bullish());
+    }
+
+    #[test]
+    fn test_candle_rejects_malformed_kline() {
+        assert_eq!(
+            Candle::try_from(kline("150", "152", "", "151", "1")).unwrap_err(),
+            CandleError::InvalidNumber {
+                open_time: 1_700_000_000_000,
+                field: "low_price",
+                value: String::new(),
+            }
+        );
+        assert!(matches!(
+            Candle::try_from(kline("150", "152", "149", "NaN", "1")),
+            Err(CandleError::OutOfRange {
+                field: "close_price",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Candle::try_from(kline("0", "152", "149", "151", "1")),
+            Err(CandleError::OutOfRange {
+                field: "open_price",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Candle::try_from(kline("150", "152", "149", "151", "-1")),
+            Err(CandleError::OutOfRange {
+                field: "volume",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Candle::try_from(kline("150", "149", "152", "151", "1")),
+            Err(CandleError::InconsistentRange { .. })
+        ));
+
+        // One bad row fails the whole series.
+        let klines = vec![
+            kline("150", "152", "149", "151", "1"),
+            kline("151", "abc", "149", "151", "1"),
+        ];
+        let error = Candle::from_klines(&klines).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"Invalid high_price "abc" in kline opened at 1700000000000"#
+        );
+    }
+}
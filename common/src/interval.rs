@@ -0,0 +1,229 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+// 1970-01-01 was a Thursday, weekly candles open on Monday.
+const FIRST_MONDAY: i64 = 4 * DAY;
+
+/// A kline interval, covering every interval Binance serves from `1s` to `1M`.
+///
+/// Parses from and displays as the exchange string ("15m", "4h", "1w", "1M"),
+/// and (de)serializes the same way.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Interval {
+    Second1,
+    Minute1,
+    Minute3,
+    Minute5,
+    Minute15,
+    Minute30,
+    Hour1,
+    Hour2,
+    Hour4,
+    Hour6,
+    Hour8,
+    Hour12,
+    Day1,
+    Day3,
+    Week1,
+    Month1,
+}
+
+impl Interval {
+    pub const ALL: [Interval; 16] = [
+        Interval::Second1,
+        Interval::Minute1,
+        Interval::Minute3,
+        Interval::Minute5,
+        Interval::Minute15,
+        Interval::Minute30,
+        Interval::Hour1,
+        Interval::Hour2,
+        Interval::Hour4,
+        Interval::Hour6,
+        Interval::Hour8,
+        Interval::Hour12,
+        Interval::Day1,
+        Interval::Day3,
+        Interval::Week1,
+        Interval::Month1,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::Second1 => "1s",
+            Interval::Minute1 => "1m",
+            Interval::Minute3 => "3m",
+            Interval::Minute5 => "5m",
+            Interval::Minute15 => "15m",
+            Interval::Minute30 => "30m",
+            Interval::Hour1 => "1h",
+            Interval::Hour2 => "2h",
+            Interval::Hour4 => "4h",
+            Interval::Hour6 => "6h",
+            Interval::Hour8 => "8h",
+            Interval::Hour12 => "12h",
+            Interval::Day1 => "1d",
+            Interval::Day3 => "3d",
+            Interval::Week1 => "1w",
+            Interval::Month1 => "1M",
+        }
+    }
+
+    /// Duration in seconds. A month counts as 30 days, use `align` for calendar months.
+    pub fn duration_seconds(&self) -> i64 {
+        match self {
+            Interval::Second1 => 1,
+            Interval::Minute1 => MINUTE,
+            Interval::Minute3 => 3 * MINUTE,
+            Interval::Minute5 => 5 * MINUTE,
+            Interval::Minute15 => 15 * MINUTE,
+            Interval::Minute30 => 30 * MINUTE,
+            Interval::Hour1 => HOUR,
+            Interval::Hour2 => 2 * HOUR,
+            Interval::Hour4 => 4 * HOUR,
+            Interval::Hour6 => 6 * HOUR,
+            Interval::Hour8 => 8 * HOUR,
+            Interval::Hour12 => 12 * HOUR,
+            Interval::Day1 => DAY,
+            Interval::Day3 => 3 * DAY,
+            Interval::Week1 => 7 * DAY,
+            Interval::Month1 => 30 * DAY,
+        }
+    }
+
+    pub fn duration_ms(&self) -> i64 {
+        self.duration_seconds() * 1000
+    }
+
+    /// Start of the candle containing `ts` (unix seconds), matching the exchange's boundaries:
+    /// weeks open on Monday 00:00 UTC and months on the 1st.
+    pub fn align(&self, ts: i64) -> i64 {
+        match self {
+            Interval::Week1 => {
+                let week = self.duration_seconds();
+                (ts - FIRST_MONDAY).div_euclid(week) * week + FIRST_MONDAY
+            }
+            Interval::Month1 => DateTime::<Utc>::from_timestamp(ts, 0)
+                .and_then(|time| {
+                    Utc.with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0)
+                        .single()
+                })
+                .map(|month_start| month_start.timestamp())
+                .unwrap_or(ts),
+            _ => round_down_timestamp(ts, self.duration_seconds()),
+        }
+    }
+
+    /// Open time (ms) of the candle after the one opened at `open_time` (ms).
+    pub fn next_open_time(&self, open_time: i64) -> i64 {
+        match self {
+            // Any 1st of the month plus 31 days lands in the following month.
+            Interval::Month1 => self.align(open_time / 1000 + 31 * DAY) * 1000,
+            _ => open_time + self.duration_ms(),
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = anyhow::Error;
+
+    fn from_str(interval: &str) -> Result<Self> {
+        let interval = interval.trim();
+        Interval::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == interval)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unsupported interval {interval}, expected one of {}",
+                    Interval::ALL.map(|interval| interval.as_str()).join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Interval {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Interval {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let interval = String::deserialize(deserializer)?;
+        interval.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Function to round down a timestamp to the nearest interval
+// ts: Unix timestamp (seconds since epoch)
+// interval_seconds: The duration of the interval in seconds
+pub fn round_down_timestamp(ts: i64, interval_seconds: i64) -> i64 {
+    if interval_seconds <= 0 {
+        // Avoid division by zero or negative intervals
+        return ts;
+    }
+    // Integer division truncates towards zero.
+    // (ts / interval) gives the number of full intervals since epoch.
+    // Multiplying back by interval gives the timestamp at the start of the current interval.
+    (ts / interval_seconds) * interval_seconds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for interval in Interval::ALL {
+            assert_eq!(interval.as_str().parse::<Interval>().unwrap(), interval);
+            assert_eq!(interval.to_string(), interval.as_str());
+        }
+        // Minutes and months only differ by case.
+        assert_eq!("1m".parse::<Interval>().unwrap(), Interval::Minute1);
+        assert_eq!("1M".parse::<Interval>().unwrap(), Interval::Month1);
+        assert!("1x".parse::<Interval>().is_err());
+        assert!("2w".parse::<Interval>().is_err());
+
+        assert_eq!(serde_json::to_string(&Interval::Week1).unwrap(), r#""1w""#);
+        assert_eq!(
+            serde_json::from_str::<Interval>(r#""4h""#).unwrap(),
+            Interval::Hour4
+        );
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(Interval::Minute15.duration_ms(), 900_000);
+        assert_eq!(Interval::Hour4.duration_ms(), 14_400_000);
+        assert_eq!(Interval::Day1.duration_ms(), 86_400_000);
+        assert_eq!(Interval::Week1.duration_seconds(), 604_800);
+    }
+
+    #[test]
+    fn test_align_week_and_month() {
+        // 2025-04-30T13:45:00Z, a Wednesday
+        let ts = 1746020700;
+        assert_eq!(Interval::Hour4.align(ts), 1746014400); // 12:00
+        assert_eq!(Interval::Day1.align(ts), 1745971200); // 2025-04-30
+        assert_eq!(Interval::Week1.align(ts), 1745798400); // Monday 2025-04-28
+        assert_eq!(Interval::Month1.align(ts), 1743465600); // 2025-04-01
+
+        assert_eq!(Interval::Hour4.next_open_time(1746014400000), 1746028800000);
+        // 2025-02-01 to 2025-03-01
+        assert_eq!(
+            Interval::Month1.next_open_time(1738368000000),
+            1740787200000
+        );
+    }
+}
@@ -1,19 +1,32 @@
 pub mod analysis;
+pub mod candle;
+pub mod interval;
+pub mod order_book;
+pub mod pair_symbol;
 pub mod predictions;
 pub mod prices;
 pub mod sources;
+pub mod store;
 pub mod subscriptions;
 pub mod transforms;
 pub mod worker_kv;
 
+#[cfg(test)]
+mod test_utils;
+
 // Conditionally compile and export the service binding helper
 #[cfg(feature = "service_binding")]
 pub mod worker_binding;
 
 pub use analysis::*;
+pub use candle::*;
+pub use interval::*;
+pub use order_book::*;
+pub use pair_symbol::*;
 pub use predictions::*;
 pub use prices::*;
 pub use sources::*;
+pub use store::*;
 pub use subscriptions::*;
 pub use transforms::*;
 pub use worker_kv::*;
@@ -0,0 +1,327 @@
+use anyhow::{bail, Context, Result};
+use std::{cmp::Ordering, collections::BTreeMap, fs, path::Path};
+
+use crate::{market::MarketDataSource, DepthUpdate, OrderBook};
+
+/// What happened to a diff-depth event handed to `LocalOrderBook::apply`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthApply {
+    /// The event was applied to the book.
+    Applied,
+    /// The event is older than the book and was dropped.
+    Ignored,
+    /// No snapshot yet, the event is kept until `apply_snapshot`.
+    Buffered,
+    /// Update ids don't line up. The book is cleared and waits for a new snapshot,
+    /// this event is buffered so it can be replayed on top of it.
+    GapDetected { expected: i64, got: i64 },
+}
+
+// f64 prices ordered with `total_cmp` so they can key a BTreeMap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PriceKey(f64);
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+// Price/quantity as received, so the output `OrderBook` keeps Binance's formatting.
+type Levels = BTreeMap<PriceKey, (String, String)>;
+
+/// An order book kept in sync from a depth snapshot plus diff-depth events.
+///
+/// Follows Binance's rules for a local book:
+/// 1. Buffer events until a snapshot arrives.
+/// 2. Drop events with `u <= lastUpdateId`.
+/// 3. The first applied event must have `U <= lastUpdateId + 1 <= u`.
+/// 4. Every following event must have `U == previous u + 1`, anything else is a gap and needs a resync.
+#[derive(Debug, Clone, Default)]
+pub struct LocalOrderBook {
+    bids: Levels,
+    asks: Levels,
+    last_update_id: Option<i64>,
+    // Set once the first event after the snapshot has been applied.
+    is_bridged: bool,
+    buffer: Vec<DepthUpdate>,
+}
+
+impl LocalOrderBook {
+    /// An empty book that buffers events until `apply_snapshot` is called.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_snapshot(snapshot: &OrderBook) -> Result<Self> {
+        let mut book = Self::new();
+        book.apply_snapshot(snapshot)?;
+        Ok(book)
+    }
+
+    /// True until a snapshot has been applied, or after a gap was detected.
+    pub fn needs_snapshot(&self) -> bool {
+        self.last_update_id.is_none()
+    }
+
+    pub fn last_update_id(&self) -> Option<i64> {
+        self.last_update_id
+    }
+
+    /// Replaces the book with `snapshot`, then replays buffered events on top of it.
+    ///
+    /// Fails if the buffered events don't line up with the snapshot, in which case
+    /// the events stay buffered and a newer snapshot is needed.
+    pub fn apply_snapshot(&mut self, snapshot: &OrderBook) -> Result<()> {
+        self.bids = parse_levels(&snapshot.bids).context("Invalid snapshot bids")?;
+        self.asks = parse_levels(&snapshot.asks).context("Invalid snapshot asks")?;
+        self.last_update_id = Some(snapshot.last_update_id);
+        self.is_bridged = false;
+
+        let buffered = std::mem::take(&mut self.buffer);
+        let replay = buffered
+            .iter()
+            .try_for_each(|update| match self.apply(update)? {
+                DepthApply::GapDetected { expected, got } => bail!(
+                "Snapshot {} doesn't line up with buffered events (expected {expected}, got {got})",
+                snapshot.last_update_id
+            ),
+                _ => Ok(()),
+            });
+
+        // Every buffered event goes back, the next snapshot replays them all again.
+        if replay.is_err() {
+            self.reset();
+            self.buffer = buffered;
+        }
+
+        replay
+    }
+
+    pub fn apply(&mut self, update: &DepthUpdate) -> Result<DepthApply> {
+        let Some(last_update_id) = self.last_update_id else {
+            self.buffer.push(update.clone());
+            return Ok(DepthApply::Buffered);
+        };
+
+        if update.final_update_id <= last_update_id {
+            return Ok(DepthApply::Ignored);
+        }
+
+        let expected = last_update_id + 1;
+        let is_in_sequence = if self.is_bridged {
+            update.first_update_id == expected
+        } else {
+            update.first_update_id <= expected && expected <= update.final_update_id
+        };
+
+        if !is_in_sequence {
+            self.reset();
+            self.buffer.push(update.clone());
+            return Ok(DepthApply::GapDetected {
+                expected,
+                got: update.first_update_id,
+            });
+        }
+
+        apply_levels(&mut self.bids, &update.bids).context("Invalid bid update")?;
+        apply_levels(&mut self.asks, &update.asks).context("Invalid ask update")?;
+        self.last_update_id = Some(update.final_update_id);
+        self.is_bridged = true;
+
+        Ok(DepthApply::Applied)
+    }
+
+    /// Fetches a fresh snapshot from `source` and replays buffered events on it.
+    pub async fn resync<S: MarketDataSource>(
+        &mut self,
+        source: &S,
+        pair_symbol: &str,
+        limit: i32,
+    ) -> Result<()> {
+        let snapshot = source
+            .fetch_orderbook(pair_symbol, limit)
+            .await
+            .context("Failed to fetch orderbook snapshot for resync")?;
+        self.apply_snapshot(&snapshot)
+    }
+
+    fn reset(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.last_update_id = None;
+        self.is_bridged = false;
+    }
+
+    /// Best bid as `(price, quantity)`.
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids
+            .iter()
+            .next_back()
+            .and_then(|(price, (_, quantity))| Some((price.0, quantity.parse().ok()?)))
+    }
+
+    /// Best ask as `(price, quantity)`.
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks
+            .iter()
+            .next()
+            .and_then(|(price, (_, quantity))| Some((price.0, quantity.parse().ok()?)))
+    }
+
+    /// The current book as an `OrderBook`, bids high to low and asks low to high,
+    /// ready for `group_by_fractional_part` or the chart.
+    pub fn to_order_book(&self, limit: Option<usize>) -> OrderBook {
+        let limit = limit.unwrap_or(usize::MAX);
+        let to_rows = |levels: &mut dyn Iterator<Item = &(String, String)>| {
+            levels
+                .take(limit)
+                .map(|(price, quantity)| vec![price.clone(), quantity.clone()])
+                .collect()
+        };
+
+        OrderBook {
+            last_update_id: self.last_update_id.unwrap_or_default(),
+            bids: to_rows(&mut self.bids.values().rev()),
+            asks: to_rows(&mut self.asks.values()),
+        }
+    }
+}
+
+fn parse_level(level: &[String]) -> Result<(PriceKey, f64)> {
+    let [price, quantity, ..] = level else {
+        bail!("Expected [price, quantity], got {level:?}");
+    };
+    let price = price
+        .parse::<f64>()
+        .with_context(|| format!("Invalid price {price}"))?;
+    let quantity = quantity
+        .parse::<f64>()
+        .with_context(|| format!("Invalid quantity {quantity}"))?;
+    Ok((PriceKey(price), quantity))
+}
+
+fn parse_levels(rows: &[Vec<String>]) -> Result<Levels> {
+    let mut levels = Levels::new();
+    apply_levels(&mut levels, rows)?;
+    Ok(levels)
+}
+
+fn apply_levels(levels: &mut Levels, rows: &[Vec<String>]) -> Result<()> {
+    for row in rows {
+        let (key, quantity) = parse_level(row)?;
+        if quantity == 0.0 {
+            levels.remove(&key);
+        } else {
+            levels.insert(key, (row[0].clone(), row[1].clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Loads recorded diff-depth events, one JSON object per line.
+/// Lines can be raw `depthUpdate` events or combined-stream frames (`{"stream":..,"data":..}`).
+pub fn load_depth_events(path: impl AsRef<Path>) -> Result<Vec<DepthUpdate>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read depth events {}", path.display()))?;
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let mut value: serde_json::Value = serde_json::from_str(line)
+                .with_context(|| format!("Invalid JSON on line {}", index + 1))?;
+            if let Some(data) = value.get_mut("data") {
+                value = data.take();
+            }
+            serde_json::from_value(value)
+                .with_context(|| format!("Invalid depth event on line {}", index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/SOLUSDT");
+
+    fn load_snapshot() -> OrderBook {
+        let content = fs::read_to_string(format!("{FIXTURES_DIR}/depth.json")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn test_replay_recorded_events() {
+        let snapshot = load_snapshot();
+        let events = load_depth_events(format!("{FIXTURES_DIR}/depth_events.jsonl")).unwrap();
+
+        let mut book = LocalOrderBook::new();
+        // Events recorded before the snapshot was fetched are buffered then replayed.
+        assert_eq!(book.apply(&events[0]).unwrap(), DepthApply::Buffered);
+        assert_eq!(book.apply(&events[1]).unwrap(), DepthApply::Buffered);
+        book.apply_snapshot(&snapshot).unwrap();
+
+        for event in &events[2..] {
+            assert_eq!(book.apply(event).unwrap(), DepthApply::Applied);
+        }
+
+        assert_eq!(book.last_update_id(), Some(snapshot.last_update_id + 25));
+        assert_eq!(book.best_bid(), Some((144.85, 40.0)));
+        assert_eq!(book.best_ask(), Some((144.90, 12.0)));
+
+        let orderbook = book.to_order_book(Some(3));
+        assert_eq!(orderbook.bids[1], ["144.73", "45.129"]); // 144.79 was removed
+        assert_eq!(orderbook.asks[1], ["144.93", "7.000"]);
+        assert_eq!(orderbook.asks.len(), 3);
+
+        // Replaying an old event doesn't move the book.
+        assert_eq!(book.apply(&events[3]).unwrap(), DepthApply::Ignored);
+    }
+
+    #[test]
+    fn test_gap_triggers_resync() {
+        let snapshot = load_snapshot();
+        let events = load_depth_events(format!("{FIXTURES_DIR}/depth_events_gap.jsonl")).unwrap();
+
+        let mut book = LocalOrderBook::from_snapshot(&snapshot).unwrap();
+        assert_eq!(book.apply(&events[0]).unwrap(), DepthApply::Ignored);
+        assert_eq!(book.apply(&events[1]).unwrap(), DepthApply::Applied);
+        assert_eq!(book.apply(&events[2]).unwrap(), DepthApply::Applied);
+        assert_eq!(
+            book.apply(&events[3]).unwrap(),
+            DepthApply::GapDetected {
+                expected: snapshot.last_update_id + 10,
+                got: snapshot.last_update_id + 13,
+            }
+        );
+        assert!(book.needs_snapshot());
+        assert_eq!(book.apply(&events[4]).unwrap(), DepthApply::Buffered);
+
+        // The old snapshot can't bridge the gap, the buffered events wait for the next one.
+        assert!(book.apply_snapshot(&snapshot).is_err());
+        assert!(book.needs_snapshot());
+
+        let fresh_snapshot = OrderBook {
+            last_update_id: snapshot.last_update_id + 15,
+            bids: vec![vec!["144.80".to_string(), "1.000".to_string()]],
+            asks: vec![vec!["144.95".to_string(), "2.000".to_string()]],
+        };
+        book.apply_snapshot(&fresh_snapshot).unwrap();
+
+        assert!(!book.needs_snapshot());
+        assert_eq!(book.last_update_id(), Some(snapshot.last_update_id + 25));
+        assert_eq!(book.best_bid(), Some((144.80, 1.0)));
+        assert_eq!(book.best_ask(), Some((144.93, 7.0)));
+    }
+}
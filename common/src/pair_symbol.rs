@@ -0,0 +1,194 @@
+use anyhow::{anyhow, bail, Result};
+use jup_sdk::token_registry::get_by_symbol;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+use crate::venue::Venue;
+
+// Longest first so "FDUSD" wins over "USD" when there's no separator.
+const KNOWN_QUOTES: [&str; 9] = [
+    "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "USD", "BTC", "ETH", "BNB",
+];
+
+const DEFAULT_QUOTE: &str = "USDT";
+
+/// A trading pair like `SOL_USDT`.
+///
+/// Parses "SOL_USDT", "SOL-USDC", "ETH/BTC", "SOLFDUSD" or a bare "SOL" (quoted in USDT),
+/// and (de)serializes as `BASE_QUOTE`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PairSymbol {
+    pub base: String,
+    pub quote: String,
+}
+
+impl PairSymbol {
+    pub fn new(base: &str, quote: &str) -> Self {
+        PairSymbol {
+            base: base.trim().to_uppercase(),
+            quote: quote.trim().to_uppercase(),
+        }
+    }
+
+    /// Checks the base token against the jup-sdk token registry and the quote against
+    /// the registry or the usual quote assets.
+    pub fn validate(&self) -> Result<()> {
+        if get_by_symbol(&self.base).is_none() {
+            bail!("Unsupported token {}", self.base);
+        }
+        if !KNOWN_QUOTES.contains(&self.quote.as_str()) && get_by_symbol(&self.quote).is_none() {
+            bail!("Unsupported quote {}", self.quote);
+        }
+        Ok(())
+    }
+
+    /// The venue's own symbol for this pair.
+    ///
+    /// * Binance, Bybit: `SOLUSDT`
+    /// * OKX: `SOL-USDT`
+    /// * Coinbase: `SOL-USD` (USDT/USDC quotes map to USD, which is where Coinbase's liquidity is)
+    pub fn format_for(&self, venue: Venue) -> String {
+        let PairSymbol { base, quote } = self;
+        match venue {
+            Venue::Binance | Venue::Bybit => format!("{base}{quote}"),
+            Venue::Okx => format!("{base}-{quote}"),
+            Venue::Coinbase => {
+                let quote = match quote.as_str() {
+                    "USDT" | "USDC" => "USD",
+                    quote => quote,
+                };
+                format!("{base}-{quote}")
+            }
+        }
+    }
+}
+
+impl FromStr for PairSymbol {
+    type Err = anyhow::Error;
+
+    fn from_str(pair_symbol: &str) -> Result<Self> {
+        let pair_symbol = pair_symbol.trim().to_uppercase();
+        if pair_symbol.is_empty() {
+            bail!("Empty pair symbol");
+        }
+
+        let (base, quote) = match pair_symbol.split_once(['_', '-', '/']) {
+            Some((base, quote)) => (base, quote),
+            // A known token is kept whole even when it ends in a quote, e.g. "STETH" or "CBBTC".
+            None if get_by_symbol(&pair_symbol).is_some() => (pair_symbol.as_str(), DEFAULT_QUOTE),
+            // A one letter base is a token ending in a quote, e.g. "SUSD" is sUSD and not S/USD.
+            None => KNOWN_QUOTES
+                .iter()
+                .find_map(|quote| {
+                    pair_symbol
+                        .strip_suffix(quote)
+                        .filter(|base| base.len() >= 2)
+                        .map(|base| (base, *quote))
+                })
+                .unwrap_or((pair_symbol.as_str(), DEFAULT_QUOTE)),
+        };
+
+        let is_symbol = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric());
+        if !is_symbol(base) || !is_symbol(quote) {
+            return Err(anyhow!("Invalid pair symbol {pair_symbol}"));
+        }
+
+        Ok(PairSymbol::new(base, quote))
+    }
+}
+
+impl fmt::Display for PairSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.base, self.quote)
+    }
+}
+
+impl Serialize for PairSymbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PairSymbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pair_symbol = String::deserialize(deserializer)?;
+        pair_symbol.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pair_symbol() {
+        for input in ["SOL", "sol_usdt", "SOL-USDT", "SOL/USDT", "SOLUSDT"] {
+            assert_eq!(
+                input.parse::<PairSymbol>().unwrap(),
+                PairSymbol::new("SOL", "USDT")
+            );
+        }
+        assert_eq!(
+            "ETHBTC".parse::<PairSymbol>().unwrap(),
+            PairSymbol::new("ETH", "BTC")
+        );
+        assert_eq!(
+            "SOLFDUSD".parse::<PairSymbol>().unwrap(),
+            PairSymbol::new("SOL", "FDUSD")
+        );
+        // Tokens containing "USD" keep their full symbol.
+        assert_eq!(
+            "SUSD_USDC".parse::<PairSymbol>().unwrap(),
+            PairSymbol::new("SUSD", "USDC")
+        );
+        for (input, base) in [("SUSD", "SUSD"), ("susd", "SUSD"), ("TUSD", "TUSD")] {
+            assert_eq!(
+                input.parse::<PairSymbol>().unwrap(),
+                PairSymbol::new(base, DEFAULT_QUOTE)
+            );
+        }
+        assert_eq!(
+            "SUSDUSDT".parse::<PairSymbol>().unwrap(),
+            PairSymbol::new("SUSD", "USDT")
+        );
+        // Registry tokens ending in a quote stay whole.
+        for (input, base) in [("STETH", "STETH"), ("cbBTC", "CBBTC")] {
+            assert_eq!(
+                input.parse::<PairSymbol>().unwrap(),
+                PairSymbol::new(base, DEFAULT_QUOTE)
+            );
+        }
+        assert_eq!(
+            "STETHUSDC".parse::<PairSymbol>().unwrap(),
+            PairSymbol::new("STETH", "USDC")
+        );
+        assert!("".parse::<PairSymbol>().is_err());
+        assert!("SOL_".parse::<PairSymbol>().is_err());
+        assert!("SOL USDT".parse::<PairSymbol>().is_err());
+    }
+
+    #[test]
+    fn test_format_and_serde() {
+        let pair_symbol = PairSymbol::new("sol", "usdc");
+        assert_eq!(pair_symbol.to_string(), "SOL_USDC");
+        assert_eq!(pair_symbol.format_for(Venue::Binance), "SOLUSDC");
+        assert_eq!(pair_symbol.format_for(Venue::Okx), "SOL-USDC");
+        assert_eq!(pair_symbol.format_for(Venue::Coinbase), "SOL-USD");
+
+        let json = serde_json::to_string(&pair_symbol).unwrap();
+        assert_eq!(json, r#""SOL_USDC""#);
+        assert_eq!(
+            serde_json::from_str::<PairSymbol>(r#""SOLUSDC""#).unwrap(),
+            pair_symbol
+        );
+        assert!(serde_json::from_str::<PairSymbol>(r#""SOL?""#).is_err());
+    }
+
+    #[test]
+    fn test_validate_against_token_registry() {
+        assert!(PairSymbol::new("SOL", "USDT").validate().is_ok());
+        assert!(PairSymbol::new("SOL", "FDUSD").validate().is_ok());
+        assert!(PairSymbol::new("NOTATOKEN", "USDT").validate().is_err());
+        assert!(PairSymbol::new("SOL", "NOTAQUOTE").validate().is_err());
+    }
+}
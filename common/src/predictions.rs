@@ -8,6 +8,10 @@ use jup_sdk::{
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{Number as JsonNumber, Value as JsonValue};
 
+use crate::{
+    derivatives::DerivativesSnapshot, oracle::OraclePrice, quality::DataQualityReport, PairSymbol,
+};
+
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum PredictionOutput {
@@ -307,20 +311,30 @@ where
 #[serde(rename_all = "snake_case")]
 pub struct TradingContext {
     pub token_symbol: String,
-    pub pair_symbol: String,
+    pub pair_symbol: PairSymbol,
     pub interval: String,
     pub current_price: f64,
+    /// Where `current_price` came from, with its age and confidence.
+    #[serde(default)]
+    pub maybe_oracle_price: Option<OraclePrice>,
     pub maybe_preps_positions: Option<Vec<PerpsPosition>>,
     pub maybe_trading_predictions: Option<Vec<RefinedTradingPrediction>>,
     pub kline_intervals: Vec<String>,
     pub stoch_rsi_intervals: Vec<String>,
     pub latest_bb_ma_intervals: Vec<String>,
+    /// Data-quality report of every interval the historical data was built from.
+    #[serde(default)]
+    pub data_quality: Vec<DataQualityReport>,
+    /// Perpetual futures positioning (funding, open interest, top traders' long/short ratio),
+    /// when the historical data was built `with_derivatives`.
+    #[serde(default)]
+    pub maybe_derivatives: Option<Box<DerivativesSnapshot>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct PredictedLongShortSignal {
-    pub pair_symbol: String,
+    pub pair_symbol: PairSymbol,
     pub direction: String,
     pub entry_price: f64,
     pub target_price: f64,
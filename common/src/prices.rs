@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize, Serializer};
 use serde_json::Value as JsonValue;
 
-use crate::KlineValue;
+use crate::{Candle, CandleError, KlineValue};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Kline {
@@ -62,15 +62,18 @@ where
     serializer.serialize_f64(rounded)
 }
 
-impl From<Kline> for ConciseKline {
-    fn from(kline: Kline) -> Self {
-        ConciseKline {
-            close_time: kline.open_time,
-            high: kline.high_price.parse().unwrap_or(0.0),
-            low: kline.low_price.parse().unwrap_or(0.0),
-            close: kline.close_price.parse().unwrap_or(0.0),
-            volume: kline.volume.parse().unwrap_or(0.0),
-        }
+impl TryFrom<Kline> for ConciseKline {
+    type Error = CandleError;
+
+    fn try_from(kline: Kline) -> Result<Self, Self::Error> {
+        let candle = Candle::try_from(&kline)?;
+        Ok(ConciseKline {
+            close_time: candle.open_time,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+        })
     }
 }
 
@@ -82,6 +85,99 @@ pub struct OrderBook {
     pub asks: Vec<Vec<String>>,
 }
 
+/// A Binance diff-depth event (`<symbol>@depth`), levels are `[price, quantity]`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DepthUpdate {
+    #[serde(rename = "E")]
+    pub event_time: i64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "U")]
+    pub first_update_id: i64,
+    #[serde(rename = "u")]
+    pub final_update_id: i64,
+    #[serde(rename = "b")]
+    pub bids: Vec<Vec<String>>,
+    #[serde(rename = "a")]
+    pub asks: Vec<Vec<String>>,
+}
+
+/// A Binance aggregate trade (`aggTrades`): fills of one taker order at the same price, merged.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct AggTrade {
+    #[serde(rename = "a")]
+    pub id: i64,
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "q")]
+    pub quantity: String,
+    #[serde(rename = "f")]
+    pub first_trade_id: i64,
+    #[serde(rename = "l")]
+    pub last_trade_id: i64,
+    #[serde(rename = "T")]
+    pub time: i64,
+    /// The seller was the taker, i.e. a market sell.
+    #[serde(rename = "m")]
+    pub is_buyer_maker: bool,
+}
+
+/// A settled perpetual funding rate (`fapi/v1/fundingRate`), positive when longs pay shorts.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRate {
+    pub symbol: String,
+    pub funding_rate: String,
+    pub funding_time: i64,
+    // Missing on older settlements.
+    #[serde(default)]
+    pub mark_price: String,
+}
+
+/// Open interest at the end of a period (`futures/data/openInterestHist`).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenInterest {
+    pub symbol: String,
+    /// In contracts of the base asset.
+    pub sum_open_interest: String,
+    /// In the quote asset.
+    pub sum_open_interest_value: String,
+    pub timestamp: i64,
+}
+
+/// Top traders' long/short position ratio at the end of a period
+/// (`futures/data/topLongShortPositionRatio`).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LongShortRatio {
+    pub symbol: String,
+    pub long_short_ratio: String,
+    /// Share of the positions that are long, 0 to 1.
+    pub long_account: String,
+    pub short_account: String,
+    pub timestamp: i64,
+}
+
+/// Perpetual futures positioning for one pair over the same window, each history oldest first.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+pub struct FuturesMetrics {
+    pub funding_rates: Vec<FundingRate>,
+    pub open_interest: Vec<OpenInterest>,
+    pub long_short_ratios: Vec<LongShortRatio>,
+}
+
+impl From<DepthUpdate> for OrderBook {
+    /// The changed levels only, a zero quantity means the level was removed.
+    fn from(update: DepthUpdate) -> Self {
+        OrderBook {
+            last_update_id: update.final_update_id,
+            bids: update.bids,
+            asks: update.asks,
+        }
+    }
+}
+
 impl Kline {
     pub fn to_kline_values(&self) -> Vec<KlineValue> {
         vec![
@@ -1,18 +1,483 @@
 use anyhow::{anyhow, Context, Result};
-use reqwest::Client;
-use serde_json::Value as JsonValue;
+use reqwest::{header::USER_AGENT, Client};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{collections::BTreeMap, time::Duration};
 
-use crate::{Kline, OrderBook};
+use crate::{
+    market::MarketDataSource,
+    taker_pressure::{taker_pressure, TakerPressure, TAKER_PRESSURE_CSV_COLUMNS},
+    venue::Venue,
+    AggTrade, Candle, FundingRate, FuturesMetrics, Interval, Kline, LongShortRatio, OpenInterest,
+    OrderBook, PairSymbol,
+};
 
 const BINANCE_API_URL: &str = "https://data-api.binance.vision/api/v3";
+// USDⓈ-M perpetuals, `fapi/v1/...` and `futures/data/...` both live under it.
+const BINANCE_FUTURES_API_URL: &str = "https://fapi.binance.com";
+// Binance rejects requests from Cloudflare's ranges, so we go through an edge proxy by default.
+const DEFAULT_PROXY_PREFIX: &str = "https://adversely-amazing-wildcat.edgecompute.app/?url=";
+const DEFAULT_USER_AGENT: &str = concat!("catbot/", env!("CARGO_PKG_VERSION"));
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+// Binance caps klines per request at 1000.
+pub const MAX_KLINES_PER_REQUEST: i32 = 1000;
+// Same for aggregate trades.
+const MAX_TRADES_PER_REQUEST: usize = 1000;
+// Open interest and long/short ratio history is capped at 500 periods (and 30 days).
+const MAX_FUTURES_DATA_PER_REQUEST: usize = 500;
 
+/// Token symbol and its Binance USDT pair, e.g. "SOL_USDC" gives `("SOL", "SOLUSDT")`.
+/// Use `PairSymbol` to keep the quote.
 pub fn get_token_and_pair_symbol_usdt(pair_symbol: &str) -> (String, String) {
-    let token_symbol = pair_symbol.split("_").next().unwrap();
-    let token_symbol = token_symbol.split("USD").next().unwrap();
+    let token_symbol = pair_symbol
+        .parse::<PairSymbol>()
+        .map(|pair_symbol| pair_symbol.base)
+        .unwrap_or_else(|_| pair_symbol.trim().to_uppercase());
 
     // We need USDT orderbook
     let binance_pair_symbol = format!("{token_symbol}USDT");
-    (token_symbol.to_string(), binance_pair_symbol)
+    (token_symbol, binance_pair_symbol)
+}
+
+/// Binance spot REST client.
+///
+/// Holds one `reqwest::Client` for every call. Requests go to `{proxy_prefix}{base_url}/...`
+/// so the default still routes through our edge proxy, which Binance doesn't block.
+#[derive(Debug, Clone)]
+pub struct BinanceClient {
+    base_url: String,
+    futures_base_url: String,
+    proxy_prefix: Option<String>,
+    user_agent: String,
+    timeout: Duration,
+    client: Client,
+}
+
+impl Default for BinanceClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BinanceClient {
+    pub fn new() -> Self {
+        BinanceClient {
+            base_url: BINANCE_API_URL.to_string(),
+            futures_base_url: BINANCE_FUTURES_API_URL.to_string(),
+            proxy_prefix: Some(DEFAULT_PROXY_PREFIX.to_string()),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            client: Client::new(),
+        }
+    }
+
+    /// Builds a client from worker vars (or any other key/value lookup).
+    ///
+    /// * `BINANCE_API_URL` - REST base URL, e.g. a local mock server.
+    /// * `BINANCE_FUTURES_API_URL` - USDⓈ-M futures REST base URL.
+    /// * `BINANCE_PROXY_URL` - proxy prefix; set it empty to call `BINANCE_API_URL` directly.
+    /// * `BINANCE_USER_AGENT` - user agent header.
+    /// * `BINANCE_TIMEOUT_MS` - per-request timeout in milliseconds.
+    ///
+    /// Unset vars keep their defaults.
+    pub fn from_env_vars<F>(lookup: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut client = Self::new();
+
+        if let Some(base_url) = lookup("BINANCE_API_URL") {
+            client = client.with_base_url(&base_url);
+        }
+        if let Some(futures_base_url) = lookup("BINANCE_FUTURES_API_URL") {
+            client = client.with_futures_base_url(&futures_base_url);
+        }
+        if let Some(proxy_prefix) = lookup("BINANCE_PROXY_URL") {
+            client = client.with_proxy(Some(proxy_prefix.as_str()));
+        }
+        if let Some(user_agent) = lookup("BINANCE_USER_AGENT") {
+            client = client.with_user_agent(&user_agent);
+        }
+        if let Some(timeout_ms) = lookup("BINANCE_TIMEOUT_MS") {
+            let timeout_ms = timeout_ms
+                .trim()
+                .parse::<u64>()
+                .with_context(|| format!("Invalid BINANCE_TIMEOUT_MS: {timeout_ms}"))?;
+            client = client.with_timeout(Duration::from_millis(timeout_ms));
+        }
+
+        Ok(client)
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the USDⓈ-M futures base URL, used for funding, open interest and long/short ratios.
+    pub fn with_futures_base_url(mut self, futures_base_url: &str) -> Self {
+        self.futures_base_url = futures_base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the proxy prefix the full Binance URL is appended to. `None` or an empty prefix disables it.
+    pub fn with_proxy(mut self, proxy_prefix: Option<&str>) -> Self {
+        self.proxy_prefix = proxy_prefix
+            .filter(|prefix| !prefix.trim().is_empty())
+            .map(str::to_string);
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reuses an existing HTTP client, e.g. one shared with other services.
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn url(&self, path_and_query: &str) -> String {
+        self.proxied(format!("{}/{path_and_query}", self.base_url))
+    }
+
+    fn futures_url(&self, path_and_query: &str) -> String {
+        self.proxied(format!("{}/{path_and_query}", self.futures_base_url))
+    }
+
+    fn proxied(&self, url: String) -> String {
+        match &self.proxy_prefix {
+            Some(proxy_prefix) => format!("{proxy_prefix}{url}"),
+            None => url,
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T> {
+        self.get_json_from(self.url(path_and_query)).await
+    }
+
+    async fn get_futures_json<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T> {
+        self.get_json_from(self.futures_url(path_and_query)).await
+    }
+
+    async fn get_json_from<T: DeserializeOwned>(&self, url: String) -> Result<T> {
+        println!("Fetching data from: {url}");
+
+        let response = self
+            .client
+            .get(&url)
+            .header(USER_AGENT, &self.user_agent)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .context("Failed to send request to Binance API")?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!("Binance API error: {:?}", response.status()));
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse JSON response from Binance API")
+    }
+
+    pub async fn fetch_klines<T>(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<T>>
+    where
+        T: DeserializeOwned + Send + TryFrom<Kline>,
+        T::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let binance_pair_symbol = Venue::Binance.format_symbol(pair_symbol)?;
+        let interval: Interval = interval.parse()?;
+
+        let kline_data: Vec<Kline> = if limit <= MAX_KLINES_PER_REQUEST {
+            self.get_json(&format!(
+                "uiKlines?limit={limit}&symbol={binance_pair_symbol}&interval={interval}"
+            ))
+            .await?
+        } else {
+            self.fetch_latest_klines_paged(&binance_pair_symbol, interval, limit)
+                .await?
+        };
+
+        kline_data
+            .into_iter()
+            .map(|kline| T::try_from(kline).map_err(anyhow::Error::from))
+            .collect()
+    }
+
+    // Walks backwards from now with `endTime` until `limit` klines are collected.
+    async fn fetch_latest_klines_paged(
+        &self,
+        binance_pair_symbol: &str,
+        interval: Interval,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        let mut pages: Vec<Kline> = Vec::new();
+        let mut end_time: Option<i64> = None;
+
+        while (pages.len() as i32) < limit {
+            let page_limit = (limit - pages.len() as i32).min(MAX_KLINES_PER_REQUEST);
+            let mut path = format!(
+                "uiKlines?limit={page_limit}&symbol={binance_pair_symbol}&interval={interval}"
+            );
+            if let Some(end_time) = end_time {
+                path.push_str(&format!("&endTime={end_time}"));
+            }
+
+            let page: Vec<Kline> = self.get_json(&path).await?;
+            let Some(first) = page.first() else {
+                break;
+            };
+            end_time = Some(first.open_time - 1);
+            let is_last_page = (page.len() as i32) < page_limit;
+            pages.extend(page);
+
+            if is_last_page {
+                break;
+            }
+        }
+
+        let mut klines = dedupe_klines(pages);
+        let skip = klines.len().saturating_sub(limit as usize);
+        Ok(klines.split_off(skip))
+    }
+
+    /// Fetches every kline whose `open_time` is in `[start_time, end_time)` (ms), paging with
+    /// `startTime`/`endTime` as needed. Overlapping candles are deduped and the result is sorted.
+    pub async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        let binance_pair_symbol = Venue::Binance.format_symbol(pair_symbol)?;
+        let interval: Interval = interval.parse()?;
+
+        let mut pages: Vec<Kline> = Vec::new();
+        let mut cursor = start_time;
+
+        while cursor < end_time {
+            let page: Vec<Kline> = self
+                .get_json(&format!(
+                    "uiKlines?limit={MAX_KLINES_PER_REQUEST}&symbol={binance_pair_symbol}&interval={interval}&startTime={cursor}&endTime={}",
+                    end_time - 1
+                ))
+                .await
+                .with_context(|| {
+                    format!("Failed to fetch {binance_pair_symbol} {interval} klines from {cursor}")
+                })?;
+
+            let Some(last_open_time) = page.last().map(|kline| kline.open_time) else {
+                break;
+            };
+            let is_last_page = (page.len() as i32) < MAX_KLINES_PER_REQUEST;
+            pages.extend(page);
+
+            // Guard against a venue echoing the same page back.
+            if is_last_page || last_open_time < cursor {
+                break;
+            }
+            cursor = last_open_time + 1;
+        }
+
+        let klines = dedupe_klines(pages)
+            .into_iter()
+            .filter(|kline| kline.open_time >= start_time && kline.open_time < end_time)
+            .collect();
+
+        Ok(klines)
+    }
+
+    pub async fn fetch_orderbook_depth(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        let binance_pair_symbol = Venue::Binance.format_symbol(pair_symbol)?;
+
+        self.get_json(&format!("depth?symbol={binance_pair_symbol}&limit={limit}"))
+            .await
+    }
+
+    /// Fetches the latest aggregate trades since `start_time` (ms), at most `limit`, oldest first.
+    ///
+    /// Binance only takes a time window of up to an hour, so this walks backwards from the
+    /// most recent trade with `fromId` instead.
+    pub async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        let binance_pair_symbol = Venue::Binance.format_symbol(pair_symbol)?;
+
+        let mut pages: Vec<AggTrade> = Vec::new();
+        let mut first_id: Option<i64> = None;
+        while pages.len() < limit {
+            let page_limit = (limit - pages.len()).min(MAX_TRADES_PER_REQUEST);
+            let mut path = format!("aggTrades?symbol={binance_pair_symbol}&limit={page_limit}");
+            if let Some(first_id) = first_id {
+                if first_id == 0 {
+                    break;
+                }
+                let from_id = (first_id - page_limit as i64).max(0);
+                path.push_str(&format!("&fromId={from_id}"));
+            }
+
+            let page: Vec<AggTrade> = self.get_json(&path).await.with_context(|| {
+                format!("Failed to fetch {binance_pair_symbol} aggregate trades")
+            })?;
+            let Some(first) = page.first() else {
+                break;
+            };
+            let reached_start = first.time < start_time;
+            // Guard against a venue echoing the same page back.
+            if first_id.is_some_and(|first_id| first.id >= first_id) {
+                break;
+            }
+            first_id = Some(first.id);
+            pages.extend(page);
+
+            if reached_start {
+                break;
+            }
+        }
+
+        let mut trades: Vec<AggTrade> = pages
+            .into_iter()
+            .filter(|trade| trade.time >= start_time)
+            .collect();
+        trades.sort_by_key(|trade| trade.id);
+        trades.dedup_by_key(|trade| trade.id);
+        let skip = trades.len().saturating_sub(limit);
+        Ok(trades.split_off(skip))
+    }
+
+    /// Fetches the latest `limit` open interest and top-trader long/short position ratio
+    /// readings at `period` (e.g. "1h", up to 500 and 30 days back), with the funding rates
+    /// settled over the same window. Falls back to the last settlement when none fell within it.
+    pub async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        let binance_pair_symbol = Venue::Binance.format_symbol(pair_symbol)?;
+        let period: Interval = period.parse()?;
+        let limit = limit.clamp(1, MAX_FUTURES_DATA_PER_REQUEST);
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let window_start = now - limit as i64 * period.duration_ms();
+        let context =
+            || format!("Failed to fetch {binance_pair_symbol} futures metrics at {period}");
+
+        let open_interest_path = format!(
+            "futures/data/openInterestHist?symbol={binance_pair_symbol}&period={period}&limit={limit}"
+        );
+        let long_short_ratio_path = format!(
+            "futures/data/topLongShortPositionRatio?symbol={binance_pair_symbol}&period={period}&limit={limit}"
+        );
+        let funding_rate_path = format!(
+            "fapi/v1/fundingRate?symbol={binance_pair_symbol}&startTime={window_start}&limit=1000"
+        );
+        let (open_interest, long_short_ratios, mut funding_rates) = futures::try_join!(
+            self.get_futures_json::<Vec<OpenInterest>>(&open_interest_path),
+            self.get_futures_json::<Vec<LongShortRatio>>(&long_short_ratio_path),
+            self.get_futures_json::<Vec<FundingRate>>(&funding_rate_path),
+        )
+        .with_context(context)?;
+
+        if funding_rates.is_empty() {
+            funding_rates = self
+                .get_futures_json(&format!(
+                    "fapi/v1/fundingRate?symbol={binance_pair_symbol}&limit=1"
+                ))
+                .await
+                .with_context(context)?;
+        }
+
+        Ok(FuturesMetrics {
+            funding_rates,
+            open_interest,
+            long_short_ratios,
+        })
+    }
+
+    pub async fn fetch_ticker_price(&self, pair_symbol: &str) -> Result<f64> {
+        let binance_pair_symbol = Venue::Binance.format_symbol(pair_symbol)?;
+
+        let ticker: TickerPrice = self
+            .get_json(&format!("ticker/price?symbol={binance_pair_symbol}"))
+            .await?;
+
+        ticker.price.parse::<f64>().with_context(|| {
+            format!(
+                "Invalid ticker price for {binance_pair_symbol}: {}",
+                ticker.price
+            )
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TickerPrice {
+    price: String,
+}
+
+impl MarketDataSource for BinanceClient {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        BinanceClient::fetch_klines::<Kline>(self, pair_symbol, interval, limit).await
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        BinanceClient::fetch_klines_range(self, pair_symbol, interval, start_time, end_time).await
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        self.fetch_orderbook_depth(pair_symbol, limit).await
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        self.fetch_ticker_price(pair_symbol).await
+    }
+
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        BinanceClient::fetch_agg_trades(self, pair_symbol, start_time, limit).await
+    }
+
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        BinanceClient::fetch_futures_metrics(self, pair_symbol, period, limit).await
+    }
 }
 
 pub async fn fetch_binance_kline_usdt<T>(
@@ -21,53 +486,40 @@ pub async fn fetch_binance_kline_usdt<T>(
     limit: i32,
 ) -> Result<Vec<T>>
 where
-    T: serde::de::DeserializeOwned + Send + std::convert::From<Kline>,
+    T: serde::de::DeserializeOwned + Send + TryFrom<Kline>,
+    T::Error: std::error::Error + Send + Sync + 'static,
 {
-    let (_, binance_pair_symbol) = get_token_and_pair_symbol_usdt(pair_symbol);
-
-    let client = Client::new();
-    // let current_time = Utc::now().timestamp_millis();
-    // https://adversely-amazing-wildcat.edgecompute.app/?url=https://data-api.binance.vision/api/v3/uiKlines?limit=1&symbol=SOLUSDT&interval=1s
-
-    let url = format!(
-        "https://adversely-amazing-wildcat.edgecompute.app/?url={BINANCE_API_URL}/uiKlines?limit={limit}&symbol={binance_pair_symbol}&interval={interval}"
-    );
-
-    println!("Fetching data from: {url}");
-
-    let response = client
-        .get(&url)
-        .send()
+    BinanceClient::default()
+        .fetch_klines(pair_symbol, interval, limit)
         .await
-        .context("Failed to send request to Binance API")?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!("Binance API error: {:?}", response.status()));
-    }
+}
 
-    let kline_data: Vec<Kline> = response
-        .json()
+/// Fetches klines for `[start_time, end_time)` (ms) across as many pages as needed.
+pub async fn fetch_binance_kline_range_usdt(
+    pair_symbol: &str,
+    interval: &str,
+    start_time: i64,
+    end_time: i64,
+) -> Result<Vec<Kline>> {
+    BinanceClient::default()
+        .fetch_klines_range(pair_symbol, interval, start_time, end_time)
         .await
-        .context("Failed to parse JSON response from Binance API")?;
-
-    let concise_kline_data: Vec<T> = kline_data.into_iter().map(|kline| kline.into()).collect();
+}
 
-    Ok(concise_kline_data)
+/// Sorts klines by `open_time` and drops duplicates, keeping the last one seen
+/// (later pages carry the most recent state of a still-open candle).
+pub fn dedupe_klines(klines: Vec<Kline>) -> Vec<Kline> {
+    let by_open_time: BTreeMap<i64, Kline> = klines
+        .into_iter()
+        .map(|kline| (kline.open_time, kline))
+        .collect();
+    by_open_time.into_values().collect()
 }
 
 pub async fn fetch_orderbook_depth_usdt(pair_symbol: &str, limit: i32) -> Result<OrderBook> {
-    // We need USDT orderbook
-    let (_, binance_pair_symbol) = get_token_and_pair_symbol_usdt(pair_symbol);
-
-    let client = Client::new();
-    // https://adversely-amazing-wildcat.edgecompute.app/?url=https://api.binance.com/api/v3/depth?symbol=SOLUSDT&limit=1
-    let url = format!(
-        "https://adversely-amazing-wildcat.edgecompute.app/?url={BINANCE_API_URL}/depth?symbol={binance_pair_symbol}&limit={limit}"
-    );
-    let response = client.get(&url).send().await?;
-    let orderbook_data: OrderBook = response.json().await?;
-
-    Ok(orderbook_data)
+    BinanceClient::default()
+        .fetch_orderbook_depth(pair_symbol, limit)
+        .await
 }
 
 /// Fetches Binance Kline data for a given pair symbol, interval, and limit, and returns it as a CSV string.
@@ -95,55 +547,68 @@ pub async fn fetch_binance_kline_usdt_csv(
         .await
         .with_context(|| format!("Failed to fetch Kline data for {binance_pair_symbol} with interval {interval} and limit {limit}"))?;
 
-    // Build CSV string manually
-    let mut csv_string = String::new();
+    klines_to_csv(&kline_data)
+}
+
+/// Parses `klines` into candles and renders them with `candles_to_csv`.
+///
+/// Fails with the `CandleError` of the first malformed kline.
+pub fn klines_to_csv(klines: &[Kline]) -> anyhow::Result<String> {
+    Ok(candles_to_csv(&Candle::from_klines(klines)?))
+}
+
+/// Same as `klines_to_csv`, with the taker pressure columns appended (see `taker_pressure`).
+pub fn klines_with_taker_pressure_to_csv(klines: &[Kline]) -> anyhow::Result<String> {
+    let candles = Candle::from_klines(klines)?;
+    let pressure = taker_pressure(klines)?;
+    Ok(candles_with_taker_pressure_to_csv(&candles, &pressure))
+}
 
+pub fn candles_to_csv(candles: &[Candle]) -> String {
+    let mut csv_string = String::new();
     // Add header
     csv_string.push_str("open_time,open,high,low,close,volume,close_time\n");
 
-    // Add each Kline record
-    for kline in kline_data {
-        let values = kline.to_array()?;
-        let mut is_first = true;
-        for value in values {
-            if !is_first {
-                csv_string.push(',');
-            }
-            if let JsonValue::Number(n) = value {
-                csv_string.push_str(&n.to_string());
-            }
-            is_first = false;
-        }
-        csv_string.push('\n');
+    for candle in candles {
+        csv_string.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            candle.open_time,
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            candle.volume,
+            candle.close_time
+        ));
     }
-
-    Ok(csv_string)
+    csv_string
 }
 
-pub fn klines_to_csv(klines: &[Kline]) -> anyhow::Result<String> {
+/// `candles_to_csv` with the taker pressure of each candle appended, `pressure` is matched by
+/// position.
+pub fn candles_with_taker_pressure_to_csv(
+    candles: &[Candle],
+    pressure: &[TakerPressure],
+) -> String {
     let mut csv_string = String::new();
-    // Add header
-    csv_string.push_str("open_time,open,high,low,close,volume,close_time\n");
+    csv_string.push_str(&format!(
+        "open_time,open,high,low,close,volume,close_time,{TAKER_PRESSURE_CSV_COLUMNS}\n"
+    ));
 
-    // Add each Kline record
-    for kline in klines {
-        let values = kline.to_array()?;
-        let mut is_first = true;
-        for value in values {
-            if !is_first {
-                csv_string.push(',');
-            }
-            // Handle potential non-number values gracefully if Kline::to_array can return them
-            match value {
-                JsonValue::Number(n) => csv_string.push_str(&n.to_string()),
-                JsonValue::String(s) => csv_string.push_str(&s), // Or handle as error if only numbers expected
-                _ => csv_string.push_str(""), // Or some placeholder/error indication
-            }
-            is_first = false;
-        }
-        csv_string.push('\n');
+    for (candle, pressure) in candles.iter().zip(pressure) {
+        csv_string.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            candle.open_time,
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            candle.volume,
+            candle.close_time,
+            pressure.csv_cells()
+        ));
     }
-    Ok(csv_string)
+    csv_string
 }
 
 #[cfg(test)]
@@ -159,6 +624,241 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_binance_client_from_env_vars() {
+        let client = BinanceClient::from_env_vars(|key| match key {
+            "BINANCE_API_URL" => Some("http://localhost:8787/api/v3/".to_string()),
+            "BINANCE_PROXY_URL" => Some("".to_string()),
+            "BINANCE_TIMEOUT_MS" => Some("2500".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert_eq!(
+            client.url("depth?symbol=SOLUSDT&limit=5"),
+            "http://localhost:8787/api/v3/depth?symbol=SOLUSDT&limit=5"
+        );
+        assert_eq!(client.timeout, Duration::from_millis(2500));
+        assert_eq!(client.user_agent, DEFAULT_USER_AGENT);
+
+        // Defaults keep routing through the edge proxy.
+        assert_eq!(
+            BinanceClient::default().url("uiKlines?limit=1&symbol=SOLUSDT&interval=1s"),
+            "https://adversely-amazing-wildcat.edgecompute.app/?url=https://data-api.binance.vision/api/v3/uiKlines?limit=1&symbol=SOLUSDT&interval=1s"
+        );
+
+        assert!(BinanceClient::from_env_vars(|key| {
+            (key == "BINANCE_TIMEOUT_MS").then(|| "soon".to_string())
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_binance_client_against_stub() {
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(|path| {
+            if path.starts_with("/api/v3/uiKlines") {
+                (200, r#"[[1745971200000,"146.10","147.00","145.80","146.50","1200.5",1745974799999,"175000.1",900,"600.2","87500.3","0"]]"#.to_string())
+            } else if path.starts_with("/api/v3/depth") {
+                (200, r#"{"lastUpdateId":42,"bids":[["146.49","10.0"]],"asks":[["146.51","12.0"]]}"#.to_string())
+            } else if path.starts_with("/api/v3/ticker/price") {
+                (200, r#"{"symbol":"SOLUSDT","price":"146.50000000"}"#.to_string())
+            } else {
+                (404, "{}".to_string())
+            }
+        })
+        .await;
+
+        let client = BinanceClient::new()
+            .with_base_url(&format!("{base_url}/api/v3"))
+            .with_proxy(None);
+
+        let klines = client
+            .fetch_klines::<Kline>("SOL_USDT", "1h", 1)
+            .await
+            .unwrap();
+        assert_eq!(klines[0].close_price, "146.50");
+
+        let orderbook = client.fetch_orderbook_depth("SOL_USDT", 5).await.unwrap();
+        assert_eq!(orderbook.last_update_id, 42);
+
+        assert_eq!(client.fetch_current_price("SOL").await.unwrap(), 146.5);
+
+        assert_eq!(
+            requests.lock().unwrap()[0],
+            "/api/v3/uiKlines?limit=1&symbol=SOLUSDT&interval=1h"
+        );
+    }
+
+    // Serves hourly candles like `uiKlines`, but starts each page one candle early so pages overlap.
+    fn overlapping_klines_stub(path: &str) -> (u16, String) {
+        const HOUR: i64 = 3_600_000;
+        let param = |name: &str| -> Option<i64> {
+            path.split(['?', '&'])
+                .find_map(|pair| pair.strip_prefix(&format!("{name}=")))
+                .and_then(|value| value.parse().ok())
+        };
+        let limit = param("limit").unwrap_or(500);
+        let end_time = param("endTime").unwrap_or(i64::MAX);
+        let start_time = param("startTime").unwrap_or(0);
+        let first_open_time = (start_time / HOUR) * HOUR - HOUR;
+
+        let klines: Vec<String> = (0..limit)
+            .map(|i| first_open_time + i * HOUR)
+            .take_while(|open_time| *open_time <= end_time)
+            .map(|open_time| {
+                format!(
+                    r#"[{open_time},"1.0","1.0","1.0","1.0","1.0",{},"1.0",1,"0.5","0.5","0"]"#,
+                    open_time + HOUR - 1
+                )
+            })
+            .collect();
+        (200, format!("[{}]", klines.join(",")))
+    }
+
+    #[tokio::test]
+    async fn test_fetch_klines_range_pages_and_dedupes() {
+        const HOUR: i64 = 3_600_000;
+        let (base_url, requests) =
+            crate::test_utils::spawn_http_stub(overlapping_klines_stub).await;
+        let client = BinanceClient::new()
+            .with_base_url(&base_url)
+            .with_proxy(None);
+
+        let start_time = 1_700_000_000_000 / HOUR * HOUR;
+        let end_time = start_time + 2500 * HOUR;
+        let klines = client
+            .fetch_klines_range("SOL_USDT", "1h", start_time, end_time)
+            .await
+            .unwrap();
+
+        assert_eq!(klines.len(), 2500);
+        assert_eq!(klines[0].open_time, start_time);
+        assert_eq!(klines[2499].open_time, end_time - HOUR);
+        assert!(klines
+            .windows(2)
+            .all(|w| w[1].open_time - w[0].open_time == HOUR));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_agg_trades_walks_back_by_id() {
+        // Trade ids 0..2500, one a second, newest first without `fromId`.
+        const START: i64 = 1_700_000_000_000;
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(|path| {
+            let param = |name: &str| -> Option<i64> {
+                path.split(['?', '&'])
+                    .find_map(|pair| pair.strip_prefix(&format!("{name}=")))
+                    .and_then(|value| value.parse().ok())
+            };
+            let limit = param("limit").unwrap_or(500);
+            let from_id = param("fromId").unwrap_or(2500 - limit);
+            let trades: Vec<String> = (from_id..(from_id + limit).min(2500))
+                .map(|id| {
+                    format!(
+                        r#"{{"a":{id},"p":"150.0","q":"1.0","f":{id},"l":{id},"T":{},"m":false,"M":true}}"#,
+                        START + id * 1000
+                    )
+                })
+                .collect();
+            (200, format!("[{}]", trades.join(",")))
+        })
+        .await;
+        let client = BinanceClient::new()
+            .with_base_url(&base_url)
+            .with_proxy(None);
+
+        let trades = client
+            .fetch_agg_trades("SOL_USDT", START + 1200 * 1000, 5000)
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 1300);
+        assert_eq!(trades[0].id, 1200);
+        assert!(trades.windows(2).all(|w| w[1].id == w[0].id + 1));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(
+            requests.lock().unwrap()[1],
+            "/aggTrades?symbol=SOLUSDT&limit=1000&fromId=500"
+        );
+
+        // Capped at the latest `limit` trades.
+        let trades = client
+            .fetch_agg_trades("SOL_USDT", START, 1500)
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 1500);
+        assert_eq!(trades[0].id, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_futures_metrics_against_stub() {
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(|path| {
+            if path.starts_with("/futures/data/openInterestHist") {
+                (200, r#"[{"symbol":"SOLUSDT","sumOpenInterest":"1000.5","sumOpenInterestValue":"146573.25","CMCCirculatingSupply":"1","timestamp":1746014400000}]"#.to_string())
+            } else if path.starts_with("/futures/data/topLongShortPositionRatio") {
+                (200, r#"[{"symbol":"SOLUSDT","longShortRatio":"2.1","longAccount":"0.6774","shortAccount":"0.3226","timestamp":1746014400000}]"#.to_string())
+            } else if path.contains("startTime") {
+                // Nothing settled within the window.
+                (200, "[]".to_string())
+            } else if path.starts_with("/fapi/v1/fundingRate") {
+                (200, r#"[{"symbol":"SOLUSDT","fundingTime":1746000000000,"fundingRate":"0.00010000","markPrice":"146.40"}]"#.to_string())
+            } else {
+                (404, "{}".to_string())
+            }
+        })
+        .await;
+        let client = BinanceClient::new()
+            .with_base_url(&format!("{base_url}/api/v3"))
+            .with_futures_base_url(&base_url)
+            .with_proxy(None);
+
+        let metrics = client
+            .fetch_futures_metrics("SOL_USDT", "1h", 24)
+            .await
+            .unwrap();
+        assert_eq!(metrics.open_interest[0].sum_open_interest, "1000.5");
+        assert_eq!(metrics.long_short_ratios[0].long_short_ratio, "2.1");
+        assert_eq!(metrics.funding_rates[0].funding_rate, "0.00010000");
+
+        // Unknown periods are refused before any request.
+        assert!(client
+            .fetch_futures_metrics("SOL_USDT", "1x", 24)
+            .await
+            .is_err());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(requests.contains(
+            &"/futures/data/openInterestHist?symbol=SOLUSDT&period=1h&limit=24".to_string()
+        ));
+        assert_eq!(requests[3], "/fapi/v1/fundingRate?symbol=SOLUSDT&limit=1");
+    }
+
+    #[test]
+    fn test_dedupe_klines() {
+        let kline = |open_time: i64, close: &str| Kline {
+            open_time,
+            open_price: "1".to_string(),
+            high_price: "1".to_string(),
+            low_price: "1".to_string(),
+            close_price: close.to_string(),
+            volume: "1".to_string(),
+            close_time: open_time + 59_999,
+            quote_asset_volume: String::new(),
+            number_of_trades: 0,
+            taker_buy_base_asset_volume: String::new(),
+            taker_buy_quote_asset_volume: String::new(),
+            ignore: String::new(),
+        };
+
+        let klines = dedupe_klines(vec![
+            kline(120_000, "3"),
+            kline(60_000, "1"),
+            kline(120_000, "4"),
+        ]);
+        let closes: Vec<_> = klines.iter().map(|k| k.close_price.as_str()).collect();
+        assert_eq!(closes, ["1", "4"]);
+    }
+
     #[tokio::test]
     async fn test() {
         use crate::ConciseKline;
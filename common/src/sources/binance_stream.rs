@@ -0,0 +1,422 @@
+use anyhow::{anyhow, bail, Context, Result};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::{net::TcpStream, sync::mpsc};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::{venue::Venue, DepthUpdate, Kline};
+
+const BINANCE_STREAM_URL: &str = "wss://stream.binance.com:9443";
+
+/// An update pushed by `BinanceStream`.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Sent on every (re)connect. Anything derived from diffs, like a local order book, must resync.
+    Connected,
+    /// The current state of a candle, `is_closed` is true once the candle is final.
+    Kline {
+        symbol: String,
+        interval: String,
+        kline: Kline,
+        is_closed: bool,
+    },
+    Depth(DepthUpdate),
+}
+
+/// Binance kline and diff-depth websocket client.
+///
+/// Subscribes to a combined stream, reconnects with exponential backoff when the socket drops
+/// and pings the server when idle. If no frame arrives within `heartbeat_timeout` the connection
+/// is treated as dead and replaced.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use common::binance_stream::{BinanceStream, StreamEvent};
+///
+/// let mut events = BinanceStream::new()
+///     .with_kline("SOL_USDT", "1m")
+///     .with_depth("SOL_USDT")
+///     .spawn()?;
+///
+/// while let Some(event) = events.recv().await {
+///     if let StreamEvent::Kline { kline, .. } = event {
+///         println!("{} {}", kline.open_time, kline.volume);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BinanceStream {
+    base_url: String,
+    streams: Vec<String>,
+    ping_interval: Duration,
+    heartbeat_timeout: Duration,
+    reconnect_delay: Duration,
+    max_reconnect_delay: Duration,
+    max_reconnects: Option<u32>,
+}
+
+impl Default for BinanceStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BinanceStream {
+    pub fn new() -> Self {
+        BinanceStream {
+            base_url: BINANCE_STREAM_URL.to_string(),
+            streams: Vec::new(),
+            ping_interval: Duration::from_secs(20),
+            heartbeat_timeout: Duration::from_secs(60),
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnect_delay: Duration::from_secs(30),
+            max_reconnects: None,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Subscribes to `<symbol>@kline_<interval>`.
+    pub fn with_kline(mut self, pair_symbol: &str, interval: &str) -> Self {
+        let symbol = stream_symbol(pair_symbol);
+        self.streams.push(format!("{symbol}@kline_{interval}"));
+        self
+    }
+
+    /// Subscribes to `<symbol>@depth@100ms` diff-depth updates.
+    pub fn with_depth(mut self, pair_symbol: &str) -> Self {
+        let symbol = stream_symbol(pair_symbol);
+        self.streams.push(format!("{symbol}@depth@100ms"));
+        self
+    }
+
+    /// How long the socket may stay quiet before we send a ping.
+    pub fn with_ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = ping_interval;
+        self
+    }
+
+    /// How long without any frame (data, ping or pong) before reconnecting.
+    pub fn with_heartbeat_timeout(mut self, heartbeat_timeout: Duration) -> Self {
+        self.heartbeat_timeout = heartbeat_timeout;
+        self
+    }
+
+    /// First reconnect delay, doubled on each failed attempt up to `max_delay`.
+    pub fn with_reconnect_delay(mut self, delay: Duration, max_delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self.max_reconnect_delay = max_delay;
+        self
+    }
+
+    /// Gives up after this many reconnects in a row. Unlimited by default.
+    pub fn with_max_reconnects(mut self, max_reconnects: u32) -> Self {
+        self.max_reconnects = Some(max_reconnects);
+        self
+    }
+
+    fn url(&self) -> String {
+        format!(
+            "{}/stream?streams={}",
+            self.base_url,
+            self.streams.join("/")
+        )
+    }
+
+    /// Starts streaming on a tokio task. The task stops when the receiver is dropped
+    /// or `max_reconnects` is exceeded.
+    pub fn spawn(self) -> Result<mpsc::Receiver<StreamEvent>> {
+        if self.streams.is_empty() {
+            bail!("No streams subscribed, call with_kline or with_depth first");
+        }
+
+        let (sender, receiver) = mpsc::channel(1024);
+        tokio::spawn(async move {
+            if let Err(e) = self.run(sender).await {
+                eprintln!("Binance stream stopped: {e:?}");
+            }
+        });
+
+        Ok(receiver)
+    }
+
+    async fn run(self, sender: mpsc::Sender<StreamEvent>) -> Result<()> {
+        let url = self.url();
+        let mut delay = self.reconnect_delay;
+        let mut attempts = 0;
+
+        loop {
+            match connect_async(url.as_str()).await {
+                Ok((socket, _)) => {
+                    println!("Binance stream connected: {url}");
+                    delay = self.reconnect_delay;
+                    attempts = 0;
+
+                    if sender.send(StreamEvent::Connected).await.is_err() {
+                        return Ok(());
+                    }
+                    if let Err(e) = self.read_until_dropped(socket, &sender).await {
+                        eprintln!("Binance stream disconnected: {e}");
+                    }
+                    if sender.is_closed() {
+                        return Ok(());
+                    }
+                }
+                Err(e) => eprintln!("Binance stream connect failed: {e}"),
+            }
+
+            attempts += 1;
+            if self.max_reconnects.is_some_and(|max| attempts > max) {
+                return Err(anyhow!("Gave up after {} reconnects", attempts - 1));
+            }
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(self.max_reconnect_delay);
+        }
+    }
+
+    // Returns once the connection is closed, errors out, or misses its heartbeat.
+    async fn read_until_dropped(
+        &self,
+        mut socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+        sender: &mpsc::Sender<StreamEvent>,
+    ) -> Result<()> {
+        let mut last_frame_at = Instant::now();
+
+        loop {
+            let message = match tokio::time::timeout(self.ping_interval, socket.next()).await {
+                Ok(Some(message)) => message.context("Failed to read frame")?,
+                Ok(None) => return Ok(()),
+                Err(_) => {
+                    if last_frame_at.elapsed() >= self.heartbeat_timeout {
+                        bail!("No frame for {:?}", last_frame_at.elapsed());
+                    }
+                    socket
+                        .send(Message::Ping(Default::default()))
+                        .await
+                        .context("Failed to send ping")?;
+                    continue;
+                }
+            };
+            last_frame_at = Instant::now();
+
+            match message {
+                Message::Text(text) => match parse_stream_message(text.as_str()) {
+                    Ok(Some(event)) => {
+                        if sender.send(event).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Skipping unparsable stream message: {e}"),
+                },
+                Message::Ping(payload) => {
+                    socket
+                        .send(Message::Pong(payload))
+                        .await
+                        .context("Failed to send pong")?;
+                }
+                Message::Close(frame) => {
+                    println!("Binance stream closed by server: {frame:?}");
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Stream names use the lowercase venue symbol, e.g. `solusdt@kline_1m`.
+fn stream_symbol(pair_symbol: &str) -> String {
+    Venue::Binance
+        .format_symbol(pair_symbol)
+        .unwrap_or_else(|_| pair_symbol.to_string())
+        .to_lowercase()
+}
+
+#[derive(Deserialize)]
+struct CombinedMessage {
+    stream: String,
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct KlineMessage {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "k")]
+    kline: KlinePayload,
+}
+
+#[derive(Deserialize)]
+struct KlinePayload {
+    #[serde(rename = "t")]
+    open_time: i64,
+    #[serde(rename = "T")]
+    close_time: i64,
+    #[serde(rename = "i")]
+    interval: String,
+    #[serde(rename = "o")]
+    open_price: String,
+    #[serde(rename = "h")]
+    high_price: String,
+    #[serde(rename = "l")]
+    low_price: String,
+    #[serde(rename = "c")]
+    close_price: String,
+    #[serde(rename = "v")]
+    volume: String,
+    #[serde(rename = "n")]
+    number_of_trades: i64,
+    #[serde(rename = "x")]
+    is_closed: bool,
+    #[serde(rename = "q")]
+    quote_asset_volume: String,
+    #[serde(rename = "V")]
+    taker_buy_base_asset_volume: String,
+    #[serde(rename = "Q")]
+    taker_buy_quote_asset_volume: String,
+}
+
+/// Parses one combined-stream frame. Returns `None` for streams we don't handle.
+pub fn parse_stream_message(text: &str) -> Result<Option<StreamEvent>> {
+    let message: CombinedMessage =
+        serde_json::from_str(text).context("Invalid combined stream message")?;
+
+    if message.stream.contains("@kline_") {
+        let KlineMessage { symbol, kline } = serde_json::from_value(message.data)?;
+        Ok(Some(StreamEvent::Kline {
+            symbol,
+            interval: kline.interval,
+            is_closed: kline.is_closed,
+            kline: Kline {
+                open_time: kline.open_time,
+                open_price: kline.open_price,
+                high_price: kline.high_price,
+                low_price: kline.low_price,
+                close_price: kline.close_price,
+                volume: kline.volume,
+                close_time: kline.close_time,
+                quote_asset_volume: kline.quote_asset_volume,
+                number_of_trades: kline.number_of_trades,
+                taker_buy_base_asset_volume: kline.taker_buy_base_asset_volume,
+                taker_buy_quote_asset_volume: kline.taker_buy_quote_asset_volume,
+                ignore: "0".to_string(),
+            },
+        }))
+    } else if message.stream.contains("@depth") {
+        Ok(Some(StreamEvent::Depth(serde_json::from_value(
+            message.data,
+        )?)))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    const KLINE_MESSAGE: &str = r#"{"stream":"solusdt@kline_1m","data":{"e":"kline","E":1745971260123,"s":"SOLUSDT","k":{"t":1745971200000,"T":1745971259999,"s":"SOLUSDT","i":"1m","f":1,"L":2,"o":"146.10","c":"146.50","h":"147.00","l":"145.80","v":"520000.5","n":900,"x":true,"q":"76000000.1","V":"300000.2","Q":"43900000.3","B":"0"}}}"#;
+    const DEPTH_MESSAGE: &str = r#"{"stream":"solusdt@depth@100ms","data":{"e":"depthUpdate","E":1745971260200,"s":"SOLUSDT","U":101,"u":105,"b":[["146.49","10.0"]],"a":[["146.51","0.0"]]}}"#;
+
+    #[test]
+    fn test_parse_stream_message() {
+        match parse_stream_message(KLINE_MESSAGE).unwrap() {
+            Some(StreamEvent::Kline {
+                symbol,
+                interval,
+                kline,
+                is_closed,
+            }) => {
+                assert_eq!(symbol, "SOLUSDT");
+                assert_eq!(interval, "1m");
+                assert_eq!(kline.volume, "520000.5");
+                assert!(is_closed);
+            }
+            other => panic!("Unexpected event: {other:?}"),
+        }
+
+        match parse_stream_message(DEPTH_MESSAGE).unwrap() {
+            Some(StreamEvent::Depth(update)) => {
+                assert_eq!(update.first_update_id, 101);
+                assert_eq!(update.final_update_id, 105);
+            }
+            other => panic!("Unexpected event: {other:?}"),
+        }
+
+        assert!(
+            parse_stream_message(r#"{"stream":"solusdt@trade","data":{}}"#)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_reconnects_after_close_and_missed_heartbeat() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("ws://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            // 1st connection: push a kline and a depth update, then close.
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+            socket.send(Message::text(KLINE_MESSAGE)).await.unwrap();
+            socket.send(Message::text(DEPTH_MESSAGE)).await.unwrap();
+            socket.close(None).await.unwrap();
+
+            // 2nd connection: accept but never read or answer pings.
+            let (stream, _) = listener.accept().await.unwrap();
+            let _silent = accept_async(stream).await.unwrap();
+
+            // 3rd connection: push one more kline.
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+            socket.send(Message::text(KLINE_MESSAGE)).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut events = BinanceStream::new()
+            .with_base_url(&base_url)
+            .with_kline("SOL_USDT", "1m")
+            .with_depth("SOL_USDT")
+            .with_ping_interval(Duration::from_millis(50))
+            .with_heartbeat_timeout(Duration::from_millis(200))
+            .with_reconnect_delay(Duration::from_millis(10), Duration::from_millis(50))
+            .spawn()
+            .unwrap();
+
+        let mut received = Vec::new();
+        while received.len() < 6 {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .expect("Timed out waiting for stream event")
+                .expect("Stream ended");
+            received.push(match event {
+                StreamEvent::Connected => "connected",
+                StreamEvent::Kline { .. } => "kline",
+                StreamEvent::Depth(_) => "depth",
+            });
+        }
+
+        assert_eq!(
+            received,
+            [
+                "connected",
+                "kline",
+                "depth",
+                "connected",
+                "connected",
+                "kline"
+            ]
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    binance::dedupe_klines,
+    market::MarketDataSource,
+    venue::{get_venue_json, ohlcv_kline, Venue},
+    Interval, Kline, OrderBook,
+};
+
+const BYBIT_API_URL: &str = "https://api.bybit.com";
+// Bybit caps spot klines per request at 1000 and spot depth at 200.
+const MAX_KLINES_PER_REQUEST: i32 = 1000;
+const MAX_DEPTH_LIMIT: i32 = 200;
+
+/// Bybit v5 spot market data, normalised to `Kline`/`OrderBook`.
+#[derive(Debug, Clone)]
+pub struct BybitClient {
+    base_url: String,
+    client: Client,
+}
+
+impl Default for BybitClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BybitClient {
+    pub fn new() -> Self {
+        BybitClient {
+            base_url: BYBIT_API_URL.to_string(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T> {
+        let url = format!("{}/{path_and_query}", self.base_url);
+        let response: BybitResponse<T> = get_venue_json(&self.client, Venue::Bybit, &url).await?;
+        if response.ret_code != 0 {
+            bail!(
+                "Bybit API error {}: {}",
+                response.ret_code,
+                response.ret_msg
+            );
+        }
+        response
+            .result
+            .ok_or_else(|| anyhow!("Bybit API returned no result"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitResponse<T> {
+    ret_code: i64,
+    ret_msg: String,
+    result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitKlines {
+    // [startTime, open, high, low, close, volume, turnover], newest first
+    list: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitOrderBook {
+    b: Vec<Vec<String>>,
+    a: Vec<Vec<String>>,
+    u: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitTickers {
+    list: Vec<BybitTicker>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitTicker {
+    last_price: String,
+}
+
+fn bybit_interval(interval: Interval) -> Result<&'static str> {
+    Ok(match interval {
+        Interval::Minute1 => "1",
+        Interval::Minute3 => "3",
+        Interval::Minute5 => "5",
+        Interval::Minute15 => "15",
+        Interval::Minute30 => "30",
+        Interval::Hour1 => "60",
+        Interval::Hour2 => "120",
+        Interval::Hour4 => "240",
+        Interval::Hour6 => "360",
+        Interval::Hour12 => "720",
+        Interval::Day1 => "D",
+        Interval::Week1 => "W",
+        Interval::Month1 => "M",
+        Interval::Second1 | Interval::Hour8 | Interval::Day3 => {
+            bail!("Bybit doesn't serve {interval} klines")
+        }
+    })
+}
+
+// Rows come newest first, we return oldest first like Binance.
+fn to_klines(rows: Vec<Vec<String>>, interval_millis: i64) -> Result<Vec<Kline>> {
+    let mut klines = rows
+        .into_iter()
+        .map(|row| {
+            let [start, open, high, low, close, volume, turnover]: [String; 7] = row
+                .try_into()
+                .map_err(|row| anyhow!("Unexpected Bybit kline row {row:?}"))?;
+            let open_time = start
+                .parse::<i64>()
+                .with_context(|| format!("Invalid Bybit start time {start}"))?;
+            Ok(ohlcv_kline(
+                open_time,
+                interval_millis,
+                [open, high, low, close, volume],
+                Some(turnover),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    klines.reverse();
+    Ok(klines)
+}
+
+impl MarketDataSource for BybitClient {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        let interval: Interval = interval.parse()?;
+        let interval_millis = interval.duration_ms();
+
+        if limit > MAX_KLINES_PER_REQUEST {
+            let end_time = chrono::Utc::now().timestamp_millis() + interval_millis;
+            let start_time = end_time - (limit as i64 + 1) * interval_millis;
+            let mut klines = self
+                .fetch_klines_range(pair_symbol, interval.as_str(), start_time, end_time)
+                .await?;
+            let skip = klines.len().saturating_sub(limit as usize);
+            return Ok(klines.split_off(skip));
+        }
+
+        let symbol = Venue::Bybit.format_symbol(pair_symbol)?;
+        let result: BybitKlines = self
+            .get(&format!(
+                "v5/market/kline?category=spot&symbol={symbol}&interval={}&limit={limit}",
+                bybit_interval(interval)?
+            ))
+            .await?;
+
+        to_klines(result.list, interval_millis)
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        let symbol = Venue::Bybit.format_symbol(pair_symbol)?;
+        let interval: Interval = interval.parse()?;
+        let bybit_interval = bybit_interval(interval)?;
+        let interval_millis = interval.duration_ms();
+
+        // Bybit pages backwards from `end`.
+        let mut pages: Vec<Kline> = Vec::new();
+        let mut cursor = end_time - 1;
+        while cursor >= start_time {
+            let result: BybitKlines = self
+                .get(&format!(
+                    "v5/market/kline?category=spot&symbol={symbol}&interval={bybit_interval}&start={start_time}&end={cursor}&limit={MAX_KLINES_PER_REQUEST}"
+                ))
+                .await?;
+
+            let is_last_page = (result.list.len() as i32) < MAX_KLINES_PER_REQUEST;
+            let page = to_klines(result.list, interval_millis)?;
+            let Some(oldest_open_time) = page.first().map(|kline| kline.open_time) else {
+                break;
+            };
+            pages.extend(page);
+
+            if is_last_page || oldest_open_time > cursor {
+                break;
+            }
+            cursor = oldest_open_time - 1;
+        }
+
+        Ok(dedupe_klines(pages)
+            .into_iter()
+            .filter(|kline| kline.open_time >= start_time && kline.open_time < end_time)
+            .collect())
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        let symbol = Venue::Bybit.format_symbol(pair_symbol)?;
+        let limit = limit.clamp(1, MAX_DEPTH_LIMIT);
+        let result: BybitOrderBook = self
+            .get(&format!(
+                "v5/market/orderbook?category=spot&symbol={symbol}&limit={limit}"
+            ))
+            .await?;
+
+        Ok(OrderBook {
+            last_update_id: result.u,
+            bids: result.b,
+            asks: result.a,
+        })
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        let symbol = Venue::Bybit.format_symbol(pair_symbol)?;
+        let result: BybitTickers = self
+            .get(&format!("v5/market/tickers?category=spot&symbol={symbol}"))
+            .await?;
+
+        let ticker = result
+            .list
+            .first()
+            .ok_or_else(|| anyhow!("No Bybit ticker for {symbol}"))?;
+        ticker
+            .last_price
+            .parse::<f64>()
+            .with_context(|| format!("Invalid Bybit price {}", ticker.last_price))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bybit_client_against_stub() {
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(|path| {
+            let body = if path.starts_with("/v5/market/kline") {
+                r#"{"retCode":0,"retMsg":"OK","result":{"symbol":"SOLUSDT","category":"spot","list":[["1745974800000","146.50","147.20","146.30","147.00","1500.2","220000.5"],["1745971200000","146.10","147.00","145.80","146.50","1200.5","175000.1"]]}}"#
+            } else if path.starts_with("/v5/market/orderbook") {
+                r#"{"retCode":0,"retMsg":"OK","result":{"s":"SOLUSDT","b":[["146.99","10.5"]],"a":[["147.01","3.2"]],"ts":1745975000000,"u":777}}"#
+            } else if path.starts_with("/v5/market/tickers") {
+                r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","list":[{"symbol":"SOLUSDT","lastPrice":"147.00"}]}}"#
+            } else {
+                r#"{"retCode":10001,"retMsg":"params error","result":null}"#
+            };
+            (200, body.to_string())
+        })
+        .await;
+        let client = BybitClient::new().with_base_url(&base_url);
+
+        let klines = client.fetch_klines("SOL_USDT", "1h", 2).await.unwrap();
+        assert_eq!(klines.len(), 2);
+        assert_eq!(klines[0].open_time, 1745971200000);
+        assert_eq!(klines[0].close_time, 1745974799999);
+        assert_eq!(klines[1].close_price, "147.00");
+        assert_eq!(klines[1].quote_asset_volume, "220000.5");
+
+        let orderbook = client.fetch_orderbook("SOL_USDT", 50).await.unwrap();
+        assert_eq!(orderbook.last_update_id, 777);
+        assert_eq!(orderbook.bids[0], ["146.99", "10.5"]);
+
+        assert_eq!(client.fetch_current_price("SOL").await.unwrap(), 147.0);
+
+        assert!(client.fetch_klines("SOL_USDT", "1s", 2).await.is_err());
+        assert_eq!(
+            requests.lock().unwrap()[0],
+            "/v5/market/kline?category=spot&symbol=SOLUSDT&interval=60&limit=2"
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::{
+    get_key_from_interval, market::MarketDataSource, AggTrade, FuturesMetrics, Interval, Kline,
+    OrderBook,
+};
+
+const DEFAULT_NAMESPACE: &str = "market";
+
+/// A string key-value store with per-entry expiry, e.g. Cloudflare KV (`KvCache`) or `MemoryCache`.
+#[allow(async_fn_in_trait)]
+pub trait CacheStore {
+    /// Returns the value stored at `key`, or `None` when missing or expired.
+    async fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Stores `value` at `key` for `ttl_seconds`.
+    async fn put(&self, key: &str, value: String, ttl_seconds: u64) -> Result<()>;
+}
+
+impl<T: CacheStore> CacheStore for &T {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        (**self).get(key).await
+    }
+
+    async fn put(&self, key: &str, value: String, ttl_seconds: u64) -> Result<()> {
+        (**self).put(key, value, ttl_seconds).await
+    }
+}
+
+// No store configured, e.g. a worker without its KV binding: everything misses.
+impl<T: CacheStore> CacheStore for Option<T> {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        match self {
+            Some(store) => store.get(key).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, key: &str, value: String, ttl_seconds: u64) -> Result<()> {
+        match self {
+            Some(store) => store.put(key, value, ttl_seconds).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// In-process `CacheStore` for native runs and tests.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    // key -> (value, expires at in ms)
+    entries: Mutex<HashMap<String, (String, i64)>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CacheStore for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        let now = Utc::now().timestamp_millis();
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(entries
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(value, _)| value.clone()))
+    }
+
+    async fn put(&self, key: &str, value: String, ttl_seconds: u64) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let expires_at = now.saturating_add((ttl_seconds as i64).saturating_mul(1000));
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (_, expires_at)| *expires_at > now);
+        entries.insert(key.to_string(), (value, expires_at));
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedOrderBook {
+    limit: i32,
+    orderbook: OrderBook,
+}
+
+/// Wraps a `MarketDataSource` so klines and orderbooks are fetched once per candle period.
+///
+/// Entries are keyed by pair, interval and the open time of the current candle
+/// (`get_key_from_interval`) and expire at the next boundary, so every request within the same
+/// period, from any worker sharing the store, reuses the same data. A cached series also serves
+/// smaller limits. Only closed candles are cached, the forming one is fetched on every request
+/// so the latest close stays live. Orderbooks are bucketed by `orderbook_interval` (1m by default).
+///
+/// Prices, trades and futures metrics aren't cached. Store errors are logged and fall through
+/// to `source`.
+pub struct CachedSource<S, C> {
+    source: S,
+    store: C,
+    namespace: String,
+    orderbook_interval: Interval,
+}
+
+impl<S: MarketDataSource, C: CacheStore> CachedSource<S, C> {
+    pub fn new(source: S, store: C) -> Self {
+        CachedSource {
+            source,
+            store,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            orderbook_interval: Interval::Minute1,
+        }
+    }
+
+    /// Prefix for every key, so sources for different venues sharing a store don't collide.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+
+    /// How long an orderbook snapshot is reused for.
+    pub fn with_orderbook_interval(mut self, interval: Interval) -> Self {
+        self.orderbook_interval = interval;
+        self
+    }
+
+    // Key of the period containing `now` (ms) and seconds until the next one.
+    fn bucket(&self, kind: &str, key: &str, interval: Interval, now: i64) -> (String, u64) {
+        let open_time = get_key_from_interval(now.div_euclid(1000), interval) * 1000;
+        let ttl_ms = interval.next_open_time(open_time) - now;
+        let ttl_seconds = (ttl_ms + 999).div_euclid(1000).max(1) as u64;
+        (
+            format!("{}:{kind}:{key}:{interval}:{open_time}", self.namespace),
+            ttl_seconds,
+        )
+    }
+
+    async fn load<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        match self.store.get(key).await {
+            Ok(Some(value)) => match serde_json::from_str(&value) {
+                Ok(value) => Some(value),
+                Err(error) => {
+                    eprintln!("Cache: Ignoring malformed entry {key}: {error}");
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(error) => {
+                eprintln!("Cache: Failed to read {key}: {error:#}");
+                None
+            }
+        }
+    }
+
+    async fn save<T: Serialize>(&self, key: &str, value: &T, ttl_seconds: u64) {
+        let result = match serde_json::to_string(value) {
+            Ok(value) => self.store.put(key, value, ttl_seconds).await,
+            Err(error) => Err(error.into()),
+        };
+        if let Err(error) = result {
+            eprintln!("Cache: Failed to write {key}: {error:#}");
+        }
+    }
+
+    async fn fetch_klines_at(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+        now: i64,
+    ) -> Result<Vec<Kline>> {
+        let parsed_interval = interval.parse::<Interval>()?;
+        let (key, ttl_seconds) =
+            self.bucket("klines", &pair_symbol.to_uppercase(), parsed_interval, now);
+
+        let current_open_time = get_key_from_interval(now.div_euclid(1000), parsed_interval) * 1000;
+
+        let limit_len = limit.max(0) as usize;
+        if let Some(mut klines) = self.load::<Vec<Kline>>(&key).await {
+            if klines.len() + 1 >= limit_len {
+                let forming = self.source.fetch_klines(pair_symbol, interval, 1).await?;
+                let last_closed = klines.last().map_or(i64::MIN, |kline| kline.open_time);
+                klines.extend(
+                    forming
+                        .into_iter()
+                        .filter(|kline| kline.open_time > last_closed),
+                );
+                let skip = klines.len().saturating_sub(limit_len);
+                return Ok(klines.split_off(skip));
+            }
+        }
+
+        let klines = self
+            .source
+            .fetch_klines(pair_symbol, interval, limit)
+            .await?;
+        let closed: Vec<&Kline> = klines
+            .iter()
+            .filter(|kline| kline.open_time < current_open_time)
+            .collect();
+        self.save(&key, &closed, ttl_seconds).await;
+        Ok(klines)
+    }
+
+    async fn fetch_klines_range_at(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+        now: i64,
+    ) -> Result<Vec<Kline>> {
+        let parsed_interval = interval.parse::<Interval>()?;
+        // A range reaching the forming candle is passed through, like the forming candle above.
+        let current_open_time = get_key_from_interval(now.div_euclid(1000), parsed_interval) * 1000;
+        if end_time > current_open_time {
+            return self
+                .source
+                .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+                .await;
+        }
+
+        let range = format!("{}:{start_time}-{end_time}", pair_symbol.to_uppercase());
+        let (key, ttl_seconds) = self.bucket("range", &range, parsed_interval, now);
+
+        if let Some(klines) = self.load::<Vec<Kline>>(&key).await {
+            return Ok(klines);
+        }
+
+        let klines = self
+            .source
+            .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+            .await?;
+        self.save(&key, &klines, ttl_seconds).await;
+        Ok(klines)
+    }
+
+    async fn fetch_orderbook_at(
+        &self,
+        pair_symbol: &str,
+        limit: i32,
+        now: i64,
+    ) -> Result<OrderBook> {
+        let (key, ttl_seconds) = self.bucket(
+            "orderbook",
+            &pair_symbol.to_uppercase(),
+            self.orderbook_interval,
+            now,
+        );
+
+        if let Some(cached) = self.load::<CachedOrderBook>(&key).await {
+            if cached.limit >= limit {
+                let limit = limit.max(0) as usize;
+                return Ok(OrderBook {
+                    last_update_id: cached.orderbook.last_update_id,
+                    bids: cached.orderbook.bids.into_iter().take(limit).collect(),
+                    asks: cached.orderbook.asks.into_iter().take(limit).collect(),
+                });
+            }
+        }
+
+        let orderbook = self.source.fetch_orderbook(pair_symbol, limit).await?;
+        let cached = CachedOrderBook { limit, orderbook };
+        self.save(&key, &cached, ttl_seconds).await;
+        Ok(cached.orderbook)
+    }
+}
+
+impl<S: MarketDataSource, C: CacheStore> MarketDataSource for CachedSource<S, C> {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        let now = Utc::now().timestamp_millis();
+        self.fetch_klines_at(pair_symbol, interval, limit, now)
+            .await
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        let now = Utc::now().timestamp_millis();
+        self.fetch_klines_range_at(pair_symbol, interval, start_time, end_time, now)
+            .await
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        let now = Utc::now().timestamp_millis();
+        self.fetch_orderbook_at(pair_symbol, limit, now).await
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        self.source.fetch_current_price(pair_symbol).await
+    }
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        self.source
+            .fetch_agg_trades(pair_symbol, start_time, limit)
+            .await
+    }
+
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        self.source
+            .fetch_futures_metrics(pair_symbol, period, limit)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::CountingSource;
+
+    // 2025-04-30T13:45:00Z
+    const NOW: i64 = 1_746_020_700_000;
+    const MINUTE: i64 = 60_000;
+    const HOUR: i64 = 60 * MINUTE;
+
+    #[tokio::test]
+    async fn test_cached_klines_reused_within_bucket() {
+        let source = CountingSource::from_fixtures();
+        let store = MemoryCache::new();
+        let cached = CachedSource::new(&source, &store);
+
+        // The last fixture hour is still forming.
+        let all = source
+            .source
+            .fetch_klines("SOL_USDT", "1h", 500)
+            .await
+            .unwrap();
+        let last = all.last().unwrap().open_time;
+        let now = last + 10 * MINUTE;
+
+        let klines = cached
+            .fetch_klines_at("SOL_USDT", "1h", 100, now)
+            .await
+            .unwrap();
+        assert_eq!(klines.len(), 100);
+        assert_eq!(source.fetches.get(), 1);
+        // Only the closed candles are cached.
+        let (key, _) = cached.bucket("klines", "SOL_USDT", Interval::Hour1, now);
+        let stored: Vec<Kline> = cached.load(&key).await.unwrap();
+        assert_eq!(stored.len(), 99);
+        assert_eq!(stored.last().unwrap().open_time, last - HOUR);
+
+        // Same hour, smaller limit: closed candles from the cache, the forming one fetched.
+        let tail = cached
+            .fetch_klines_at("sol_usdt", "1h", 24, now + 10 * MINUTE)
+            .await
+            .unwrap();
+        assert_eq!(source.fetches.get(), 2);
+        assert_eq!(tail.len(), 24);
+        assert_eq!(tail[0].open_time, klines[76].open_time);
+        assert_eq!(tail[23].open_time, last);
+
+        // More than cached, another interval, or the next hour: fetched again.
+        cached
+            .fetch_klines_at("SOL_USDT", "1h", 200, now)
+            .await
+            .unwrap();
+        assert_eq!(source.fetches.get(), 3);
+        cached
+            .fetch_klines_at("SOL_USDT", "4h", 24, now)
+            .await
+            .unwrap();
+        assert_eq!(source.fetches.get(), 4);
+        cached
+            .fetch_klines_at("SOL_USDT", "1h", 24, now + HOUR)
+            .await
+            .unwrap();
+        assert_eq!(source.fetches.get(), 5);
+
+        // Another worker sharing the store reuses the closed candles.
+        let other = CountingSource::from_fixtures();
+        let other_cached = CachedSource::new(&other, &store);
+        let other_tail = other_cached
+            .fetch_klines_at("SOL_USDT", "1h", 24, now + 20 * MINUTE)
+            .await
+            .unwrap();
+        assert_eq!(other.fetches.get(), 1);
+        assert_eq!(other_tail.last().unwrap().open_time, last);
+
+        assert!(cached
+            .fetch_klines_at("SOL_USDT", "2x", 24, now)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cached_ranges_of_closed_candles_only() {
+        let source = CountingSource::from_fixtures();
+        let cached = CachedSource::new(&source, MemoryCache::new());
+        let all = source
+            .source
+            .fetch_klines("SOL_USDT", "1h", 500)
+            .await
+            .unwrap();
+        let last = all.last().unwrap().open_time;
+        let now = last + 10 * MINUTE;
+
+        let start = all[400].open_time;
+        for _ in 0..2 {
+            cached
+                .fetch_klines_range_at("SOL_USDT", "1h", start, last, now)
+                .await
+                .unwrap();
+        }
+        assert_eq!(source.fetches.get(), 1);
+
+        // Up to the forming candle, every request is fetched.
+        for _ in 0..2 {
+            cached
+                .fetch_klines_range_at("SOL_USDT", "1h", start, last + HOUR, now)
+                .await
+                .unwrap();
+        }
+        assert_eq!(source.fetches.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cached_orderbook_and_expiry() {
+        let source = CountingSource::from_fixtures();
+        let cached = CachedSource::new(&source, MemoryCache::new());
+
+        let orderbook = cached
+            .fetch_orderbook_at("SOL_USDT", 100, NOW)
+            .await
+            .unwrap();
+        assert_eq!(orderbook.bids.len(), 100);
+        let smaller = cached
+            .fetch_orderbook_at("SOL_USDT", 20, NOW + 30_000)
+            .await
+            .unwrap();
+        assert_eq!(smaller.bids.len(), 20);
+        assert_eq!(smaller.asks[0], orderbook.asks[0]);
+        assert_eq!(source.fetches.get(), 1);
+
+        // The next minute gets a fresh snapshot.
+        cached
+            .fetch_orderbook_at("SOL_USDT", 20, NOW + MINUTE)
+            .await
+            .unwrap();
+        assert_eq!(source.fetches.get(), 2);
+
+        // Entries expire at the next boundary.
+        let (key, ttl_seconds) = cached.bucket("klines", "SOL_USDT", Interval::Hour1, NOW);
+        assert_eq!(key, "market:klines:SOL_USDT:1h:1746018000000");
+        assert_eq!(ttl_seconds, 15 * 60);
+
+        let store = MemoryCache::new();
+        store.put("gone", "1".to_string(), 0).await.unwrap();
+        store.put("kept", "2".to_string(), 60).await.unwrap();
+        assert_eq!(store.get("gone").await.unwrap(), None);
+        assert_eq!(store.get("kept").await.unwrap().as_deref(), Some("2"));
+        assert_eq!(None::<MemoryCache>.get("kept").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, SecondsFormat};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    binance::dedupe_klines,
+    market::MarketDataSource,
+    venue::{get_venue_json, ohlcv_kline, StringOrNumber, Venue},
+    Interval, Kline, OrderBook,
+};
+
+const COINBASE_API_URL: &str = "https://api.exchange.coinbase.com";
+// Coinbase returns at most 300 candles per request.
+const MAX_CANDLES_PER_REQUEST: i32 = 300;
+
+/// Coinbase Exchange market data, normalised to `Kline`/`OrderBook`.
+///
+/// Coinbase only serves 1m, 5m, 15m, 1h, 6h and 1d candles.
+#[derive(Debug, Clone)]
+pub struct CoinbaseClient {
+    base_url: String,
+    client: Client,
+}
+
+impl Default for CoinbaseClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CoinbaseClient {
+    pub fn new() -> Self {
+        CoinbaseClient {
+            base_url: COINBASE_API_URL.to_string(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T> {
+        let url = format!("{}/{path_and_query}", self.base_url);
+        get_venue_json(&self.client, Venue::Coinbase, &url).await
+    }
+
+    async fn fetch_candles(
+        &self,
+        product_id: &str,
+        interval: Interval,
+        window: Option<(i64, i64)>,
+    ) -> Result<Vec<Kline>> {
+        let interval_millis = interval.duration_ms();
+        let mut path = format!(
+            "products/{product_id}/candles?granularity={}",
+            coinbase_granularity(interval)?
+        );
+        if let Some((start_time, end_time)) = window {
+            path.push_str(&format!(
+                "&start={}&end={}",
+                to_iso(start_time)?,
+                to_iso(end_time)?
+            ));
+        }
+
+        // [time (s), low, high, open, close, volume], newest first
+        let rows: Vec<Vec<StringOrNumber>> = self.get(&path).await?;
+        let mut klines = rows
+            .into_iter()
+            .map(|row| {
+                let [time, low, high, open, close, volume]: [StringOrNumber; 6] = row
+                    .try_into()
+                    .map_err(|row| anyhow!("Unexpected Coinbase candle row {row:?}"))?;
+                let open_time = time
+                    .to_string()
+                    .parse::<i64>()
+                    .with_context(|| format!("Invalid Coinbase candle time {time}"))?
+                    * 1000;
+                Ok(ohlcv_kline(
+                    open_time,
+                    interval_millis,
+                    [open, high, low, close, volume].map(|value| value.to_string()),
+                    None,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        klines.reverse();
+        Ok(klines)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinbaseBook {
+    sequence: i64,
+    // [price, size, num_orders]
+    bids: Vec<Vec<StringOrNumber>>,
+    asks: Vec<Vec<StringOrNumber>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoinbaseTicker {
+    price: String,
+}
+
+fn coinbase_granularity(interval: Interval) -> Result<i64> {
+    match interval {
+        Interval::Minute1
+        | Interval::Minute5
+        | Interval::Minute15
+        | Interval::Hour1
+        | Interval::Hour6
+        | Interval::Day1 => Ok(interval.duration_seconds()),
+        _ => bail!("Coinbase doesn't serve {interval} candles"),
+    }
+}
+
+fn to_iso(timestamp_millis: i64) -> Result<String> {
+    DateTime::from_timestamp_millis(timestamp_millis)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .ok_or_else(|| anyhow!("Invalid timestamp {timestamp_millis}"))
+}
+
+impl MarketDataSource for CoinbaseClient {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        let interval: Interval = interval.parse()?;
+        let mut klines = if limit > MAX_CANDLES_PER_REQUEST {
+            let interval_millis = interval.duration_ms();
+            let end_time = chrono::Utc::now().timestamp_millis() + interval_millis;
+            let start_time = end_time - (limit as i64 + 1) * interval_millis;
+            self.fetch_klines_range(pair_symbol, interval.as_str(), start_time, end_time)
+                .await?
+        } else {
+            let product_id = Venue::Coinbase.format_symbol(pair_symbol)?;
+            self.fetch_candles(&product_id, interval, None).await?
+        };
+
+        let skip = klines.len().saturating_sub(limit.max(0) as usize);
+        Ok(klines.split_off(skip))
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        let product_id = Venue::Coinbase.format_symbol(pair_symbol)?;
+        let interval: Interval = interval.parse()?;
+        // `start` and `end` are both inclusive, so a full window ends one candle short of the limit.
+        let window_millis = interval.duration_ms() * (MAX_CANDLES_PER_REQUEST - 1) as i64;
+
+        let mut pages: Vec<Kline> = Vec::new();
+        let mut window_start = start_time;
+        while window_start < end_time {
+            let window_end = (window_start + window_millis).min(end_time);
+            pages.extend(
+                self.fetch_candles(&product_id, interval, Some((window_start, window_end)))
+                    .await?,
+            );
+            window_start = window_end + interval.duration_ms();
+        }
+
+        Ok(dedupe_klines(pages)
+            .into_iter()
+            .filter(|kline| kline.open_time >= start_time && kline.open_time < end_time)
+            .collect())
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        let product_id = Venue::Coinbase.format_symbol(pair_symbol)?;
+        let book: CoinbaseBook = self
+            .get(&format!("products/{product_id}/book?level=2"))
+            .await?;
+
+        let limit = limit.max(0) as usize;
+        let price_and_size = |levels: Vec<Vec<StringOrNumber>>| -> Vec<Vec<String>> {
+            levels
+                .into_iter()
+                .take(limit)
+                .map(|level| level.iter().take(2).map(|v| v.to_string()).collect())
+                .collect()
+        };
+
+        Ok(OrderBook {
+            last_update_id: book.sequence,
+            bids: price_and_size(book.bids),
+            asks: price_and_size(book.asks),
+        })
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        let product_id = Venue::Coinbase.format_symbol(pair_symbol)?;
+        let ticker: CoinbaseTicker = self.get(&format!("products/{product_id}/ticker")).await?;
+        ticker
+            .price
+            .parse::<f64>()
+            .with_context(|| format!("Invalid Coinbase price {}", ticker.price))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_coinbase_client_against_stub() {
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(|path| {
+            let body = if path.contains("/candles") {
+                "[[1745974800,146.3,147.2,146.5,147.0,1500.2],[1745971200,145.8,147.0,146.1,146.5,1200.5]]"
+            } else if path.contains("/book") {
+                r#"{"sequence":123456,"bids":[["146.99","10.5",3],["146.98","1.0",1]],"asks":[["147.01","3.2",2]],"time":"2025-04-30T01:03:20Z"}"#
+            } else if path.contains("/ticker") {
+                r#"{"trade_id":1,"price":"147.00","size":"1","time":"2025-04-30T01:03:20Z"}"#
+            } else {
+                r#"{"message":"NotFound"}"#
+            };
+            (200, body.to_string())
+        })
+        .await;
+        let client = CoinbaseClient::new().with_base_url(&base_url);
+
+        let klines = client.fetch_klines("SOL_USDT", "1h", 1).await.unwrap();
+        assert_eq!(klines.len(), 1);
+        assert_eq!(klines[0].open_time, 1745974800000);
+        assert_eq!(klines[0].open_price, "146.5");
+        assert_eq!(klines[0].high_price, "147.2");
+        assert_eq!(klines[0].low_price, "146.3");
+
+        let orderbook = client.fetch_orderbook("SOL_USDT", 1).await.unwrap();
+        assert_eq!(orderbook.last_update_id, 123456);
+        assert_eq!(orderbook.bids, [["146.99", "10.5"]]);
+
+        assert_eq!(client.fetch_current_price("SOL").await.unwrap(), 147.0);
+
+        assert!(client.fetch_klines("SOL_USDT", "4h", 1).await.is_err());
+        assert_eq!(
+            requests.lock().unwrap()[0],
+            "/products/SOL-USD/candles?granularity=3600"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coinbase_range_pages_within_the_candle_limit() {
+        // Serves every hour in [start, end], refusing more than 300 like Coinbase does.
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(|path| {
+            let param = |name: &str| {
+                let value = path.split(&format!("{name}=")).nth(1)?.split('&').next()?;
+                Some(DateTime::parse_from_rfc3339(value).ok()?.timestamp())
+            };
+            let (Some(start), Some(end)) = (param("start"), param("end")) else {
+                return (400, r#"{"message":"missing start/end"}"#.to_string());
+            };
+            if (end - start) / 3600 + 1 > MAX_CANDLES_PER_REQUEST as i64 {
+                return (
+                    400,
+                    r#"{"message":"granularity too small for the requested time range"}"#
+                        .to_string(),
+                );
+            }
+            let rows: Vec<String> = (0..=(end - start) / 3600)
+                .rev()
+                .map(|hour| {
+                    let time = start + hour * 3600;
+                    format!("[{time},146.3,147.2,146.5,147.0,1500.2]")
+                })
+                .collect();
+            (200, format!("[{}]", rows.join(",")))
+        })
+        .await;
+        let client = CoinbaseClient::new().with_base_url(&base_url);
+
+        let start_time = 1_745_971_200_000;
+        let end_time = start_time + 700 * 3_600_000;
+        let klines = client
+            .fetch_klines_range("SOL_USDT", "1h", start_time, end_time)
+            .await
+            .unwrap();
+        assert_eq!(klines.len(), 700);
+        assert_eq!(klines[0].open_time, start_time);
+        assert_eq!(klines[699].open_time, end_time - 3_600_000);
+        assert!(klines
+            .windows(2)
+            .all(|pair| pair[1].open_time - pair[0].open_time == 3_600_000));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+}
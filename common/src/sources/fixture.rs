@@ -0,0 +1,306 @@
+use anyhow::{anyhow, Context, Result};
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    market::MarketDataSource, venue::Venue, AggTrade, Candle, FuturesMetrics, Kline, OrderBook,
+};
+
+/// A `MarketDataSource` backed by recorded data, for running prompts and charts offline.
+///
+/// Data can be added in memory with the `with_*` methods or loaded from a directory laid out as:
+///
+/// ```text
+/// <dir>/SOLUSDT/1h.json     Binance kline arrays, oldest first
+/// <dir>/SOLUSDT/depth.json  Binance depth snapshot
+/// <dir>/SOLUSDT/price.json  optional, a bare number
+/// <dir>/SOLUSDT/agg_trades.json  optional, Binance aggregate trades, oldest first
+/// <dir>/SOLUSDT/futures_1h.json   optional, `FuturesMetrics` recorded at a 1h period
+/// ```
+///
+/// Files without a `.json` extension are ignored.
+///
+/// Without a recorded price, `fetch_current_price` falls back to the latest close.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureSource {
+    klines: HashMap<(String, String), Vec<Kline>>,
+    orderbooks: HashMap<String, OrderBook>,
+    prices: HashMap<String, f64>,
+    agg_trades: HashMap<String, Vec<AggTrade>>,
+    futures_metrics: HashMap<(String, String), FuturesMetrics>,
+}
+
+// Fixtures are keyed by the venue symbol so "SOL_USDT" and "SOLUSDT" hit the same data.
+fn fixture_key(pair_symbol: &str) -> String {
+    Venue::Binance
+        .format_symbol(pair_symbol)
+        .unwrap_or_else(|_| pair_symbol.to_uppercase())
+}
+
+impl FixtureSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every pair directory found under `dir`.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut source = Self::new();
+
+        let pair_dirs = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read fixture dir {}", dir.display()))?;
+        for pair_dir in pair_dirs {
+            let pair_dir = pair_dir?.path();
+            if !pair_dir.is_dir() {
+                continue;
+            }
+            let Some(symbol) = pair_dir.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let symbol = symbol.to_string();
+
+            for entry in fs::read_dir(&pair_dir)? {
+                let path = entry?.path();
+                // Recordings (e.g. `depth_events.jsonl`) live alongside but aren't sources.
+                if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                    continue;
+                }
+                let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read fixture {}", path.display()))?;
+                let parse_context = || format!("Failed to parse fixture {}", path.display());
+
+                match name {
+                    "depth" => {
+                        let orderbook: OrderBook =
+                            serde_json::from_str(&content).with_context(parse_context)?;
+                        source.orderbooks.insert(symbol.clone(), orderbook);
+                    }
+                    "agg_trades" => {
+                        let trades: Vec<AggTrade> =
+                            serde_json::from_str(&content).with_context(parse_context)?;
+                        source.agg_trades.insert(symbol.clone(), trades);
+                    }
+                    name if name.starts_with("futures_") => {
+                        let metrics: FuturesMetrics =
+                            serde_json::from_str(&content).with_context(parse_context)?;
+                        let period = name.trim_start_matches("futures_").to_string();
+                        source
+                            .futures_metrics
+                            .insert((symbol.clone(), period), metrics);
+                    }
+                    "price" => {
+                        let price: f64 =
+                            serde_json::from_str(&content).with_context(parse_context)?;
+                        source.prices.insert(symbol.clone(), price);
+                    }
+                    interval => {
+                        let klines: Vec<Kline> =
+                            serde_json::from_str(&content).with_context(parse_context)?;
+                        source
+                            .klines
+                            .insert((symbol.clone(), interval.to_string()), klines);
+                    }
+                }
+            }
+        }
+
+        Ok(source)
+    }
+
+    pub fn with_klines(mut self, pair_symbol: &str, interval: &str, klines: Vec<Kline>) -> Self {
+        self.klines
+            .insert((fixture_key(pair_symbol), interval.to_string()), klines);
+        self
+    }
+
+    pub fn with_orderbook(mut self, pair_symbol: &str, orderbook: OrderBook) -> Self {
+        self.orderbooks.insert(fixture_key(pair_symbol), orderbook);
+        self
+    }
+
+    pub fn with_price(mut self, pair_symbol: &str, price: f64) -> Self {
+        self.prices.insert(fixture_key(pair_symbol), price);
+        self
+    }
+
+    pub fn with_agg_trades(mut self, pair_symbol: &str, trades: Vec<AggTrade>) -> Self {
+        self.agg_trades.insert(fixture_key(pair_symbol), trades);
+        self
+    }
+
+    pub fn with_futures_metrics(
+        mut self,
+        pair_symbol: &str,
+        period: &str,
+        metrics: FuturesMetrics,
+    ) -> Self {
+        self.futures_metrics
+            .insert((fixture_key(pair_symbol), period.to_string()), metrics);
+        self
+    }
+}
+
+impl MarketDataSource for FixtureSource {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        let key = (fixture_key(pair_symbol), interval.to_string());
+        let klines = self
+            .klines
+            .get(&key)
+            .ok_or_else(|| anyhow!("No fixture klines for {} {}", key.0, key.1))?;
+
+        let skip = klines.len().saturating_sub(limit.max(0) as usize);
+        Ok(klines[skip..].to_vec())
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        let key = (fixture_key(pair_symbol), interval.to_string());
+        let klines = self
+            .klines
+            .get(&key)
+            .ok_or_else(|| anyhow!("No fixture klines for {} {}", key.0, key.1))?;
+
+        Ok(klines
+            .iter()
+            .filter(|kline| kline.open_time >= start_time && kline.open_time < end_time)
+            .cloned()
+            .collect())
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        let key = fixture_key(pair_symbol);
+        let orderbook = self
+            .orderbooks
+            .get(&key)
+            .ok_or_else(|| anyhow!("No fixture orderbook for {key}"))?;
+
+        let limit = limit.max(0) as usize;
+        Ok(OrderBook {
+            last_update_id: orderbook.last_update_id,
+            bids: orderbook.bids.iter().take(limit).cloned().collect(),
+            asks: orderbook.asks.iter().take(limit).cloned().collect(),
+        })
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        let key = fixture_key(pair_symbol);
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+
+        let latest = self
+            .klines
+            .iter()
+            .filter(|((symbol, _), _)| *symbol == key)
+            .filter_map(|(_, klines)| klines.last())
+            .max_by_key(|kline| kline.close_time)
+            .ok_or_else(|| anyhow!("No fixture price for {key}"))?;
+
+        let candle = Candle::try_from(latest)
+            .with_context(|| format!("Invalid latest kline in fixture for {key}"))?;
+        Ok(candle.close)
+    }
+
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        let key = fixture_key(pair_symbol);
+        let trades = self
+            .agg_trades
+            .get(&key)
+            .ok_or_else(|| anyhow!("No fixture trades for {key}"))?;
+
+        let trades: Vec<AggTrade> = trades
+            .iter()
+            .filter(|trade| trade.time >= start_time)
+            .cloned()
+            .collect();
+        let skip = trades.len().saturating_sub(limit);
+        Ok(trades[skip..].to_vec())
+    }
+
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        let key = (fixture_key(pair_symbol), period.to_string());
+        let metrics = self
+            .futures_metrics
+            .get(&key)
+            .ok_or_else(|| anyhow!("No fixture futures metrics for {} {}", key.0, key.1))?;
+
+        let skip = metrics.open_interest.len().saturating_sub(limit);
+        let open_interest = metrics.open_interest[skip..].to_vec();
+        let skip = metrics.long_short_ratios.len().saturating_sub(limit);
+        let long_short_ratios = metrics.long_short_ratios[skip..].to_vec();
+        // Funding settled within the window, or the last settlement before it.
+        let window_start = open_interest.first().map_or(i64::MIN, |oi| oi.timestamp);
+        let mut funding_rates: Vec<_> = metrics
+            .funding_rates
+            .iter()
+            .filter(|rate| rate.funding_time >= window_start)
+            .cloned()
+            .collect();
+        if funding_rates.is_empty() {
+            funding_rates.extend(metrics.funding_rates.last().cloned());
+        }
+
+        Ok(FuturesMetrics {
+            funding_rates,
+            open_interest,
+            long_short_ratios,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    #[tokio::test]
+    async fn test_fixture_source_from_dir() {
+        let source = FixtureSource::from_dir(FIXTURES_DIR).unwrap();
+
+        let klines = source.fetch_klines("SOL_USDT", "1h", 24).await.unwrap();
+        assert_eq!(klines.len(), 24);
+        assert!(klines.windows(2).all(|w| w[0].open_time < w[1].open_time));
+
+        let orderbook = source.fetch_orderbook("SOLUSDT", 50).await.unwrap();
+        assert_eq!(orderbook.bids.len(), 50);
+        assert_eq!(orderbook.asks.len(), 50);
+
+        // Falls back to the latest close across intervals.
+        let price = source.fetch_current_price("SOL_USDT").await.unwrap();
+        let latest_15m = source.fetch_klines("SOL_USDT", "15m", 1).await.unwrap();
+        assert_eq!(price, latest_15m[0].close_price.parse::<f64>().unwrap());
+
+        assert!(source.fetch_klines("BTC_USDT", "1h", 10).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fixture_source_in_memory() {
+        let source = FixtureSource::new().with_price("SOL_USDC", 150.5);
+        assert_eq!(source.fetch_current_price("SOLUSDC").await.unwrap(), 150.5);
+        // The quote is part of the key, a bare token means USDT.
+        assert!(source.fetch_current_price("SOL").await.is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{bail, Result};
+
+use crate::{AggTrade, FuturesMetrics, Kline, OrderBook};
+
+/// A backend that can serve klines, orderbook depth and the current price for a pair.
+///
+/// Everything that needs market data (`PriceHistoryBuilder`, the prompt and chart workers)
+/// takes a source instead of calling a venue directly, so the same pipeline can run against
+/// live Binance or against recorded fixtures (see `FixtureSource`).
+#[allow(async_fn_in_trait)]
+pub trait MarketDataSource {
+    /// Fetches the latest `limit` klines for `pair_symbol` (e.g. "SOL_USDT") at `interval` (e.g. "1h"), oldest first.
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>>;
+
+    /// Fetches every kline with `open_time` in `[start_time, end_time)` (ms), oldest first.
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>>;
+
+    /// Fetches an orderbook depth snapshot with up to `limit` levels per side.
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook>;
+
+    /// Fetches the latest traded price.
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64>;
+
+    /// Fetches the latest aggregate trades since `start_time` (ms), at most `limit`, oldest first.
+    ///
+    /// Only some venues serve trades, the others fail.
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        let _ = (start_time, limit);
+        bail!("Aggregate trades aren't available for {pair_symbol} from this source")
+    }
+
+    /// Fetches the latest `limit` open interest and top-trader long/short ratio readings at
+    /// `period` (e.g. "1h"), with the funding rates settled over the same window.
+    ///
+    /// Only venues with perpetual futures serve these, the others fail.
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        let _ = (period, limit);
+        bail!("Futures metrics aren't available for {pair_symbol} from this source")
+    }
+}
+
+// Lets builders borrow a source instead of taking ownership of it.
+impl<T: MarketDataSource> MarketDataSource for &T {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        (**self).fetch_klines(pair_symbol, interval, limit).await
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        (**self)
+            .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+            .await
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        (**self).fetch_orderbook(pair_symbol, limit).await
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        (**self).fetch_current_price(pair_symbol).await
+    }
+
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        (**self)
+            .fetch_agg_trades(pair_symbol, start_time, limit)
+            .await
+    }
+
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        (**self)
+            .fetch_futures_metrics(pair_symbol, period, limit)
+            .await
+    }
+}
@@ -1,3 +1,14 @@
 pub mod binance;
+// Websockets need a native tokio runtime
+#[cfg(not(target_arch = "wasm32"))]
+pub mod binance_stream;
+pub mod bybit;
+pub mod cache;
+pub mod coinbase;
 pub mod cooker;
+pub mod fixture;
 pub mod jup;
+pub mod market;
+pub mod okx;
+pub mod oracle;
+pub mod venue;
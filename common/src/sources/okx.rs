@@ -0,0 +1,281 @@
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    binance::dedupe_klines,
+    market::MarketDataSource,
+    venue::{get_venue_json, ohlcv_kline, Venue},
+    Interval, Kline, OrderBook,
+};
+
+const OKX_API_URL: &str = "https://www.okx.com";
+// `candles` serves the latest 300, `history-candles` pages 100 at a time.
+const MAX_CANDLES_PER_REQUEST: i32 = 300;
+const MAX_HISTORY_CANDLES_PER_REQUEST: i32 = 100;
+const MAX_DEPTH_LIMIT: i32 = 400;
+
+/// OKX v5 spot market data, normalised to `Kline`/`OrderBook`.
+#[derive(Debug, Clone)]
+pub struct OkxClient {
+    base_url: String,
+    client: Client,
+}
+
+impl Default for OkxClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OkxClient {
+    pub fn new() -> Self {
+        OkxClient {
+            base_url: OKX_API_URL.to_string(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<Vec<T>> {
+        let url = format!("{}/{path_and_query}", self.base_url);
+        let response: OkxResponse<T> = get_venue_json(&self.client, Venue::Okx, &url).await?;
+        if response.code != "0" {
+            bail!("OKX API error {}: {}", response.code, response.msg);
+        }
+        Ok(response.data)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OkxResponse<T> {
+    code: String,
+    msg: String,
+    data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OkxOrderBook {
+    // [price, size, deprecated, order count]
+    asks: Vec<Vec<String>>,
+    bids: Vec<Vec<String>>,
+    ts: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OkxTicker {
+    last: String,
+}
+
+// Daily and longer bars default to Hong Kong time on OKX, the `utc` variants line up with Binance.
+fn okx_bar(interval: Interval) -> Result<&'static str> {
+    Ok(match interval {
+        Interval::Minute1 => "1m",
+        Interval::Minute3 => "3m",
+        Interval::Minute5 => "5m",
+        Interval::Minute15 => "15m",
+        Interval::Minute30 => "30m",
+        Interval::Hour1 => "1H",
+        Interval::Hour2 => "2H",
+        Interval::Hour4 => "4H",
+        Interval::Hour6 => "6Hutc",
+        Interval::Hour12 => "12Hutc",
+        Interval::Day1 => "1Dutc",
+        Interval::Day3 => "3Dutc",
+        Interval::Week1 => "1Wutc",
+        Interval::Month1 => "1Mutc",
+        Interval::Second1 | Interval::Hour8 => bail!("OKX doesn't serve {interval} candles"),
+    })
+}
+
+// Rows are [ts, o, h, l, c, vol, volCcy, volCcyQuote, confirm], newest first.
+fn to_klines(rows: Vec<Vec<String>>, interval_millis: i64) -> Result<Vec<Kline>> {
+    let mut klines = rows
+        .into_iter()
+        .map(|row| {
+            let mut values = row.into_iter();
+            let mut next = || {
+                values
+                    .next()
+                    .ok_or_else(|| anyhow!("Unexpected OKX candle row"))
+            };
+            let ts = next()?;
+            let open_time = ts
+                .parse::<i64>()
+                .with_context(|| format!("Invalid OKX timestamp {ts}"))?;
+            let ohlcv = [next()?, next()?, next()?, next()?, next()?];
+            let _volume_ccy = next()?;
+            let quote_volume = next().ok();
+            Ok(ohlcv_kline(open_time, interval_millis, ohlcv, quote_volume))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    klines.reverse();
+    Ok(klines)
+}
+
+impl MarketDataSource for OkxClient {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        let interval: Interval = interval.parse()?;
+        let interval_millis = interval.duration_ms();
+
+        if limit > MAX_CANDLES_PER_REQUEST {
+            let end_time = chrono::Utc::now().timestamp_millis() + interval_millis;
+            let start_time = end_time - (limit as i64 + 1) * interval_millis;
+            let mut klines = self
+                .fetch_klines_range(pair_symbol, interval.as_str(), start_time, end_time)
+                .await?;
+            let skip = klines.len().saturating_sub(limit as usize);
+            return Ok(klines.split_off(skip));
+        }
+
+        let inst_id = Venue::Okx.format_symbol(pair_symbol)?;
+        let rows: Vec<Vec<String>> = self
+            .get(&format!(
+                "api/v5/market/candles?instId={inst_id}&bar={}&limit={limit}",
+                okx_bar(interval)?
+            ))
+            .await?;
+
+        to_klines(rows, interval_millis)
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        let inst_id = Venue::Okx.format_symbol(pair_symbol)?;
+        let interval: Interval = interval.parse()?;
+        let bar = okx_bar(interval)?;
+        let interval_millis = interval.duration_ms();
+
+        // `after` returns candles strictly older than the cursor.
+        let mut pages: Vec<Kline> = Vec::new();
+        let mut cursor = end_time;
+        while cursor > start_time {
+            let rows: Vec<Vec<String>> = self
+                .get(&format!(
+                    "api/v5/market/history-candles?instId={inst_id}&bar={bar}&after={cursor}&limit={MAX_HISTORY_CANDLES_PER_REQUEST}"
+                ))
+                .await?;
+
+            let is_last_page = (rows.len() as i32) < MAX_HISTORY_CANDLES_PER_REQUEST;
+            let page = to_klines(rows, interval_millis)?;
+            let Some(oldest_open_time) = page.first().map(|kline| kline.open_time) else {
+                break;
+            };
+            pages.extend(page);
+
+            if is_last_page || oldest_open_time >= cursor {
+                break;
+            }
+            cursor = oldest_open_time;
+        }
+
+        Ok(dedupe_klines(pages)
+            .into_iter()
+            .filter(|kline| kline.open_time >= start_time && kline.open_time < end_time)
+            .collect())
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        let inst_id = Venue::Okx.format_symbol(pair_symbol)?;
+        let limit = limit.clamp(1, MAX_DEPTH_LIMIT);
+        let books: Vec<OkxOrderBook> = self
+            .get(&format!("api/v5/market/books?instId={inst_id}&sz={limit}"))
+            .await?;
+        let book = books
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No OKX orderbook for {inst_id}"))?;
+
+        let price_and_size = |levels: Vec<Vec<String>>| -> Vec<Vec<String>> {
+            levels
+                .into_iter()
+                .map(|level| level.into_iter().take(2).collect())
+                .collect()
+        };
+
+        // OKX books don't carry an update id, the snapshot time is the closest thing.
+        let last_update_id = book
+            .ts
+            .parse()
+            .with_context(|| format!("Invalid OKX book ts {:?}", book.ts))?;
+
+        Ok(OrderBook {
+            last_update_id,
+            bids: price_and_size(book.bids),
+            asks: price_and_size(book.asks),
+        })
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        let inst_id = Venue::Okx.format_symbol(pair_symbol)?;
+        let tickers: Vec<OkxTicker> = self
+            .get(&format!("api/v5/market/ticker?instId={inst_id}"))
+            .await?;
+        let ticker = tickers
+            .first()
+            .ok_or_else(|| anyhow!("No OKX ticker for {inst_id}"))?;
+        ticker
+            .last
+            .parse::<f64>()
+            .with_context(|| format!("Invalid OKX price {}", ticker.last))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_okx_client_against_stub() {
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(|path| {
+            let body = if path.starts_with("/api/v5/market/candles") {
+                r#"{"code":"0","msg":"","data":[["1745974800000","146.50","147.20","146.30","147.00","1500.2","1500.2","220000.5","0"],["1745971200000","146.10","147.00","145.80","146.50","1200.5","1200.5","175000.1","1"]]}"#
+            } else if path.starts_with("/api/v5/market/books") {
+                r#"{"code":"0","msg":"","data":[{"asks":[["147.01","3.2","0","4"]],"bids":[["146.99","10.5","0","7"]],"ts":"1745975000000"}]}"#
+            } else if path.starts_with("/api/v5/market/ticker") {
+                r#"{"code":"0","msg":"","data":[{"instId":"SOL-USDT","last":"147.00"}]}"#
+            } else {
+                r#"{"code":"51000","msg":"Parameter error","data":[]}"#
+            };
+            (200, body.to_string())
+        })
+        .await;
+        let client = OkxClient::new().with_base_url(&base_url);
+
+        let klines = client.fetch_klines("SOL_USDT", "1h", 2).await.unwrap();
+        assert_eq!(klines[0].open_time, 1745971200000);
+        assert_eq!(klines[0].close_time, 1745974799999);
+        assert_eq!(klines[1].close_price, "147.00");
+        assert_eq!(klines[1].quote_asset_volume, "220000.5");
+
+        let orderbook = client.fetch_orderbook("SOL_USDT", 50).await.unwrap();
+        assert_eq!(orderbook.bids[0], ["146.99", "10.5"]);
+        assert_eq!(orderbook.asks[0], ["147.01", "3.2"]);
+
+        assert_eq!(client.fetch_current_price("SOL_USDT").await.unwrap(), 147.0);
+
+        assert_eq!(
+            requests.lock().unwrap()[0],
+            "/api/v5/market/candles?instId=SOL-USDT&bar=1H&limit=2"
+        );
+    }
+}
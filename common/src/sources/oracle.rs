@@ -0,0 +1,541 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+use crate::{
+    binance::BinanceClient,
+    market::MarketDataSource,
+    venue::{Venue, VenueSource},
+    PairSymbol,
+};
+
+const PYTH_HERMES_URL: &str = "https://hermes.pyth.network";
+const JUPITER_PRICE_URL: &str = "https://lite-api.jup.ag";
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60);
+
+// Pyth USD feeds for the Jupiter perps markets.
+const PYTH_FEED_IDS: [(&str, &str); 3] = [
+    (
+        "SOL",
+        "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d",
+    ),
+    (
+        "BTC",
+        "e62df6c8b4a85fe1a67db44dc12de5db330f7ac66b72dc658afedf0f4a415b43",
+    ),
+    (
+        "ETH",
+        "ff61491a931112ddf1bd8147cd1b641375f79f82825126a65e5f1e8e0bfc3fe6",
+    ),
+];
+
+// The mints Jupiter perps custody for each market.
+const JUPITER_MINTS: [(&str, &str); 4] = [
+    ("SOL", "So11111111111111111111111111111111111111112"),
+    ("BTC", "3NZ9JMVBmGAqocybic2c7LQCJScmgsAZ6vQqTDzcqmJh"),
+    ("ETH", "7vfCXTUXx5WJV5JADk17DUJ4ksgau7utNKj4b963voxs"),
+    ("JUP", "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN"),
+];
+
+// Quotes a USD price stands in for, Pyth and Jupiter can't price e.g. ETH_BTC.
+const USD_QUOTES: [&str; 3] = ["USD", "USDT", "USDC"];
+
+/// A USD price reported by an oracle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct OraclePrice {
+    pub price: f64,
+    /// Confidence interval (±, in USD) when the oracle reports one.
+    pub confidence: Option<f64>,
+    /// Unix time in seconds at which the price was published.
+    pub publish_time: i64,
+    /// Which oracle answered, e.g. "pyth".
+    pub source: String,
+}
+
+impl OraclePrice {
+    /// Seconds between `publish_time` and `now` (unix seconds).
+    pub fn age_seconds(&self, now: i64) -> i64 {
+        (now - self.publish_time).max(0)
+    }
+}
+
+/// Something that can tell the current USD price of a token.
+#[allow(async_fn_in_trait)]
+pub trait PriceOracle {
+    /// Fetches the current price for `pair_symbol` (e.g. "SOL_USDT" or "SOL").
+    async fn fetch_price(&self, pair_symbol: &str) -> Result<OraclePrice>;
+}
+
+// Lets callers borrow an oracle instead of taking ownership of it.
+impl<T: PriceOracle> PriceOracle for &T {
+    async fn fetch_price(&self, pair_symbol: &str) -> Result<OraclePrice> {
+        (**self).fetch_price(pair_symbol).await
+    }
+}
+
+/// Pyth prices from the Hermes API.
+#[derive(Debug, Clone)]
+pub struct PythOracle {
+    base_url: String,
+    feed_ids: HashMap<String, String>,
+    client: Client,
+}
+
+impl Default for PythOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PythOracle {
+    pub fn new() -> Self {
+        PythOracle {
+            base_url: PYTH_HERMES_URL.to_string(),
+            feed_ids: PYTH_FEED_IDS
+                .iter()
+                .map(|(token, id)| (token.to_string(), id.to_string()))
+                .collect(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Adds or replaces the USD feed id for `token_symbol`.
+    pub fn with_feed_id(mut self, token_symbol: &str, feed_id: &str) -> Self {
+        self.feed_ids.insert(
+            token_symbol.to_uppercase(),
+            feed_id.trim_start_matches("0x").to_string(),
+        );
+        self
+    }
+
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PythLatest {
+    parsed: Vec<PythFeed>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PythFeed {
+    price: PythPrice,
+}
+
+#[derive(Debug, Deserialize)]
+struct PythPrice {
+    price: String,
+    conf: String,
+    expo: i32,
+    publish_time: i64,
+}
+
+impl PriceOracle for PythOracle {
+    async fn fetch_price(&self, pair_symbol: &str) -> Result<OraclePrice> {
+        let token_symbol = usd_quoted_base(pair_symbol, "Pyth")?;
+        let feed_id = self
+            .feed_ids
+            .get(&token_symbol)
+            .ok_or_else(|| anyhow!("No Pyth feed for {token_symbol}"))?;
+
+        let url = format!(
+            "{}/v2/updates/price/latest?ids[]={feed_id}&parsed=true",
+            self.base_url
+        );
+        let latest: PythLatest = get_oracle_json(&self.client, "Pyth", &url).await?;
+        let feed = latest
+            .parsed
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Pyth returned no price for {token_symbol}"))?;
+
+        // Prices are fixed point: value * 10^expo.
+        let scale = 10f64.powi(feed.price.expo);
+        let price = feed
+            .price
+            .price
+            .parse::<f64>()
+            .with_context(|| format!("Invalid Pyth price {}", feed.price.price))?;
+        let confidence = feed
+            .price
+            .conf
+            .parse::<f64>()
+            .with_context(|| format!("Invalid Pyth confidence {}", feed.price.conf))?;
+
+        Ok(OraclePrice {
+            price: price * scale,
+            confidence: Some(confidence * scale),
+            publish_time: feed.price.publish_time,
+            source: "pyth".to_string(),
+        })
+    }
+}
+
+/// Jupiter Price API v3. It doesn't report a publish time, so the fetch time is used.
+#[derive(Debug, Clone)]
+pub struct JupiterOracle {
+    base_url: String,
+    mints: HashMap<String, String>,
+    client: Client,
+}
+
+impl Default for JupiterOracle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JupiterOracle {
+    pub fn new() -> Self {
+        JupiterOracle {
+            base_url: JUPITER_PRICE_URL.to_string(),
+            mints: JUPITER_MINTS
+                .iter()
+                .map(|(token, mint)| (token.to_string(), mint.to_string()))
+                .collect(),
+            client: Client::new(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Adds or replaces the mint priced for `token_symbol`.
+    pub fn with_mint(mut self, token_symbol: &str, mint: &str) -> Self {
+        self.mints
+            .insert(token_symbol.to_uppercase(), mint.to_string());
+        self
+    }
+
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JupiterPrice {
+    usd_price: f64,
+}
+
+impl PriceOracle for JupiterOracle {
+    async fn fetch_price(&self, pair_symbol: &str) -> Result<OraclePrice> {
+        let token_symbol = usd_quoted_base(pair_symbol, "Jupiter")?;
+        let mint = self
+            .mints
+            .get(&token_symbol)
+            .ok_or_else(|| anyhow!("No Jupiter mint for {token_symbol}"))?;
+
+        let url = format!("{}/price/v3?ids={mint}", self.base_url);
+        let prices: HashMap<String, JupiterPrice> =
+            get_oracle_json(&self.client, "Jupiter", &url).await?;
+        let price = prices
+            .get(mint)
+            .ok_or_else(|| anyhow!("Jupiter returned no price for {token_symbol}"))?;
+
+        Ok(OraclePrice {
+            price: price.usd_price,
+            confidence: None,
+            publish_time: Utc::now().timestamp(),
+            source: "jupiter".to_string(),
+        })
+    }
+}
+
+// The base token of `pair_symbol`, refused when the pair isn't quoted in a dollar.
+fn usd_quoted_base(pair_symbol: &str, name: &str) -> Result<String> {
+    let pair_symbol = pair_symbol.parse::<PairSymbol>()?;
+    if !USD_QUOTES.contains(&pair_symbol.quote.as_str()) {
+        bail!("{name} only prices in USD, not {pair_symbol}");
+    }
+    Ok(pair_symbol.base)
+}
+
+/// Uses a `MarketDataSource`'s last traded price as an oracle, timestamped at fetch time.
+#[derive(Debug, Clone)]
+pub struct MarketPriceOracle<S> {
+    source: S,
+    name: String,
+}
+
+impl<S: MarketDataSource> MarketPriceOracle<S> {
+    pub fn new(source: S, name: &str) -> Self {
+        MarketPriceOracle {
+            source,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl<S: MarketDataSource> PriceOracle for MarketPriceOracle<S> {
+    async fn fetch_price(&self, pair_symbol: &str) -> Result<OraclePrice> {
+        let price = self.source.fetch_current_price(pair_symbol).await?;
+        Ok(OraclePrice {
+            price,
+            confidence: None,
+            publish_time: Utc::now().timestamp(),
+            source: self.name.clone(),
+        })
+    }
+}
+
+impl From<VenueSource> for MarketPriceOracle<VenueSource> {
+    fn from(source: VenueSource) -> Self {
+        let name = source.venue().to_string();
+        MarketPriceOracle::new(source, &name)
+    }
+}
+
+/// One of the oracles `FallbackOracle` can try.
+#[derive(Debug, Clone)]
+pub enum OracleSource {
+    Pyth(PythOracle),
+    Jupiter(JupiterOracle),
+    Market(MarketPriceOracle<VenueSource>),
+}
+
+impl From<PythOracle> for OracleSource {
+    fn from(oracle: PythOracle) -> Self {
+        OracleSource::Pyth(oracle)
+    }
+}
+
+impl From<JupiterOracle> for OracleSource {
+    fn from(oracle: JupiterOracle) -> Self {
+        OracleSource::Jupiter(oracle)
+    }
+}
+
+impl From<VenueSource> for OracleSource {
+    fn from(source: VenueSource) -> Self {
+        OracleSource::Market(source.into())
+    }
+}
+
+impl From<Venue> for OracleSource {
+    fn from(venue: Venue) -> Self {
+        VenueSource::from(venue).into()
+    }
+}
+
+impl From<BinanceClient> for OracleSource {
+    fn from(client: BinanceClient) -> Self {
+        VenueSource::Binance(client).into()
+    }
+}
+
+impl PriceOracle for OracleSource {
+    async fn fetch_price(&self, pair_symbol: &str) -> Result<OraclePrice> {
+        match self {
+            OracleSource::Pyth(oracle) => oracle.fetch_price(pair_symbol).await,
+            OracleSource::Jupiter(oracle) => oracle.fetch_price(pair_symbol).await,
+            OracleSource::Market(oracle) => oracle.fetch_price(pair_symbol).await,
+        }
+    }
+}
+
+/// Tries each oracle in order and returns the first fresh price.
+///
+/// A price older than `max_age` counts as a failure, so a stalled feed falls
+/// through to the next oracle.
+#[derive(Debug, Clone)]
+pub struct FallbackOracle {
+    sources: Vec<OracleSource>,
+    max_age: Duration,
+}
+
+impl Default for FallbackOracle {
+    /// Pyth, then Jupiter, then the Binance ticker.
+    fn default() -> Self {
+        Self::new(vec![
+            PythOracle::default().into(),
+            JupiterOracle::default().into(),
+            Venue::Binance.into(),
+        ])
+    }
+}
+
+impl FallbackOracle {
+    pub fn new(sources: Vec<OracleSource>) -> Self {
+        FallbackOracle {
+            sources,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+
+    /// Appends an oracle to try after the existing ones.
+    pub fn with_source(mut self, source: impl Into<OracleSource>) -> Self {
+        self.sources.push(source.into());
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+}
+
+impl PriceOracle for FallbackOracle {
+    async fn fetch_price(&self, pair_symbol: &str) -> Result<OraclePrice> {
+        let mut errors = Vec::new();
+
+        for source in &self.sources {
+            match source.fetch_price(pair_symbol).await {
+                Ok(price) => {
+                    let age = price.age_seconds(Utc::now().timestamp());
+                    if age as u64 <= self.max_age.as_secs() {
+                        return Ok(price);
+                    }
+                    errors.push(format!("{}: stale by {age}s", price.source));
+                }
+                Err(error) => errors.push(error.to_string()),
+            }
+        }
+
+        bail!(
+            "No oracle price for {pair_symbol}: {}",
+            if errors.is_empty() {
+                "no oracles configured".to_string()
+            } else {
+                errors.join("; ")
+            }
+        )
+    }
+}
+
+async fn get_oracle_json<T: DeserializeOwned>(client: &Client, name: &str, url: &str) -> Result<T> {
+    println!("Fetching data from: {url}");
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {name} API"))?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("{name} API error: {:?}", response.status()));
+    }
+
+    response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse JSON response from {name} API"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+    fn pyth_body(publish_time: i64) -> String {
+        format!(
+            r#"{{"binary":{{"encoding":"hex","data":[]}},"parsed":[{{"id":"{}","price":{{"price":"14712345678","conf":"5432100","expo":-8,"publish_time":{publish_time}}},"ema_price":{{"price":"14700000000","conf":"5000000","expo":-8,"publish_time":{publish_time}}}}}]}}"#,
+            PYTH_FEED_IDS[0].1
+        )
+    }
+
+    #[tokio::test]
+    async fn test_pyth_and_jupiter_against_stub() {
+        let now = Utc::now().timestamp();
+        let (base_url, requests) = crate::test_utils::spawn_http_stub(move |path| {
+            if path.starts_with("/v2/updates/price/latest") {
+                (200, pyth_body(now - 5))
+            } else if path.starts_with("/price/v3") {
+                (
+                    200,
+                    format!(
+                        r#"{{"{SOL_MINT}":{{"usdPrice":147.05,"blockId":1,"decimals":9,"priceChange24h":1.2}}}}"#
+                    ),
+                )
+            } else {
+                (404, "{}".to_string())
+            }
+        })
+        .await;
+
+        let pyth = PythOracle::new().with_base_url(&base_url);
+        let price = pyth.fetch_price("SOL_USDT").await.unwrap();
+        assert!((price.price - 147.12345678).abs() < 1e-9);
+        assert!((price.confidence.unwrap() - 0.054321).abs() < 1e-9);
+        assert_eq!(price.age_seconds(now), 5);
+        assert_eq!(price.source, "pyth");
+        assert!(pyth.fetch_price("DOGE").await.is_err());
+        assert!(pyth.fetch_price("ETH_BTC").await.is_err());
+
+        let jupiter = JupiterOracle::new().with_base_url(&base_url);
+        let price = jupiter.fetch_price("SOL").await.unwrap();
+        assert_eq!(price.price, 147.05);
+        assert_eq!(price.confidence, None);
+        assert!(jupiter.fetch_price("SOL_BTC").await.is_err());
+
+        assert_eq!(
+            requests.lock().unwrap()[1],
+            format!("/price/v3?ids={SOL_MINT}")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fallback_skips_failed_and_stale_oracles() {
+        let stale_time = Utc::now().timestamp() - 600;
+        let (stale_url, _) =
+            crate::test_utils::spawn_http_stub(move |_| (200, pyth_body(stale_time))).await;
+        let (down_url, _) =
+            crate::test_utils::spawn_http_stub(|_| (503, "unavailable".to_string())).await;
+        let (binance_url, _) = crate::test_utils::spawn_http_stub(|_| {
+            (200, r#"{"symbol":"SOLUSDT","price":"146.90"}"#.to_string())
+        })
+        .await;
+
+        let oracle = FallbackOracle::new(vec![
+            PythOracle::new().with_base_url(&stale_url).into(),
+            JupiterOracle::new().with_base_url(&down_url).into(),
+        ])
+        .with_source(
+            BinanceClient::default()
+                .with_base_url(&binance_url)
+                .with_proxy(None),
+        );
+
+        let price = oracle.fetch_price("SOL_USDT").await.unwrap();
+        assert_eq!(price.price, 146.9);
+        assert_eq!(price.source, "binance");
+
+        // Accepting older prices lets Pyth answer first.
+        let price = oracle
+            .clone()
+            .with_max_age(Duration::from_secs(3600))
+            .fetch_price("SOL_USDT")
+            .await
+            .unwrap();
+        assert_eq!(price.source, "pyth");
+
+        // A pair not quoted in a dollar is priced by the market, not in USD.
+        let price = oracle
+            .clone()
+            .with_max_age(Duration::from_secs(3600))
+            .fetch_price("SOL_BTC")
+            .await
+            .unwrap();
+        assert_eq!(price.source, "binance");
+
+        let error = FallbackOracle::new(vec![JupiterOracle::new().with_base_url(&down_url).into()])
+            .fetch_price("SOL")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Jupiter API error"));
+    }
+}
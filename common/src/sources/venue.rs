@@ -0,0 +1,276 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::{header::USER_AGENT, Client};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::{
+    binance::BinanceClient, bybit::BybitClient, coinbase::CoinbaseClient, market::MarketDataSource,
+    okx::OkxClient, AggTrade, FuturesMetrics, Kline, OrderBook, PairSymbol,
+};
+
+const VENUE_USER_AGENT: &str = concat!("catbot/", env!("CARGO_PKG_VERSION"));
+
+/// Exchanges we can read market data from.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, EnumString, Display, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[serde(rename_all = "lowercase")]
+pub enum Venue {
+    #[default]
+    Binance,
+    Bybit,
+    Okx,
+    Coinbase,
+}
+
+impl Venue {
+    /// The venue's own symbol for a pair like "SOL_USDT", see `PairSymbol::format_for`.
+    pub fn format_symbol(&self, pair_symbol: &str) -> Result<String> {
+        Ok(pair_symbol.parse::<PairSymbol>()?.format_for(*self))
+    }
+}
+
+// Shared GET for the venue adapters.
+pub(crate) async fn get_venue_json<T: DeserializeOwned>(
+    client: &Client,
+    venue: Venue,
+    url: &str,
+) -> Result<T> {
+    println!("Fetching data from: {url}");
+
+    let response = client
+        .get(url)
+        .header(USER_AGENT, VENUE_USER_AGENT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {venue} API"))?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("{venue} API error: {:?}", response.status()));
+    }
+
+    response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse JSON response from {venue} API"))
+}
+
+// Builds a kline from venue rows that only carry OHLCV; Binance-only fields stay empty.
+pub(crate) fn ohlcv_kline(
+    open_time: i64,
+    interval_millis: i64,
+    [open, high, low, close, volume]: [String; 5],
+    quote_asset_volume: Option<String>,
+) -> Kline {
+    Kline {
+        open_time,
+        open_price: open,
+        high_price: high,
+        low_price: low,
+        close_price: close,
+        volume,
+        close_time: open_time + interval_millis - 1,
+        quote_asset_volume: quote_asset_volume.unwrap_or_default(),
+        number_of_trades: 0,
+        taker_buy_base_asset_volume: String::new(),
+        taker_buy_quote_asset_volume: String::new(),
+        ignore: String::new(),
+    }
+}
+
+/// A `MarketDataSource` for a venue chosen at runtime, e.g. per pair.
+#[derive(Debug, Clone)]
+pub enum VenueSource {
+    Binance(BinanceClient),
+    Bybit(BybitClient),
+    Okx(OkxClient),
+    Coinbase(CoinbaseClient),
+}
+
+impl From<Venue> for VenueSource {
+    fn from(venue: Venue) -> Self {
+        match venue {
+            Venue::Binance => VenueSource::Binance(BinanceClient::default()),
+            Venue::Bybit => VenueSource::Bybit(BybitClient::default()),
+            Venue::Okx => VenueSource::Okx(OkxClient::default()),
+            Venue::Coinbase => VenueSource::Coinbase(CoinbaseClient::default()),
+        }
+    }
+}
+
+impl VenueSource {
+    pub fn venue(&self) -> Venue {
+        match self {
+            VenueSource::Binance(_) => Venue::Binance,
+            VenueSource::Bybit(_) => Venue::Bybit,
+            VenueSource::Okx(_) => Venue::Okx,
+            VenueSource::Coinbase(_) => Venue::Coinbase,
+        }
+    }
+}
+
+impl MarketDataSource for VenueSource {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        match self {
+            VenueSource::Binance(client) => {
+                MarketDataSource::fetch_klines(client, pair_symbol, interval, limit).await
+            }
+            VenueSource::Bybit(client) => client.fetch_klines(pair_symbol, interval, limit).await,
+            VenueSource::Okx(client) => client.fetch_klines(pair_symbol, interval, limit).await,
+            VenueSource::Coinbase(client) => {
+                client.fetch_klines(pair_symbol, interval, limit).await
+            }
+        }
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        match self {
+            VenueSource::Binance(client) => {
+                client
+                    .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+                    .await
+            }
+            VenueSource::Bybit(client) => {
+                client
+                    .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+                    .await
+            }
+            VenueSource::Okx(client) => {
+                client
+                    .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+                    .await
+            }
+            VenueSource::Coinbase(client) => {
+                client
+                    .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+                    .await
+            }
+        }
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        match self {
+            VenueSource::Binance(client) => client.fetch_orderbook(pair_symbol, limit).await,
+            VenueSource::Bybit(client) => client.fetch_orderbook(pair_symbol, limit).await,
+            VenueSource::Okx(client) => client.fetch_orderbook(pair_symbol, limit).await,
+            VenueSource::Coinbase(client) => client.fetch_orderbook(pair_symbol, limit).await,
+        }
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        match self {
+            VenueSource::Binance(client) => client.fetch_current_price(pair_symbol).await,
+            VenueSource::Bybit(client) => client.fetch_current_price(pair_symbol).await,
+            VenueSource::Okx(client) => client.fetch_current_price(pair_symbol).await,
+            VenueSource::Coinbase(client) => client.fetch_current_price(pair_symbol).await,
+        }
+    }
+
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        match self {
+            VenueSource::Binance(client) => {
+                MarketDataSource::fetch_agg_trades(client, pair_symbol, start_time, limit).await
+            }
+            VenueSource::Bybit(client) => {
+                client
+                    .fetch_agg_trades(pair_symbol, start_time, limit)
+                    .await
+            }
+            VenueSource::Okx(client) => {
+                client
+                    .fetch_agg_trades(pair_symbol, start_time, limit)
+                    .await
+            }
+            VenueSource::Coinbase(client) => {
+                client
+                    .fetch_agg_trades(pair_symbol, start_time, limit)
+                    .await
+            }
+        }
+    }
+
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        match self {
+            VenueSource::Binance(client) => {
+                MarketDataSource::fetch_futures_metrics(client, pair_symbol, period, limit).await
+            }
+            VenueSource::Bybit(client) => {
+                client
+                    .fetch_futures_metrics(pair_symbol, period, limit)
+                    .await
+            }
+            VenueSource::Okx(client) => {
+                client
+                    .fetch_futures_metrics(pair_symbol, period, limit)
+                    .await
+            }
+            VenueSource::Coinbase(client) => {
+                client
+                    .fetch_futures_metrics(pair_symbol, period, limit)
+                    .await
+            }
+        }
+    }
+}
+
+// Venue rows arrive as JSON strings or numbers depending on the exchange.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum StringOrNumber {
+    String(String),
+    Number(serde_json::Number),
+}
+
+impl std::fmt::Display for StringOrNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StringOrNumber::String(s) => write!(f, "{s}"),
+            StringOrNumber::Number(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_format_symbol_per_venue() {
+        assert_eq!(Venue::Binance.format_symbol("SOL_USDT").unwrap(), "SOLUSDT");
+        assert_eq!(Venue::Bybit.format_symbol("sol").unwrap(), "SOLUSDT");
+        assert_eq!(Venue::Okx.format_symbol("SOLUSDC").unwrap(), "SOL-USDC");
+        assert_eq!(
+            Venue::Coinbase.format_symbol("SOL_USDT").unwrap(),
+            "SOL-USD"
+        );
+        assert_eq!(Venue::Okx.format_symbol("ETHBTC").unwrap(), "ETH-BTC");
+        assert_eq!(
+            Venue::Binance.format_symbol("SOL_FDUSD").unwrap(),
+            "SOLFDUSD"
+        );
+        assert_eq!(Venue::from_str("OKX").unwrap(), Venue::Okx);
+    }
+}
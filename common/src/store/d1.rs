@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use worker::{wasm_bindgen::JsValue, D1Database};
+
+use super::sql::{
+    CREATE_KLINES_SQL, DELETE_BEFORE_SQL, SELECT_LATEST_SQL, SELECT_RANGE_SQL, UPSERT_KLINE_SQL,
+};
+use crate::{
+    kline_store::{KlineStore, SeriesKey},
+    Kline,
+};
+
+/// `KlineStore` in a Cloudflare D1 database, shared by every worker bound to it.
+///
+/// Uses the same schema and queries as `SqliteKlineStore`, call `migrate` once per database.
+pub struct D1KlineStore {
+    database: D1Database,
+}
+
+// D1 binds JS numbers, millisecond timestamps fit in an f64 exactly.
+fn number(value: i64) -> JsValue {
+    JsValue::from_f64(value as f64)
+}
+
+fn series_params(key: &SeriesKey) -> [JsValue; 3] {
+    [
+        key.venue.to_string().into(),
+        key.pair_symbol.to_string().into(),
+        key.interval.as_str().into(),
+    ]
+}
+
+impl D1KlineStore {
+    pub fn new(database: D1Database) -> Self {
+        D1KlineStore { database }
+    }
+
+    /// Creates the `klines` table if missing.
+    pub async fn migrate(&self) -> Result<()> {
+        self.database
+            .prepare(CREATE_KLINES_SQL)
+            .run()
+            .await
+            .map_err(|e| anyhow!("Failed to create klines table: {e}"))?;
+        Ok(())
+    }
+
+    async fn query(&self, sql: &str, params: &[JsValue]) -> Result<Vec<Kline>> {
+        self.database
+            .prepare(sql)
+            .bind(params)
+            .map_err(|e| anyhow!("Failed to bind kline query: {e}"))?
+            .all()
+            .await
+            .map_err(|e| anyhow!("Failed to query klines: {e}"))?
+            .results::<Kline>()
+            .map_err(|e| anyhow!("Failed to read klines: {e}"))
+    }
+}
+
+impl KlineStore for D1KlineStore {
+    async fn upsert(&self, key: &SeriesKey, klines: &[Kline]) -> Result<usize> {
+        if klines.is_empty() {
+            return Ok(0);
+        }
+
+        let [venue, pair_symbol, interval] = series_params(key);
+        let statements = klines
+            .iter()
+            .map(|kline| {
+                self.database.prepare(UPSERT_KLINE_SQL).bind(&[
+                    venue.clone(),
+                    pair_symbol.clone(),
+                    interval.clone(),
+                    number(kline.open_time),
+                    kline.open_price.as_str().into(),
+                    kline.high_price.as_str().into(),
+                    kline.low_price.as_str().into(),
+                    kline.close_price.as_str().into(),
+                    kline.volume.as_str().into(),
+                    number(kline.close_time),
+                    kline.quote_asset_volume.as_str().into(),
+                    number(kline.number_of_trades),
+                    kline.taker_buy_base_asset_volume.as_str().into(),
+                    kline.taker_buy_quote_asset_volume.as_str().into(),
+                ])
+            })
+            .collect::<worker::Result<Vec<_>>>()
+            .map_err(|e| anyhow!("Failed to bind klines for {key}: {e}"))?;
+
+        // A batch runs as one transaction.
+        self.database
+            .batch(statements)
+            .await
+            .map_err(|e| anyhow!("Failed to store klines for {key}: {e}"))?;
+        Ok(klines.len())
+    }
+
+    async fn range(&self, key: &SeriesKey, start_time: i64, end_time: i64) -> Result<Vec<Kline>> {
+        let [venue, pair_symbol, interval] = series_params(key);
+        self.query(
+            SELECT_RANGE_SQL,
+            &[
+                venue,
+                pair_symbol,
+                interval,
+                number(start_time),
+                number(end_time),
+            ],
+        )
+        .await
+    }
+
+    async fn latest(&self, key: &SeriesKey, limit: usize) -> Result<Vec<Kline>> {
+        let [venue, pair_symbol, interval] = series_params(key);
+        let mut klines = self
+            .query(
+                SELECT_LATEST_SQL,
+                &[venue, pair_symbol, interval, number(limit as i64)],
+            )
+            .await?;
+        klines.reverse();
+        Ok(klines)
+    }
+
+    async fn prune(&self, key: &SeriesKey, open_time: i64) -> Result<usize> {
+        let [venue, pair_symbol, interval] = series_params(key);
+        let result = self
+            .database
+            .prepare(DELETE_BEFORE_SQL)
+            .bind(&[venue, pair_symbol, interval, number(open_time)])
+            .map_err(|e| anyhow!("Failed to bind kline prune: {e}"))?
+            .run()
+            .await
+            .map_err(|e| anyhow!("Failed to prune klines for {key}: {e}"))?;
+        let deleted = result
+            .meta()
+            .map_err(|e| anyhow!("Failed to read prune result: {e}"))?
+            .and_then(|meta| meta.changes)
+            .unwrap_or_default();
+        Ok(deleted)
+    }
+}
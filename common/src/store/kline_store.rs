@@ -0,0 +1,493 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Mutex,
+};
+
+use crate::{
+    market::MarketDataSource, venue::Venue, AggTrade, FuturesMetrics, Interval, Kline, OrderBook,
+    PairSymbol,
+};
+
+/// Identifies one kline series, e.g. binance SOL_USDT 1h.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeriesKey {
+    pub venue: Venue,
+    pub pair_symbol: PairSymbol,
+    pub interval: Interval,
+}
+
+impl SeriesKey {
+    /// Parses `pair_symbol` ("SOL_USDT", "SOLUSDT") and `interval` ("1h") so spellings share a series.
+    pub fn new(venue: Venue, pair_symbol: &str, interval: &str) -> Result<Self> {
+        Ok(SeriesKey {
+            venue,
+            pair_symbol: pair_symbol.parse()?,
+            interval: interval.parse()?,
+        })
+    }
+
+    // Open time (ms) of the candle forming at `now` (ms).
+    fn current_open_time(&self, now: i64) -> i64 {
+        self.interval.align(now.div_euclid(1000)) * 1000
+    }
+}
+
+impl fmt::Display for SeriesKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.venue, self.pair_symbol, self.interval)
+    }
+}
+
+/// Local kline history, one series per `SeriesKey`, rows unique by open time.
+///
+/// Backends: `MemoryKlineStore`, `sqlite::SqliteKlineStore` (feature `sqlite`, native only) and
+/// `d1::D1KlineStore` (feature `d1_store`, workers). The SQL backends share one schema.
+#[allow(async_fn_in_trait)]
+pub trait KlineStore {
+    /// Inserts `klines`, replacing rows with the same open time. Returns the number written.
+    async fn upsert(&self, key: &SeriesKey, klines: &[Kline]) -> Result<usize>;
+
+    /// Every stored kline with `open_time` in `[start_time, end_time)` (ms), oldest first.
+    async fn range(&self, key: &SeriesKey, start_time: i64, end_time: i64) -> Result<Vec<Kline>>;
+
+    /// The latest `limit` stored klines, oldest first.
+    async fn latest(&self, key: &SeriesKey, limit: usize) -> Result<Vec<Kline>>;
+
+    /// Deletes klines opened before `open_time` (ms). Returns the number deleted.
+    async fn prune(&self, key: &SeriesKey, open_time: i64) -> Result<usize>;
+
+    /// Retention: keeps the `keep` candles before the one forming at `now` (ms), months
+    /// counting as 30 days.
+    async fn retain(&self, key: &SeriesKey, keep: i64, now: i64) -> Result<usize> {
+        let cutoff = key.current_open_time(now) - keep * key.interval.duration_ms();
+        self.prune(key, cutoff).await
+    }
+}
+
+impl<T: KlineStore> KlineStore for &T {
+    async fn upsert(&self, key: &SeriesKey, klines: &[Kline]) -> Result<usize> {
+        (**self).upsert(key, klines).await
+    }
+
+    async fn range(&self, key: &SeriesKey, start_time: i64, end_time: i64) -> Result<Vec<Kline>> {
+        (**self).range(key, start_time, end_time).await
+    }
+
+    async fn latest(&self, key: &SeriesKey, limit: usize) -> Result<Vec<Kline>> {
+        (**self).latest(key, limit).await
+    }
+
+    async fn prune(&self, key: &SeriesKey, open_time: i64) -> Result<usize> {
+        (**self).prune(key, open_time).await
+    }
+}
+
+/// In-process `KlineStore` for tests and short-lived runs.
+#[derive(Debug, Default)]
+pub struct MemoryKlineStore {
+    series: Mutex<HashMap<SeriesKey, BTreeMap<i64, Kline>>>,
+}
+
+impl MemoryKlineStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KlineStore for MemoryKlineStore {
+    async fn upsert(&self, key: &SeriesKey, klines: &[Kline]) -> Result<usize> {
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let rows = series.entry(key.clone()).or_default();
+        for kline in klines {
+            rows.insert(kline.open_time, kline.clone());
+        }
+        Ok(klines.len())
+    }
+
+    async fn range(&self, key: &SeriesKey, start_time: i64, end_time: i64) -> Result<Vec<Kline>> {
+        if start_time >= end_time {
+            return Ok(Vec::new());
+        }
+        let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(series
+            .get(key)
+            .map(|rows| {
+                rows.range(start_time..end_time)
+                    .map(|(_, k)| k.clone())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn latest(&self, key: &SeriesKey, limit: usize) -> Result<Vec<Kline>> {
+        let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let mut klines: Vec<Kline> = series
+            .get(key)
+            .map(|rows| rows.values().rev().take(limit).cloned().collect())
+            .unwrap_or_default();
+        klines.reverse();
+        Ok(klines)
+    }
+
+    async fn prune(&self, key: &SeriesKey, open_time: i64) -> Result<usize> {
+        let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(rows) = series.get_mut(key) else {
+            return Ok(0);
+        };
+        let kept = rows.split_off(&open_time);
+        let pruned = rows.len();
+        *rows = kept;
+        Ok(pruned)
+    }
+}
+
+/// Reads klines from a `KlineStore` and only asks `source` for what's missing.
+///
+/// Closed klines fetched from `source` are written back, so long windows are served locally
+/// once backfilled and later calls only fetch the candles since the last stored one. The
+/// forming candle is always fetched. A hole in the stored range (e.g. an exchange outage)
+/// means refetching from the hole on.
+///
+/// Orderbooks, prices, trades and futures metrics go straight to `source`. Store errors are
+/// logged and fall through to `source`.
+pub struct StoredSource<S, K> {
+    source: S,
+    store: K,
+    venue: Venue,
+}
+
+impl<S: MarketDataSource, K: KlineStore> StoredSource<S, K> {
+    /// `venue` is the venue `source` reads from, it keys the stored series.
+    pub fn new(source: S, store: K, venue: Venue) -> Self {
+        StoredSource {
+            source,
+            store,
+            venue,
+        }
+    }
+
+    // Writes back the klines closed at `now`.
+    async fn save(&self, key: &SeriesKey, klines: &[Kline], now: i64) {
+        let closed: Vec<Kline> = klines
+            .iter()
+            .filter(|kline| kline.close_time < now)
+            .cloned()
+            .collect();
+        if let Err(error) = self.store.upsert(key, &closed).await {
+            eprintln!("Store: Failed to write {key}: {error:#}");
+        }
+    }
+
+    async fn fetch_klines_at(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+        now: i64,
+    ) -> Result<Vec<Kline>> {
+        let key = SeriesKey::new(self.venue, pair_symbol, interval)?;
+        let limit_len = limit.max(0) as usize;
+        let current_open_time = key.current_open_time(now);
+
+        let stored = self
+            .store
+            .latest(&key, limit_len)
+            .await
+            .unwrap_or_else(|error| {
+                eprintln!("Store: Failed to read {key}: {error:#}");
+                Vec::new()
+            });
+        let missing = stored.last().map(|last| {
+            (current_open_time - last.open_time).div_euclid(key.interval.duration_ms())
+        });
+
+        // A hole in the stored tail would be served as is, only a full fetch fills it.
+        let is_contiguous = stored
+            .windows(2)
+            .all(|pair| key.interval.next_open_time(pair[0].open_time) == pair[1].open_time);
+
+        // Only top up when the stored tail is recent, otherwise a plain fetch is cheaper.
+        let (mut klines, fetched) = match (stored.last(), missing) {
+            (Some(last), Some(missing))
+                if stored.len() == limit_len && is_contiguous && missing < limit as i64 =>
+            {
+                let start_time = key.interval.next_open_time(last.open_time);
+                let end_time = key.interval.next_open_time(current_open_time);
+                let fetched = self
+                    .source
+                    .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+                    .await?;
+                (stored, fetched)
+            }
+            _ => {
+                let fetched = self
+                    .source
+                    .fetch_klines(pair_symbol, interval, limit)
+                    .await?;
+                (Vec::new(), fetched)
+            }
+        };
+
+        self.save(&key, &fetched, now).await;
+        klines.extend(fetched);
+        let skip = klines.len().saturating_sub(limit_len);
+        Ok(klines.split_off(skip))
+    }
+
+    async fn fetch_klines_range_at(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+        now: i64,
+    ) -> Result<Vec<Kline>> {
+        let key = SeriesKey::new(self.venue, pair_symbol, interval)?;
+        let closed_end_time = end_time.min(key.current_open_time(now));
+
+        let mut stored = self
+            .store
+            .range(&key, start_time, closed_end_time)
+            .await
+            .unwrap_or_else(|error| {
+                eprintln!("Store: Failed to read {key}: {error:#}");
+                Vec::new()
+            });
+
+        // First open time not covered by the stored candles from `start_time` on.
+        let mut expected = key.interval.align(start_time.div_euclid(1000)) * 1000;
+        if expected < start_time {
+            expected = key.interval.next_open_time(expected);
+        }
+        for kline in &stored {
+            if kline.open_time != expected {
+                break;
+            }
+            expected = key.interval.next_open_time(expected);
+        }
+        if expected >= end_time {
+            return Ok(stored);
+        }
+
+        let fetched = self
+            .source
+            .fetch_klines_range(pair_symbol, interval, expected, end_time)
+            .await?;
+        self.save(&key, &fetched, now).await;
+        stored.retain(|kline| kline.open_time < expected);
+        stored.extend(fetched);
+        Ok(stored)
+    }
+}
+
+impl<S: MarketDataSource, K: KlineStore> MarketDataSource for StoredSource<S, K> {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        let now = Utc::now().timestamp_millis();
+        self.fetch_klines_at(pair_symbol, interval, limit, now)
+            .await
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        let now = Utc::now().timestamp_millis();
+        self.fetch_klines_range_at(pair_symbol, interval, start_time, end_time, now)
+            .await
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        self.source.fetch_orderbook(pair_symbol, limit).await
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        self.source.fetch_current_price(pair_symbol).await
+    }
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        self.source
+            .fetch_agg_trades(pair_symbol, start_time, limit)
+            .await
+    }
+
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        self.source
+            .fetch_futures_metrics(pair_symbol, period, limit)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::CountingSource;
+
+    const HOUR: i64 = 3_600_000;
+
+    fn key() -> SeriesKey {
+        SeriesKey::new(Venue::Binance, "SOLUSDT", "1h").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_upsert_range_and_retention() {
+        let source = CountingSource::from_fixtures();
+        let klines = source
+            .source
+            .fetch_klines("SOL_USDT", "1h", 10)
+            .await
+            .unwrap();
+        let first = klines[0].open_time;
+        let store = MemoryKlineStore::new();
+        let key = key();
+        assert_eq!(key.to_string(), "binance SOL_USDT 1h");
+
+        store.upsert(&key, &klines[..6]).await.unwrap();
+        // Overlapping rows replace the stored ones.
+        let mut updated = klines[4..].to_vec();
+        updated[0].close_price = "1".to_string();
+        store.upsert(&key, &updated).await.unwrap();
+
+        let range = store
+            .range(&key, first + 2 * HOUR, first + 5 * HOUR)
+            .await
+            .unwrap();
+        assert_eq!(range.len(), 3);
+        assert_eq!(range[2].close_price, "1");
+        assert_eq!(
+            store.latest(&key, 3).await.unwrap()[2].open_time,
+            klines[9].open_time
+        );
+        assert_eq!(store.latest(&key, 100).await.unwrap().len(), 10);
+
+        let other = SeriesKey::new(Venue::Okx, "SOL_USDT", "1h").unwrap();
+        assert!(store.latest(&other, 10).await.unwrap().is_empty());
+
+        // Keep the 3 candles before the one forming right after the last stored.
+        let now = klines[9].open_time + HOUR + 60_000;
+        assert_eq!(store.retain(&key, 3, now).await.unwrap(), 7);
+        let kept = store.latest(&key, 100).await.unwrap();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].open_time, klines[7].open_time);
+    }
+
+    #[tokio::test]
+    async fn test_stored_source_fetches_only_missing_klines() {
+        let source = CountingSource::from_fixtures();
+        let all = source
+            .source
+            .fetch_klines("SOL_USDT", "1h", 500)
+            .await
+            .unwrap();
+        let last = all.last().unwrap().open_time;
+        // The last fixture candle is still forming.
+        let now = last + HOUR / 2;
+
+        let store = MemoryKlineStore::new();
+        let stored_source = StoredSource::new(&source, &store, Venue::Binance);
+
+        let klines = stored_source
+            .fetch_klines_at("SOL_USDT", "1h", 100, now)
+            .await
+            .unwrap();
+        assert_eq!(klines.len(), 100);
+        assert_eq!(source.fetches.get(), 1);
+        assert_eq!(store.latest(&key(), 500).await.unwrap().len(), 99);
+
+        // Only the forming candle is fetched again.
+        let klines = stored_source
+            .fetch_klines_at("SOLUSDT", "1h", 50, now)
+            .await
+            .unwrap();
+        assert_eq!(klines.len(), 50);
+        assert_eq!(klines.last().unwrap().open_time, last);
+        assert!(klines
+            .windows(2)
+            .all(|w| w[1].open_time - w[0].open_time == HOUR));
+        assert_eq!(source.fetches.get(), 2);
+
+        // Closed history already stored is served locally.
+        let start = all[450].open_time;
+        let range = stored_source
+            .fetch_klines_range_at("SOL_USDT", "1h", start, start + 20 * HOUR, now)
+            .await
+            .unwrap();
+        assert_eq!(range.len(), 20);
+        assert_eq!(source.fetches.get(), 2);
+
+        // Older history is fetched once, then stored.
+        let start = all[300].open_time;
+        let range = stored_source
+            .fetch_klines_range_at("SOL_USDT", "1h", start, all[460].open_time, now)
+            .await
+            .unwrap();
+        assert_eq!(range.len(), 160);
+        assert_eq!(range[0].open_time, start);
+        assert_eq!(source.fetches.get(), 3);
+        stored_source
+            .fetch_klines_range_at("SOL_USDT", "1h", start, all[460].open_time, now)
+            .await
+            .unwrap();
+        assert_eq!(source.fetches.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stored_source_refetches_klines_with_a_hole() {
+        let source = CountingSource::from_fixtures();
+        let all = source
+            .source
+            .fetch_klines("SOL_USDT", "1h", 500)
+            .await
+            .unwrap();
+        let last = all.last().unwrap().open_time;
+        let now = last + HOUR / 2;
+
+        // Closed history stored with one candle missing in the middle.
+        let store = MemoryKlineStore::new();
+        let (before, after) = all[..all.len() - 1].split_at(470);
+        store.upsert(&key(), before).await.unwrap();
+        store.upsert(&key(), &after[1..]).await.unwrap();
+        let stored_source = StoredSource::new(&source, &store, Venue::Binance);
+
+        // The hole is fetched in full instead of topped up around.
+        let klines = stored_source
+            .fetch_klines_at("SOL_USDT", "1h", 50, now)
+            .await
+            .unwrap();
+        assert_eq!(klines.len(), 50);
+        assert_eq!(klines.last().unwrap().open_time, last);
+        assert!(klines
+            .windows(2)
+            .all(|w| w[1].open_time - w[0].open_time == HOUR));
+        assert_eq!(source.fetches.get(), 1);
+
+        // Filled in, the next call only tops up again.
+        let klines = stored_source
+            .fetch_klines_at("SOL_USDT", "1h", 50, now)
+            .await
+            .unwrap();
+        assert!(klines
+            .windows(2)
+            .all(|w| w[1].open_time - w[0].open_time == HOUR));
+        assert_eq!(source.fetches.get(), 2);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod kline_store;
+// Schema and queries shared by the SQL backends
+#[cfg(any(feature = "sqlite", feature = "d1_store"))]
+mod sql;
+// D1 is only reachable from a Cloudflare Worker
+#[cfg(feature = "d1_store")]
+pub mod d1;
+// Bundled SQLite needs a native target
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub mod sqlite;
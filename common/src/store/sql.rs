@@ -0,0 +1,53 @@
+// Shared by the SQLite and D1 backends, so keep to SQL both understand.
+pub const CREATE_KLINES_SQL: &str = "CREATE TABLE IF NOT EXISTS klines (
+    venue TEXT NOT NULL,
+    pair_symbol TEXT NOT NULL,
+    interval TEXT NOT NULL,
+    open_time INTEGER NOT NULL,
+    open_price TEXT NOT NULL,
+    high_price TEXT NOT NULL,
+    low_price TEXT NOT NULL,
+    close_price TEXT NOT NULL,
+    volume TEXT NOT NULL,
+    close_time INTEGER NOT NULL,
+    quote_asset_volume TEXT NOT NULL DEFAULT '',
+    number_of_trades INTEGER NOT NULL DEFAULT 0,
+    taker_buy_base_asset_volume TEXT NOT NULL DEFAULT '',
+    taker_buy_quote_asset_volume TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (venue, pair_symbol, interval, open_time)
+)";
+
+pub const UPSERT_KLINE_SQL: &str = "INSERT INTO klines (
+    venue, pair_symbol, interval, open_time, open_price, high_price, low_price, close_price,
+    volume, close_time, quote_asset_volume, number_of_trades, taker_buy_base_asset_volume,
+    taker_buy_quote_asset_volume
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+ON CONFLICT (venue, pair_symbol, interval, open_time) DO UPDATE SET
+    open_price = excluded.open_price,
+    high_price = excluded.high_price,
+    low_price = excluded.low_price,
+    close_price = excluded.close_price,
+    volume = excluded.volume,
+    close_time = excluded.close_time,
+    quote_asset_volume = excluded.quote_asset_volume,
+    number_of_trades = excluded.number_of_trades,
+    taker_buy_base_asset_volume = excluded.taker_buy_base_asset_volume,
+    taker_buy_quote_asset_volume = excluded.taker_buy_quote_asset_volume";
+
+pub const SELECT_RANGE_SQL: &str = "SELECT open_time, open_price, high_price, low_price,
+    close_price, volume, close_time, quote_asset_volume, number_of_trades,
+    taker_buy_base_asset_volume, taker_buy_quote_asset_volume
+FROM klines
+WHERE venue = ?1 AND pair_symbol = ?2 AND interval = ?3 AND open_time >= ?4 AND open_time < ?5
+ORDER BY open_time";
+
+pub const SELECT_LATEST_SQL: &str = "SELECT open_time, open_price, high_price, low_price,
+    close_price, volume, close_time, quote_asset_volume, number_of_trades,
+    taker_buy_base_asset_volume, taker_buy_quote_asset_volume
+FROM klines
+WHERE venue = ?1 AND pair_symbol = ?2 AND interval = ?3
+ORDER BY open_time DESC
+LIMIT ?4";
+
+pub const DELETE_BEFORE_SQL: &str = "DELETE FROM klines
+WHERE venue = ?1 AND pair_symbol = ?2 AND interval = ?3 AND open_time < ?4";
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, Row};
+use std::{path::Path, sync::Mutex};
+
+use super::sql::{
+    CREATE_KLINES_SQL, DELETE_BEFORE_SQL, SELECT_LATEST_SQL, SELECT_RANGE_SQL, UPSERT_KLINE_SQL,
+};
+use crate::{
+    kline_store::{KlineStore, SeriesKey},
+    Kline,
+};
+
+/// `KlineStore` in a local SQLite file, for backtests and long-running native processes.
+///
+/// Queries are short and run inline on the calling task.
+pub struct SqliteKlineStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteKlineStore {
+    /// Opens (or creates) the database at `path` and its `klines` table.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open kline store {}", path.display()))?;
+        Self::from_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self> {
+        connection
+            .execute(CREATE_KLINES_SQL, [])
+            .context("Failed to create klines table")?;
+        Ok(SqliteKlineStore {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Kline>> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let mut statement = connection.prepare_cached(sql)?;
+        let klines = statement
+            .query_map(params, kline_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(klines)
+    }
+}
+
+fn kline_from_row(row: &Row) -> rusqlite::Result<Kline> {
+    Ok(Kline {
+        open_time: row.get("open_time")?,
+        open_price: row.get("open_price")?,
+        high_price: row.get("high_price")?,
+        low_price: row.get("low_price")?,
+        close_price: row.get("close_price")?,
+        volume: row.get("volume")?,
+        close_time: row.get("close_time")?,
+        quote_asset_volume: row.get("quote_asset_volume")?,
+        number_of_trades: row.get("number_of_trades")?,
+        taker_buy_base_asset_volume: row.get("taker_buy_base_asset_volume")?,
+        taker_buy_quote_asset_volume: row.get("taker_buy_quote_asset_volume")?,
+        ignore: "0".to_string(),
+    })
+}
+
+impl KlineStore for SqliteKlineStore {
+    async fn upsert(&self, key: &SeriesKey, klines: &[Kline]) -> Result<usize> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(UPSERT_KLINE_SQL)?;
+            for kline in klines {
+                statement.execute(params![
+                    key.venue.to_string(),
+                    key.pair_symbol.to_string(),
+                    key.interval.as_str(),
+                    kline.open_time,
+                    kline.open_price,
+                    kline.high_price,
+                    kline.low_price,
+                    kline.close_price,
+                    kline.volume,
+                    kline.close_time,
+                    kline.quote_asset_volume,
+                    kline.number_of_trades,
+                    kline.taker_buy_base_asset_volume,
+                    kline.taker_buy_quote_asset_volume,
+                ])?;
+            }
+        }
+        transaction
+            .commit()
+            .with_context(|| format!("Failed to store klines for {key}"))?;
+        Ok(klines.len())
+    }
+
+    async fn range(&self, key: &SeriesKey, start_time: i64, end_time: i64) -> Result<Vec<Kline>> {
+        self.query(
+            SELECT_RANGE_SQL,
+            params![
+                key.venue.to_string(),
+                key.pair_symbol.to_string(),
+                key.interval.as_str(),
+                start_time,
+                end_time,
+            ],
+        )
+    }
+
+    async fn latest(&self, key: &SeriesKey, limit: usize) -> Result<Vec<Kline>> {
+        let mut klines = self.query(
+            SELECT_LATEST_SQL,
+            params![
+                key.venue.to_string(),
+                key.pair_symbol.to_string(),
+                key.interval.as_str(),
+                limit as i64,
+            ],
+        )?;
+        klines.reverse();
+        Ok(klines)
+    }
+
+    async fn prune(&self, key: &SeriesKey, open_time: i64) -> Result<usize> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let deleted = connection.execute(
+            DELETE_BEFORE_SQL,
+            params![
+                key.venue.to_string(),
+                key.pair_symbol.to_string(),
+                key.interval.as_str(),
+                open_time,
+            ],
+        )?;
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture::FixtureSource, market::MarketDataSource, venue::Venue};
+
+    const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+    #[tokio::test]
+    async fn test_sqlite_store_round_trip() {
+        let source = FixtureSource::from_dir(FIXTURES_DIR).unwrap();
+        let klines = source.fetch_klines("SOL_USDT", "1h", 48).await.unwrap();
+        let key = SeriesKey::new(Venue::Binance, "SOL_USDT", "1h").unwrap();
+        let store = SqliteKlineStore::open_in_memory().unwrap();
+
+        assert_eq!(store.upsert(&key, &klines).await.unwrap(), 48);
+        let mut updated = klines[47].clone();
+        updated.close_price = "1".to_string();
+        store.upsert(&key, &[updated]).await.unwrap();
+
+        let latest = store.latest(&key, 2).await.unwrap();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].open_time, klines[46].open_time);
+        assert_eq!(latest[1].close_price, "1");
+
+        let range = store
+            .range(&key, klines[10].open_time, klines[20].open_time)
+            .await
+            .unwrap();
+        assert_eq!(range.len(), 10);
+        assert_eq!(range[0].volume, klines[10].volume);
+        assert_eq!(range[0].number_of_trades, klines[10].number_of_trades);
+
+        assert_eq!(store.prune(&key, klines[40].open_time).await.unwrap(), 40);
+        assert_eq!(store.latest(&key, 100).await.unwrap().len(), 8);
+    }
+}
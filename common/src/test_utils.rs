@@ -0,0 +1,158 @@
+// Helpers shared by unit tests.
+
+use anyhow::Result;
+use std::{
+    cell::Cell,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::{
+    fixture::FixtureSource, market::MarketDataSource, AggTrade, Candle, FuturesMetrics, Kline,
+    OrderBook,
+};
+
+const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// Serves the recorded fixtures and counts the fetches that reach it.
+pub struct CountingSource {
+    pub source: FixtureSource,
+    pub fetches: Cell<usize>,
+}
+
+impl CountingSource {
+    pub fn from_fixtures() -> Self {
+        CountingSource {
+            source: FixtureSource::from_dir(FIXTURES_DIR).unwrap(),
+            fetches: Cell::new(0),
+        }
+    }
+}
+
+impl MarketDataSource for CountingSource {
+    async fn fetch_klines(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        limit: i32,
+    ) -> Result<Vec<Kline>> {
+        self.fetches.set(self.fetches.get() + 1);
+        self.source.fetch_klines(pair_symbol, interval, limit).await
+    }
+
+    async fn fetch_klines_range(
+        &self,
+        pair_symbol: &str,
+        interval: &str,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<Kline>> {
+        self.fetches.set(self.fetches.get() + 1);
+        self.source
+            .fetch_klines_range(pair_symbol, interval, start_time, end_time)
+            .await
+    }
+
+    async fn fetch_orderbook(&self, pair_symbol: &str, limit: i32) -> Result<OrderBook> {
+        self.fetches.set(self.fetches.get() + 1);
+        self.source.fetch_orderbook(pair_symbol, limit).await
+    }
+
+    async fn fetch_current_price(&self, pair_symbol: &str) -> Result<f64> {
+        self.source.fetch_current_price(pair_symbol).await
+    }
+    async fn fetch_agg_trades(
+        &self,
+        pair_symbol: &str,
+        start_time: i64,
+        limit: usize,
+    ) -> Result<Vec<AggTrade>> {
+        self.source
+            .fetch_agg_trades(pair_symbol, start_time, limit)
+            .await
+    }
+
+    async fn fetch_futures_metrics(
+        &self,
+        pair_symbol: &str,
+        period: &str,
+        limit: usize,
+    ) -> Result<FuturesMetrics> {
+        self.source
+            .fetch_futures_metrics(pair_symbol, period, limit)
+            .await
+    }
+}
+
+/// One-minute candles closing at `closes`, with a high and low one above and below the close.
+pub fn candles_from_closes(closes: &[f64]) -> Vec<Candle> {
+    closes
+        .iter()
+        .enumerate()
+        .map(|(i, &close)| Candle {
+            open_time: i as i64 * 60_000,
+            open: close,
+            high: close + 1.0,
+            low: close - 1.0,
+            close,
+            volume: 1.0,
+            close_time: i as i64 * 60_000 + 59_999,
+        })
+        .collect()
+}
+
+/// Starts a local HTTP server answering every request with `handler(path_and_query)`.
+///
+/// Returns the base url (`http://127.0.0.1:<port>`) and the list of requested paths.
+pub async fn spawn_http_stub<F>(handler: F) -> (String, Arc<Mutex<Vec<String>>>)
+where
+    F: Fn(&str) -> (u16, String) + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let handler = Arc::new(handler);
+
+    let requests_for_server = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                break;
+            };
+            let handler = handler.clone();
+            let requests = requests_for_server.clone();
+            tokio::spawn(async move {
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut chunk).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                    }
+                }
+
+                let request = String::from_utf8_lossy(&buffer);
+                let path = request
+                    .lines()
+                    .next()
+                    .and_then(|line| line.split_whitespace().nth(1))
+                    .unwrap_or("/")
+                    .to_string();
+                requests.lock().unwrap().push(path.clone());
+
+                let (status, body) = handler(&path);
+                let response = format!(
+                    "HTTP/1.1 {status} STUB\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+
+    (base_url, requests)
+}
@@ -1,10 +1,32 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use futures::{stream, StreamExt};
 use std::collections::HashMap;
 
 use crate::{
-    binance::{fetch_binance_kline_usdt, klines_to_csv},
+    atr::{calculate_atr, format_atr_stop_buffers, parse_atr_csv},
+    binance::{candles_to_csv, candles_with_taker_pressure_to_csv, BinanceClient},
+    bollinger::{calculate_bollinger_bands, parse_bollinger_csv},
+    derivatives::{funding_rates_to_csv, positioning_to_csv, DerivativesSnapshot},
+    divergence::{divergences_to_csv, find_divergences},
+    ema::{calculate_ema_cross, parse_ema_csv},
+    fibonacci::get_fibonacci_csv,
+    indicator_config::{BollingerConfig, EmaCrossConfig, IndicatorConfig, MacdConfig},
+    macd::{calculate_macd, parse_macd_csv},
+    market::MarketDataSource,
+    moving_average::{calculate_moving_averages, parse_ma_csv, MovingAverage},
+    numbers::FractionalPart,
+    order_flow::{
+        large_trade_threshold, large_trades, large_trades_to_csv, order_flow, order_flow_to_csv,
+        Trade,
+    },
+    patterns::{find_patterns, patterns_to_csv, PATTERN_LOOKBACK},
+    quality::{check_and_repair, DataQualityReport, QualityConfig},
+    resample::{can_resample, candles_per, resample_klines},
     rsi::{get_latest_bb_ma, get_stoch_rsi_csv},
-    Kline,
+    support_resistance::{book_clusters, find_zones, pivots_from_candles, zones_to_csv},
+    taker_pressure::{taker_pressure, taker_pressure_to_csv},
+    venue::{Venue, VenueSource},
+    Candle, Interval, Kline, OrderBook, TradingContext,
 };
 
 // Helper function to parse interval specification strings like "1h" or "1h:200".
@@ -30,7 +52,8 @@ fn parse_interval_specs_list(specs: &[&str]) -> Vec<(String, Option<i32>)> {
 }
 
 // The Price History Builder
-pub struct PriceHistoryBuilder<'a> {
+pub struct PriceHistoryBuilder<'a, S = BinanceClient> {
+    source: S,
     pair_symbol: &'a str,
     default_limit: i32,
     kline_intervals: Vec<(String, Option<i32>)>,
@@ -38,12 +61,70 @@ pub struct PriceHistoryBuilder<'a> {
     bb_intervals: Vec<(String, Option<i32>)>,
     ma_intervals: Vec<(String, Option<i32>)>,
     latest_bb_ma_intervals: Vec<(String, Option<i32>)>,
+    ema_intervals: Vec<(String, Option<i32>)>,
+    macd_intervals: Vec<(String, Option<i32>)>,
+    atr_intervals: Vec<(String, Option<i32>)>,
+    fibonacci_intervals: Vec<(String, Option<i32>)>,
+    support_resistance_intervals: Vec<(String, Option<i32>)>,
+    orderbook: Option<OrderBook>,
+    pattern_intervals: Vec<(String, Option<i32>)>,
+    divergence_intervals: Vec<(String, Option<i32>)>,
+    taker_pressure_intervals: Vec<(String, Option<i32>)>,
+    taker_columns: bool,
+    order_flow_intervals: Vec<(String, Option<i32>)>,
+    max_trades: usize,
+    derivatives_periods: Vec<(String, Option<i32>)>,
+    indicators: IndicatorConfig,
+    quality: Option<QualityConfig>,
+    base_intervals: Vec<String>,
+    required_intervals: Vec<String>,
+    concurrency: usize,
 }
 
+// Candles of one interval along with the klines they were parsed from.
+struct IntervalData {
+    klines: Vec<Kline>,
+    candles: Vec<Candle>,
+}
+
+// Fetched candles by interval name, or why the interval is unavailable.
+type KlineDataMap = HashMap<String, Result<IntervalData, String>>;
+
+// One interval's klines along with their data-quality report, if checked.
+type IntervalFetch = Result<(Vec<Kline>, Option<DataQualityReport>)>;
+
+const DEFAULT_CONCURRENCY: usize = 4;
+// Order flow covers fewer candles than the other sections by default, trades are heavy to fetch.
+const DEFAULT_ORDER_FLOW_CANDLES: i32 = 12;
+const DEFAULT_MAX_TRADES: usize = 10_000;
+const MAX_LARGE_TRADES: usize = 10;
+const DEFAULT_DERIVATIVES_PERIODS: i32 = 24;
+// Order book depth read for support/resistance zones and the largest buckets kept per side.
+const ZONE_ORDERBOOK_LIMIT: i32 = 1000;
+const ZONE_BOOK_CLUSTERS: usize = 5;
+const MAX_ZONES: usize = 8;
+
 impl<'a> PriceHistoryBuilder<'a> {
-    /// Creates a new PriceHistoryBuilder.
+    /// Creates a new PriceHistoryBuilder backed by live Binance data.
     pub fn new(pair_symbol: &'a str, default_limit: i32) -> Self {
+        Self::from_source(BinanceClient::default(), pair_symbol, default_limit)
+    }
+
+    /// Creates a new PriceHistoryBuilder backed by live data from `venue`.
+    pub fn from_venue(
+        venue: Venue,
+        pair_symbol: &'a str,
+        default_limit: i32,
+    ) -> PriceHistoryBuilder<'a, VenueSource> {
+        PriceHistoryBuilder::from_source(VenueSource::from(venue), pair_symbol, default_limit)
+    }
+}
+
+impl<'a, S: MarketDataSource> PriceHistoryBuilder<'a, S> {
+    /// Creates a new PriceHistoryBuilder that reads klines from `source`.
+    pub fn from_source(source: S, pair_symbol: &'a str, default_limit: i32) -> Self {
         PriceHistoryBuilder {
+            source,
             pair_symbol,
             default_limit,
             kline_intervals: Vec::new(),
@@ -51,6 +132,24 @@ impl<'a> PriceHistoryBuilder<'a> {
             bb_intervals: Vec::new(),
             ma_intervals: Vec::new(),
             latest_bb_ma_intervals: Vec::new(),
+            ema_intervals: Vec::new(),
+            macd_intervals: Vec::new(),
+            atr_intervals: Vec::new(),
+            fibonacci_intervals: Vec::new(),
+            support_resistance_intervals: Vec::new(),
+            orderbook: None,
+            pattern_intervals: Vec::new(),
+            divergence_intervals: Vec::new(),
+            taker_pressure_intervals: Vec::new(),
+            taker_columns: false,
+            order_flow_intervals: Vec::new(),
+            max_trades: DEFAULT_MAX_TRADES,
+            derivatives_periods: Vec::new(),
+            indicators: IndicatorConfig::default(),
+            quality: None,
+            base_intervals: Vec::new(),
+            required_intervals: Vec::new(),
+            concurrency: DEFAULT_CONCURRENCY,
         }
     }
 
@@ -68,30 +167,247 @@ impl<'a> PriceHistoryBuilder<'a> {
         self
     }
 
+    /// Adds Bollinger Band history intervals, 20 periods and 2 standard deviations unless
+    /// set with `with_bb_settings` or the indicator config. Can be called multiple times.
     pub fn with_bb(mut self, intervals: &[&str]) -> Self {
         self.bb_intervals
             .extend(parse_interval_specs_list(intervals));
         self
     }
 
+    /// Sets the SMA period and band width, in standard deviations, of the `with_bb` section.
+    /// Same as the config's `bb` setting, a later `with_indicator_config` replaces it.
+    pub fn with_bb_settings(mut self, period: usize, std_dev: f64) -> Self {
+        self.indicators.bollinger = BollingerConfig { period, std_dev };
+        self
+    }
+
+    /// Adds moving average history intervals, SMA 7/25/99 unless set with `with_ma_lines`
+    /// or the indicator config. Can be called multiple times.
     pub fn with_ma(mut self, intervals: &[&str]) -> Self {
         self.ma_intervals
             .extend(parse_interval_specs_list(intervals));
         self
     }
 
+    /// Sets the lines of the `with_ma` section, e.g. `[MovingAverage::ema(50), MovingAverage::sma(200)]`.
+    /// Same as the config's `ma` setting, a later `with_indicator_config` replaces it.
+    pub fn with_ma_lines(mut self, lines: &[MovingAverage]) -> Self {
+        self.indicators.moving_averages = lines.to_vec();
+        self
+    }
+
+    /// Periods of every indicator section, defaults to `IndicatorConfig::default()`.
+    /// Pass the config the chart is drawn with so both show the same numbers.
+    pub fn with_indicator_config(mut self, indicators: IndicatorConfig) -> Self {
+        self.indicators = indicators;
+        self
+    }
+
     pub fn with_latest_bb_ma(mut self, intervals: &[&str]) -> Self {
         self.latest_bb_ma_intervals
             .extend(parse_interval_specs_list(intervals));
         self
     }
 
-    /// Fetches the required Kline data sequentially, one interval at a time.
-    async fn fetch_each_intervals(&self) -> Result<HashMap<String, Vec<Kline>>> {
+    /// Adds fast and slow EMA intervals, EMA(9) and EMA(21) by default, marking the candles where they cross.
+    /// Can be called multiple times.
+    pub fn with_ema(mut self, intervals: &[&str]) -> Self {
+        self.ema_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Adds MACD intervals, MACD(12,26,9) by default, marking the candles where MACD crosses its signal line.
+    /// Can be called multiple times.
+    pub fn with_macd(mut self, intervals: &[&str]) -> Self {
+        self.macd_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Adds ATR intervals, ATR(14) by default, with each candle's range in ATRs and the 1.5x/2x ATR
+    /// stop buffers of the latest candle. Can be called multiple times.
+    pub fn with_atr(mut self, intervals: &[&str]) -> Self {
+        self.atr_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Adds Fibonacci intervals: the swing highs and lows over the last `limit` candles, found
+    /// with the config's swing threshold (5% by default), and the 38.2/61.8/100% retracements and
+    /// 161.8% extension of the latest leg. Can be called multiple times.
+    pub fn with_fibonacci(mut self, intervals: &[&str]) -> Self {
+        self.fibonacci_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Adds support/resistance intervals: the swing pivots over the last `limit` candles of
+    /// every interval are clustered with the largest order book buckets into one list of zones,
+    /// scored by touches, volume, resting quantity and recency. Can be called multiple times.
+    pub fn with_support_resistance(mut self, intervals: &[&str]) -> Self {
+        self.support_resistance_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Clusters the support/resistance zones from `orderbook` instead of fetching a depth
+    /// 1000 book, for callers that already hold one.
+    pub fn with_orderbook(mut self, orderbook: OrderBook) -> Self {
+        self.orderbook = Some(orderbook);
+        self
+    }
+
+    /// Adds recent candlestick pattern intervals: engulfings, hammers, shooting stars, dojis,
+    /// morning/evening stars and inside bars completed within the last `limit` candles.
+    /// Can be called multiple times.
+    pub fn with_patterns(mut self, intervals: &[&str]) -> Self {
+        self.pattern_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Adds divergence intervals: regular and hidden divergences between the swings of price,
+    /// found with the config's divergence threshold (2% by default), and RSI, Stoch RSI, the
+    /// MACD histogram and volume, ending within the last `limit` candles.
+    /// Can be called multiple times.
+    pub fn with_divergences(mut self, intervals: &[&str]) -> Self {
+        self.divergence_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Adds taker pressure intervals: quote volume, taker buy ratio and how unusual the trade
+    /// count is, per candle. Comes from the klines themselves, no extra fetch. Can be called
+    /// multiple times.
+    pub fn with_taker_pressure(mut self, intervals: &[&str]) -> Self {
+        self.taker_pressure_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// Appends the taker pressure columns (`quote_volume,taker_buy_ratio,trades,trade_count_zscore`)
+    /// to every table of the Klines section.
+    pub fn with_taker_columns(mut self) -> Self {
+        self.taker_columns = true;
+        self
+    }
+
+    /// Adds order flow intervals, built from aggregate trades: taker buy and sell volume,
+    /// cumulative volume delta and large prints per candle. Covers 12 candles unless the spec
+    /// says otherwise (e.g. "1h:24"). Can be called multiple times.
+    pub fn with_order_flow(mut self, intervals: &[&str]) -> Self {
+        self.order_flow_intervals
+            .extend(parse_interval_specs_list(intervals));
+        self
+    }
+
+    /// How many of the latest trades the order flow section reads at most, 10000 by default,
+    /// shared by all of its intervals. Candles older than the oldest trade read are left out
+    /// and the one it falls in is marked as partly covered.
+    pub fn with_max_trades(mut self, max_trades: usize) -> Self {
+        self.max_trades = max_trades;
+        self
+    }
+
+    /// Adds perpetual futures positioning periods: open interest and the top traders' long/short
+    /// ratio per period, plus the funding rates settled over the window. Covers 24 periods unless
+    /// the spec says otherwise (e.g. "4h:30"). The first period given is also summarised into a
+    /// `DerivativesSnapshot`, see `build_with_context`. Can be called multiple times.
+    pub fn with_derivatives(mut self, periods: &[&str]) -> Self {
+        self.derivatives_periods
+            .extend(parse_interval_specs_list(periods));
+        self
+    }
+
+    /// Checks every fetched interval for gaps, duplicates, out-of-order rows and staleness,
+    /// repairs them following `config` and refuses to build on data that stays broken.
+    pub fn with_quality_check(mut self, config: QualityConfig) -> Self {
+        self.quality = Some(config);
+        self
+    }
+
+    /// Fetches only these intervals (e.g. `&["15m", "4h"]`) and resamples every coarser
+    /// interval from the coarsest base that divides it, other intervals are still fetched as is.
+    /// Can be called multiple times.
+    pub fn with_base_intervals(mut self, intervals: &[&str]) -> Self {
+        self.base_intervals
+            .extend(intervals.iter().map(|interval| interval.to_string()));
+        self
+    }
+
+    /// How many intervals are fetched at once, 4 by default.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Requested intervals `build` can't do without. Any other interval that fails to fetch only
+    /// marks its sections as unavailable, as long as at least one interval succeeded.
+    /// Can be called multiple times.
+    pub fn with_required_intervals(mut self, intervals: &[&str]) -> Self {
+        self.required_intervals
+            .extend(intervals.iter().map(|interval| interval.to_string()));
+        self
+    }
+
+    // The base interval `interval_name` is resampled from, if any.
+    fn base_interval_for(&self, interval_name: &str) -> Result<Option<(Interval, Interval)>> {
+        let bases = self
+            .base_intervals
+            .iter()
+            .map(|base| base.parse::<Interval>())
+            .collect::<Result<Vec<_>>>()?;
+        let Ok(interval) = interval_name.parse::<Interval>() else {
+            return Ok(None);
+        };
+        if bases.contains(&interval) {
+            return Ok(None);
+        }
+        Ok(bases
+            .into_iter()
+            .filter(|base| can_resample(*base, interval))
+            .max()
+            .map(|base| (base, interval)))
+    }
+
+    /// `specs` with `warm_up` more candles each, for indicators that need a history first.
+    fn with_warm_up(
+        &self,
+        specs: &[(String, Option<i32>)],
+        warm_up: usize,
+    ) -> Vec<(String, Option<i32>)> {
+        specs
+            .iter()
+            .map(|(name, opt_limit)| {
+                let limit = opt_limit.unwrap_or(self.default_limit);
+                (name.clone(), Some(limit.saturating_add(warm_up as i32)))
+            })
+            .collect()
+    }
+
+    /// Fetches the required Kline data, up to `with_concurrency` intervals at a time.
+    async fn fetch_each_intervals(&self) -> Result<(KlineDataMap, Vec<DataQualityReport>)> {
         let mut all_interval_specs = self.kline_intervals.clone();
         all_interval_specs.extend(self.stoch_rsi_intervals.clone());
-        all_interval_specs.extend(self.bb_intervals.clone());
         all_interval_specs.extend(self.latest_bb_ma_intervals.clone());
+        // Indicator sections show `limit` rows, their first reading needs candles before those.
+        let indicators = &self.indicators;
+        all_interval_specs
+            .extend(self.with_warm_up(&self.bb_intervals, indicators.bollinger_warm_up()));
+        all_interval_specs
+            .extend(self.with_warm_up(&self.ma_intervals, indicators.moving_average_warm_up()));
+        all_interval_specs.extend(self.with_warm_up(&self.ema_intervals, indicators.ema_warm_up()));
+        all_interval_specs
+            .extend(self.with_warm_up(&self.macd_intervals, indicators.macd_warm_up()));
+        all_interval_specs.extend(self.with_warm_up(&self.atr_intervals, indicators.atr_warm_up()));
+        all_interval_specs.extend(self.fibonacci_intervals.clone());
+        all_interval_specs.extend(self.support_resistance_intervals.clone());
+        all_interval_specs.extend(self.with_warm_up(&self.pattern_intervals, PATTERN_LOOKBACK));
+        all_interval_specs
+            .extend(self.with_warm_up(&self.divergence_intervals, indicators.divergence_warm_up()));
+        all_interval_specs.extend(self.taker_pressure_intervals.clone());
 
         let mut effective_fetch_params: HashMap<String, i32> = HashMap::new();
         for (name, opt_limit) in &all_interval_specs {
@@ -103,47 +419,162 @@ impl<'a> PriceHistoryBuilder<'a> {
         }
 
         if effective_fetch_params.is_empty() {
-            return Ok(HashMap::new());
+            return Ok((KlineDataMap::new(), Vec::new()));
+        }
+
+        // Intervals derived from a base interval need one extra candle's worth of base data,
+        // the oldest bucket is usually partial and gets dropped by the resampler.
+        let mut derived_intervals: HashMap<String, (Interval, Interval)> = HashMap::new();
+        let mut fetch_params: HashMap<String, i32> = HashMap::new();
+        for (interval_name, &limit) in &effective_fetch_params {
+            let (fetch_interval, fetch_limit) = match self.base_interval_for(interval_name)? {
+                Some((base, interval)) => {
+                    derived_intervals.insert(interval_name.clone(), (base, interval));
+                    let base_limit = (limit as i64 + 1) * candles_per(base, interval);
+                    (base.to_string(), base_limit.min(i32::MAX as i64) as i32)
+                }
+                None => (interval_name.clone(), limit),
+            };
+            fetch_params
+                .entry(fetch_interval)
+                .and_modify(|current_limit| *current_limit = (*current_limit).max(fetch_limit))
+                .or_insert(fetch_limit);
         }
 
         println!(
             "Builder: Effective fetch params for {}: {:?}",
-            self.pair_symbol, effective_fetch_params
+            self.pair_symbol, fetch_params
         );
 
-        let mut kline_data_map: HashMap<String, Vec<Kline>> = HashMap::new();
+        let now = chrono::Utc::now().timestamp_millis();
+        let fetches = fetch_params
+            .iter()
+            .map(|(interval_name, &limit)| async move {
+                (
+                    interval_name.clone(),
+                    self.fetch_interval(interval_name, limit, now).await,
+                )
+            });
+        // `buffer_unordered` only polls, so this runs on the worker's event loop and on tokio alike.
+        let results: Vec<(String, IntervalFetch)> = stream::iter(fetches)
+            .buffer_unordered(self.concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut fetched_klines: HashMap<String, Result<Vec<Kline>>> = HashMap::new();
+        let mut quality_reports = Vec::new();
+        for (interval_name, result) in results {
+            let result = result.map(|(kline_data, report)| {
+                quality_reports.extend(report);
+                kline_data
+            });
+            fetched_klines.insert(interval_name, result);
+        }
 
-        // Fetch data one by one
-        for (interval_name, &limit_to_use) in &effective_fetch_params {
-            let interval = interval_name.clone();
-            let pair_symbol_for_fetch = self.pair_symbol.to_string();
+        let mut kline_results: HashMap<String, Result<IntervalData>> = HashMap::new();
+        for (interval_name, &limit) in &effective_fetch_params {
+            let kline_data = match derived_intervals.get(interval_name) {
+                Some(&(base, interval)) => match &fetched_klines[base.as_str()] {
+                    Ok(base_klines) => {
+                        println!("Builder: Resampling {interval} from {base}");
+                        resample_klines(base_klines, base, interval)
+                    }
+                    Err(error) => Err(anyhow!("Base interval {base} unavailable: {error:#}")),
+                },
+                None => match &fetched_klines[interval_name] {
+                    Ok(kline_data) => Ok(kline_data.clone()),
+                    Err(error) => Err(anyhow!("{error:#}")),
+                },
+            };
 
-            let kline_data: Vec<Kline> =
-                fetch_binance_kline_usdt::<Kline>(&pair_symbol_for_fetch, &interval, limit_to_use)
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "Builder: Failed fetching klines for {pair_symbol_for_fetch} interval {interval} with limit {limit_to_use}"
-                        )
-                    })?;
+            let interval_data = kline_data.and_then(|mut kline_data| {
+                // A base interval may have been fetched further back for the intervals derived from it.
+                let skip = kline_data.len().saturating_sub(limit.max(0) as usize);
+                let klines = kline_data.split_off(skip);
+                let candles = Candle::from_klines(&klines).with_context(|| {
+                    format!(
+                        "Builder: Malformed klines for {} interval {interval_name}",
+                        self.pair_symbol
+                    )
+                })?;
+                Ok(IntervalData { klines, candles })
+            });
+            kline_results.insert(interval_name.clone(), interval_data);
+        }
 
-            kline_data_map.insert(interval.clone(), kline_data);
+        // Failed intervals degrade their sections, unless they're required or nothing worked.
+        for required in &self.required_intervals {
+            if kline_results.get(required).is_some_and(Result::is_err) {
+                if let Some(Err(error)) = kline_results.remove(required) {
+                    return Err(error.context(format!(
+                        "Builder: Required interval {required} unavailable for {}",
+                        self.pair_symbol
+                    )));
+                }
+            }
         }
+        if kline_results.values().all(|result| result.is_err()) {
+            if let Some((_, Err(error))) = kline_results.drain().next() {
+                return Err(error);
+            }
+        }
+
+        let kline_data_map: KlineDataMap = kline_results
+            .into_iter()
+            .map(|(interval_name, result)| {
+                let result = result.map_err(|error| {
+                    eprintln!("Builder: Interval {interval_name} unavailable: {error:#}");
+                    format!("{error:#}")
+                });
+                (interval_name, result)
+            })
+            .collect();
 
         println!(
             "Builder: Fetched kline data for intervals: {:?}",
             kline_data_map.keys()
         );
-        Ok(kline_data_map)
+        quality_reports.sort_by_key(|report| report.interval);
+        Ok((kline_data_map, quality_reports))
+    }
+
+    // Fetches one interval and runs the quality check on it when enabled.
+    async fn fetch_interval(&self, interval: &str, limit_to_use: i32, now: i64) -> IntervalFetch {
+        let pair_symbol_for_fetch = self.pair_symbol.to_string();
+
+        let kline_data: Vec<Kline> = self
+            .source
+            .fetch_klines(&pair_symbol_for_fetch, interval, limit_to_use)
+            .await
+            .with_context(|| {
+                format!(
+                    "Builder: Failed fetching klines for {pair_symbol_for_fetch} interval {interval} with limit {limit_to_use}"
+                )
+            })?;
+
+        let Some(config) = &self.quality else {
+            return Ok((kline_data, None));
+        };
+        let (kline_data, report) = check_and_repair(
+            &self.source,
+            &pair_symbol_for_fetch,
+            interval.parse::<Interval>()?,
+            kline_data,
+            config,
+            now,
+        )
+        .await;
+        println!("Builder: Data quality for {pair_symbol_for_fetch} {report}");
+        if !report.usable {
+            bail!("Builder: Refusing stale or broken klines for {pair_symbol_for_fetch} {report}");
+        }
+        Ok((kline_data, Some(report)))
     }
 
     // --- Formatting Sections ---
 
     /// Formats the Klines section based on intervals requested via `with_klines`.
-    fn format_klines_section(
-        &self,
-        kline_data_map: &HashMap<String, Vec<Kline>>,
-    ) -> Result<String> {
+    fn format_klines_section(&self, kline_data_map: &KlineDataMap) -> Result<String> {
         if self.kline_intervals.is_empty() {
             return Ok(String::new());
         }
@@ -160,25 +591,32 @@ impl<'a> PriceHistoryBuilder<'a> {
                 None => interval_name.clone(),
             };
 
-            if let Some(data) = kline_data_map.get(interval_name) {
-                if data.is_empty() {
+            if let Some(Err(error)) = kline_data_map.get(interval_name) {
+                klines_output.push_str(&format!(
+                    "\n* Interval: {display_interval} (Unavailable: {error})\n"
+                ));
+                continue;
+            }
+            if let Some(Ok(IntervalData { klines, candles })) = kline_data_map.get(interval_name) {
+                if candles.is_empty() {
                     klines_output.push_str(&format!(" ({display_interval}) No data found.\n"));
                     continue;
                 }
-                match klines_to_csv(data) {
-                    Ok(csv_data) => {
-                        klines_output.push_str(&format!("\n* Price: {interval_name}\n"));
-                        klines_output.push_str("```csv\n");
-                        klines_output.push_str(&csv_data);
-                        klines_output.push_str("```\n");
+                let csv = if self.taker_columns {
+                    match taker_pressure(klines) {
+                        Ok(pressure) => candles_with_taker_pressure_to_csv(candles, &pressure),
+                        Err(e) => {
+                            eprintln!("Error calculating taker pressure for {interval_name}: {e}");
+                            candles_to_csv(candles)
+                        }
                     }
-                    Err(e) => {
-                        klines_output.push_str(&format!(
-                            "\n* Interval: {display_interval} (Error formatting Klines to CSV: {e})\n"
-                        ));
-                        eprintln!("Error formatting klines to CSV for {interval_name}: {e}");
-                    }
-                }
+                } else {
+                    candles_to_csv(candles)
+                };
+                klines_output.push_str(&format!("\n* Price: {interval_name}\n"));
+                klines_output.push_str("```csv\n");
+                klines_output.push_str(&csv);
+                klines_output.push_str("```\n");
             } else {
                 klines_output.push_str(&format!(
                     "\n* Interval: {display_interval} (Data unexpectedly missing after fetch)\n"
@@ -192,10 +630,7 @@ impl<'a> PriceHistoryBuilder<'a> {
     }
 
     /// Formats the Stochastic RSI section based on intervals requested via `with_stoch_rsi`.
-    fn format_stoch_rsi_section(
-        &self,
-        kline_data_map: &HashMap<String, Vec<Kline>>,
-    ) -> Result<String> {
+    fn format_stoch_rsi_section(&self, kline_data_map: &KlineDataMap) -> Result<String> {
         if self.stoch_rsi_intervals.is_empty() {
             return Ok(String::new());
         }
@@ -212,14 +647,21 @@ impl<'a> PriceHistoryBuilder<'a> {
                 None => interval_name.clone(),
             };
 
-            if let Some(data) = kline_data_map.get(interval_name) {
+            if let Some(Err(error)) = kline_data_map.get(interval_name) {
+                stoch_rsi_output.push_str(&format!(
+                    "\n* Interval: {display_interval} (Unavailable: {error})\n"
+                ));
+                continue;
+            }
+            if let Some(Ok(IntervalData { candles: data, .. })) = kline_data_map.get(interval_name)
+            {
                 if data.is_empty() {
                     stoch_rsi_output.push_str(&format!(
                         " ({display_interval}) No kline data available to calculate StochRSI.\n"
                     ));
                     continue;
                 }
-                match get_stoch_rsi_csv(data) {
+                match get_stoch_rsi_csv(data, &self.indicators.stoch_rsi) {
                     Ok(stoch_rsi_csv) => {
                         stoch_rsi_output
                             .push_str(&format!("\n* Stochastic RSI: {interval_name}\n"));
@@ -246,113 +688,611 @@ impl<'a> PriceHistoryBuilder<'a> {
         Ok(stoch_rsi_output)
     }
 
-    fn format_bb_section(&self, kline_data_map: &HashMap<String, Vec<Kline>>) -> Result<String> {
-        if self.bb_intervals.is_empty() {
+    fn format_latest_bb_ma_section(&self, kline_data_map: &KlineDataMap) -> Result<String> {
+        if self.latest_bb_ma_intervals.is_empty() {
             return Ok(String::new());
         }
 
         let mut output = String::new();
-        output.push_str("\n**Boilinger Band:**\n");
+        output.push_str("\n**Boilinger Band and Moving Average:**\n");
 
-        let mut sorted_requested_bb = self.bb_intervals.clone();
-        sorted_requested_bb.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut sorted_requested_bb_ma = self.latest_bb_ma_intervals.clone();
+        sorted_requested_bb_ma.sort_by(|a, b| a.0.cmp(&b.0));
 
-        for (interval_name, opt_limit) in &sorted_requested_bb {
+        for (interval_name, opt_limit) in &sorted_requested_bb_ma {
             let display_interval = match opt_limit {
                 Some(limit) => format!("{interval_name}:{limit}"),
                 None => interval_name.clone(),
             };
 
-            if let Some(data) = kline_data_map.get(interval_name) {
+            if let Some(Err(error)) = kline_data_map.get(interval_name) {
+                output.push_str(&format!(
+                    "\n* Interval: {display_interval} (Unavailable: {error})\n"
+                ));
+                continue;
+            }
+            if let Some(Ok(IntervalData { candles: data, .. })) = kline_data_map.get(interval_name)
+            {
                 if data.is_empty() {
                     output.push_str(&format!(
-                        " ({display_interval}) No kline data available to calculate Boilinger Band.\n"
+                        " ({display_interval}) No kline data available to calculate Boilinger Band and Moving Average.\n"
                     ));
                     continue;
                 }
-                match get_latest_bb_ma(data) {
-                    Ok(csv) => {
-                        output.push_str(&format!("\n* Boilinger Band: {interval_name}\n"));
-                        output.push_str("```csv\n");
-                        output.push_str(&csv);
+                match get_latest_bb_ma(data, &self.indicators) {
+                    Ok(detail) => {
+                        output.push_str(&format!(
+                            "\n* Boilinger Band and Moving Average: {interval_name}\n"
+                        ));
                         output.push_str("```\n");
+                        output.push_str(&detail);
+                        output.push_str("\n```\n");
                     }
                     Err(e) => {
                         output.push_str(&format!(
-                            "\n* Interval: {display_interval} (Error calculating Boilinger Band: {e})\n"
+                            "\n* Interval: {display_interval} (Error calculating Boilinger Band and Moving Average: {e})\n"
                         ));
-                        eprintln!("Error calculating Boilinger Band for {interval_name}: {e}");
+                        eprintln!(
+                            "Error calculating Boilinger Band and Moving Average for {interval_name}: {e}"
+                        );
                     }
                 }
             } else {
                 output.push_str(&format!(
-                    "\n* Interval: {display_interval} (Boilinger Band data unexpectedly missing for Boilinger Band calculation)\n"
-                ));
+                        "\n* Interval: {display_interval} (Boilinger Band data unexpectedly missing for Boilinger Band calculation)\n"
+                    ));
                 eprintln!(
-                    "Warning: Boilinger Band data for interval {interval_name} needed for Boilinger Band but not found in map."
-                );
+                        "Warning: Boilinger Band data for interval {interval_name} needed for Boilinger Band but not found in map."
+                    );
             }
         }
         Ok(output)
     }
 
-    fn format_latest_bb_ma_section(
+    // Formats one table per requested interval with `render(candles, rows)`, which gets every
+    // fetched candle (indicators need the warm-up) and how many of the latest rows to show.
+    fn format_indicator_section<F>(
+        &self,
+        kline_data_map: &KlineDataMap,
+        intervals: &[(String, Option<i32>)],
+        title: &str,
+        render: F,
+    ) -> String
+    where
+        F: Fn(&[Candle], usize) -> Result<String>,
+    {
+        let mut output = String::new();
+        output.push_str(&format!("\n**{title}:**\n"));
+
+        let mut sorted_requested = intervals.to_vec();
+        sorted_requested.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (interval_name, opt_limit) in &sorted_requested {
+            let display_interval = match opt_limit {
+                Some(limit) => format!("{interval_name}:{limit}"),
+                None => interval_name.clone(),
+            };
+
+            match kline_data_map.get(interval_name) {
+                Some(Err(error)) => {
+                    output.push_str(&format!(
+                        "\n* Interval: {display_interval} (Unavailable: {error})\n"
+                    ));
+                }
+                Some(Ok(IntervalData { candles, .. })) if candles.is_empty() => {
+                    output.push_str(&format!(
+                        " ({display_interval}) No kline data available to calculate {title}.\n"
+                    ));
+                }
+                Some(Ok(IntervalData { candles, .. })) => {
+                    let rows = opt_limit.unwrap_or(self.default_limit).max(0) as usize;
+                    match render(candles, rows) {
+                        Ok(csv) => {
+                            output.push_str(&format!("\n* {title}: {interval_name}\n"));
+                            output.push_str("```csv\n");
+                            output.push_str(&csv);
+                            output.push_str("```\n");
+                        }
+                        Err(e) => {
+                            output.push_str(&format!(
+                                "\n* Interval: {display_interval} (Error calculating {title}: {e})\n"
+                            ));
+                            eprintln!("Error calculating {title} for {interval_name}: {e}");
+                        }
+                    }
+                }
+                None => {
+                    output.push_str(&format!(
+                        "\n* Interval: {display_interval} (Kline data unexpectedly missing for {title} calculation)\n"
+                    ));
+                    eprintln!(
+                        "Warning: Kline data for interval {interval_name} needed for {title} but not found in map."
+                    );
+                }
+            }
+        }
+        output
+    }
+
+    /// Formats the Bollinger Band history section based on intervals requested via `with_bb`.
+    fn format_bb_section(&self, kline_data_map: &KlineDataMap) -> String {
+        let BollingerConfig { period, std_dev } = self.indicators.bollinger;
+        let title = format!("Bollinger Bands ({period}, {std_dev})");
+        self.format_indicator_section(
+            kline_data_map,
+            &self.bb_intervals,
+            &title,
+            |candles, rows| {
+                let points = calculate_bollinger_bands(candles, period, std_dev)?;
+                let skip = points.len().saturating_sub(rows);
+                Ok(parse_bollinger_csv(&points[skip..]))
+            },
+        )
+    }
+
+    /// Formats the moving average history section based on intervals requested via `with_ma`.
+    fn format_ma_section(&self, kline_data_map: &KlineDataMap) -> String {
+        let lines = &self.indicators.moving_averages;
+        let names: Vec<String> = lines.iter().map(MovingAverage::to_string).collect();
+        let title = format!("Moving Averages ({})", names.join(", "));
+        self.format_indicator_section(
+            kline_data_map,
+            &self.ma_intervals,
+            &title,
+            |candles, rows| {
+                let points = calculate_moving_averages(candles, lines)?;
+                let skip = points.len().saturating_sub(rows);
+                Ok(parse_ma_csv(&points[skip..], lines))
+            },
+        )
+    }
+
+    /// Formats the EMA section based on intervals requested via `with_ema`.
+    fn format_ema_section(&self, kline_data_map: &KlineDataMap) -> String {
+        let EmaCrossConfig { fast, slow } = self.indicators.ema;
+        let title = format!("EMA ({fast}/{slow})");
+        self.format_indicator_section(
+            kline_data_map,
+            &self.ema_intervals,
+            &title,
+            |candles, rows| {
+                let points = calculate_ema_cross(candles, fast, slow)?;
+                let skip = points.len().saturating_sub(rows);
+                Ok(parse_ema_csv(&points[skip..], fast, slow))
+            },
+        )
+    }
+
+    /// Formats the MACD section based on intervals requested via `with_macd`.
+    fn format_macd_section(&self, kline_data_map: &KlineDataMap) -> String {
+        let MacdConfig { fast, slow, signal } = self.indicators.macd;
+        let title = format!("MACD ({fast}/{slow}/{signal})");
+        self.format_indicator_section(
+            kline_data_map,
+            &self.macd_intervals,
+            &title,
+            |candles, rows| {
+                let points = calculate_macd(candles, fast, slow, signal)?;
+                let skip = points.len().saturating_sub(rows);
+                Ok(parse_macd_csv(&points[skip..]))
+            },
+        )
+    }
+
+    /// Formats the ATR section based on intervals requested via `with_atr`.
+    fn format_atr_section(&self, kline_data_map: &KlineDataMap) -> String {
+        let period = self.indicators.atr_period;
+        let title = format!("ATR ({period})");
+        self.format_indicator_section(
+            kline_data_map,
+            &self.atr_intervals,
+            &title,
+            |candles, rows| {
+                let points = calculate_atr(candles, period)?;
+                let skip = points.len().saturating_sub(rows);
+                let mut csv = parse_atr_csv(&points[skip..]);
+                if let Some(latest) = points.last() {
+                    csv.push_str(&format_atr_stop_buffers(latest));
+                }
+                Ok(csv)
+            },
+        )
+    }
+
+    /// Formats the Fibonacci section based on intervals requested via `with_fibonacci`.
+    fn format_fibonacci_section(&self, kline_data_map: &KlineDataMap) -> String {
+        let threshold_pct = self.indicators.swing_threshold_pct;
+        let title = format!("Fibonacci Levels ({threshold_pct}% swings)");
+        self.format_indicator_section(
+            kline_data_map,
+            &self.fibonacci_intervals,
+            &title,
+            |candles, rows| {
+                let skip = candles.len().saturating_sub(rows);
+                get_fibonacci_csv(&candles[skip..], threshold_pct)
+            },
+        )
+    }
+
+    /// Formats the Recent Patterns section based on intervals requested via `with_patterns`.
+    fn format_patterns_section(&self, kline_data_map: &KlineDataMap) -> String {
+        self.format_indicator_section(
+            kline_data_map,
+            &self.pattern_intervals,
+            "Recent Candlestick Patterns",
+            |candles, rows| {
+                let skip = candles.len().saturating_sub(rows);
+                let since = candles
+                    .get(skip)
+                    .map_or(i64::MAX, |candle| candle.open_time);
+                let events: Vec<_> = find_patterns(candles)
+                    .into_iter()
+                    .filter(|event| event.open_time >= since)
+                    .collect();
+                Ok(patterns_to_csv(&events))
+            },
+        )
+    }
+
+    /// Formats the Divergences section based on intervals requested via `with_divergences`.
+    fn format_divergences_section(&self, kline_data_map: &KlineDataMap) -> String {
+        let threshold_pct = self.indicators.divergence_swing_pct;
+        let title = format!("Divergences ({threshold_pct}% swings)");
+        self.format_indicator_section(
+            kline_data_map,
+            &self.divergence_intervals,
+            &title,
+            |candles, rows| {
+                let skip = candles.len().saturating_sub(rows);
+                let since = candles
+                    .get(skip)
+                    .map_or(i64::MAX, |candle| candle.open_time);
+                let divergences: Vec<_> = find_divergences(candles, &self.indicators)?
+                    .into_iter()
+                    .filter(|divergence| divergence.end >= since)
+                    .collect();
+                Ok(divergences_to_csv(&divergences))
+            },
+        )
+    }
+
+    /// Formats the Support/Resistance section based on intervals requested via
+    /// `with_support_resistance`. Zones are built from the intervals that could be fetched, and
+    /// from pivots alone when the order book can't be.
+    async fn format_support_resistance_section(
         &self,
-        kline_data_map: &HashMap<String, Vec<Kline>>,
+        kline_data_map: &KlineDataMap,
     ) -> Result<String> {
-        if self.latest_bb_ma_intervals.is_empty() {
+        let IndicatorConfig {
+            swing_threshold_pct,
+            zone_tolerance_pct,
+            ..
+        } = self.indicators;
+        let mut output = String::new();
+        output.push_str(&format!(
+            "\n**Support/Resistance Zones ({swing_threshold_pct}% swings, {zone_tolerance_pct}% wide):**\n"
+        ));
+
+        let mut sorted_requested = self.support_resistance_intervals.clone();
+        sorted_requested.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut pivots = Vec::new();
+        let mut used_intervals = Vec::new();
+        let mut latest_candle: Option<Candle> = None;
+        for (interval_name, opt_limit) in &sorted_requested {
+            let display_interval = match opt_limit {
+                Some(limit) => format!("{interval_name}:{limit}"),
+                None => interval_name.clone(),
+            };
+            match kline_data_map.get(interval_name) {
+                Some(Ok(IntervalData { candles, .. })) if !candles.is_empty() => {
+                    let rows = opt_limit.unwrap_or(self.default_limit).max(0) as usize;
+                    let skip = candles.len().saturating_sub(rows);
+                    pivots.extend(pivots_from_candles(&candles[skip..], swing_threshold_pct));
+                    used_intervals.push(display_interval);
+                    latest_candle = latest_candle
+                        .into_iter()
+                        .chain(candles.last().copied())
+                        .max_by_key(|candle| candle.close_time);
+                }
+                Some(Err(error)) => {
+                    output.push_str(&format!(
+                        "\n* Interval: {display_interval} (Unavailable: {error})\n"
+                    ));
+                }
+                _ => {
+                    output.push_str(&format!(
+                        " ({display_interval}) No kline data available to find pivots.\n"
+                    ));
+                }
+            }
+        }
+        let Some(latest_candle) = latest_candle else {
+            return Ok(output);
+        };
+
+        let orderbook = match &self.orderbook {
+            Some(orderbook) => Ok(orderbook.clone()),
+            None => {
+                self.source
+                    .fetch_orderbook(self.pair_symbol, ZONE_ORDERBOOK_LIMIT)
+                    .await
+            }
+        };
+        let clusters = match orderbook {
+            Ok(orderbook) => book_clusters(&orderbook, FractionalPart::One, ZONE_BOOK_CLUSTERS),
+            Err(error) => {
+                eprintln!("Builder: Order book unavailable for zones: {error:#}");
+                output.push_str(&format!(
+                    "\n* Order Book: (Unavailable: {error:#}), zones from pivots only\n"
+                ));
+                Vec::new()
+            }
+        };
+
+        let zones = find_zones(
+            &pivots,
+            &clusters,
+            latest_candle.close,
+            zone_tolerance_pct,
+            MAX_ZONES,
+        );
+        output.push_str(&format!(
+            "\n* Zones: {} (current_price={:.4})\n",
+            used_intervals.join(", "),
+            latest_candle.close
+        ));
+        output.push_str("```csv\n");
+        output.push_str(&zones_to_csv(&zones));
+        output.push_str("```\n");
+
+        Ok(output)
+    }
+
+    /// Formats the Taker Pressure section based on intervals requested via `with_taker_pressure`.
+    /// Shows the last `limit` candles, the trade count z-score still looks back over every
+    /// fetched candle.
+    fn format_taker_pressure_section(&self, kline_data_map: &KlineDataMap) -> Result<String> {
+        if self.taker_pressure_intervals.is_empty() {
             return Ok(String::new());
         }
 
         let mut output = String::new();
-        output.push_str("\n**Boilinger Band and Moving Average:**\n");
+        output.push_str("\n**Taker Pressure:**\n");
 
-        let mut sorted_requested_bb_ma = self.latest_bb_ma_intervals.clone();
-        sorted_requested_bb_ma.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut sorted_requested_taker = self.taker_pressure_intervals.clone();
+        sorted_requested_taker.sort_by(|a, b| a.0.cmp(&b.0));
 
-        for (interval_name, opt_limit) in &sorted_requested_bb_ma {
+        for (interval_name, opt_limit) in &sorted_requested_taker {
             let display_interval = match opt_limit {
                 Some(limit) => format!("{interval_name}:{limit}"),
                 None => interval_name.clone(),
             };
 
-            if let Some(data) = kline_data_map.get(interval_name) {
-                if data.is_empty() {
+            match kline_data_map.get(interval_name) {
+                Some(Err(error)) => {
                     output.push_str(&format!(
-                        " ({display_interval}) No kline data available to calculate Boilinger Band and Moving Average.\n"
+                        "\n* Interval: {display_interval} (Unavailable: {error})\n"
                     ));
-                    continue;
                 }
-                match get_latest_bb_ma(data) {
-                    Ok(detail) => {
-                        output.push_str(&format!(
-                            "\n* Boilinger Band and Moving Average: {interval_name}\n"
-                        ));
+                Some(Ok(IntervalData { klines, .. })) if klines.is_empty() => {
+                    output.push_str(&format!(
+                        " ({display_interval}) No kline data available to calculate Taker Pressure.\n"
+                    ));
+                }
+                Some(Ok(IntervalData { klines, .. })) => match taker_pressure(klines) {
+                    Ok(pressure) => {
+                        let limit = opt_limit.unwrap_or(self.default_limit).max(0) as usize;
+                        let skip = pressure.len().saturating_sub(limit);
+                        output.push_str(&format!("\n* Taker Pressure: {interval_name}\n"));
+                        output.push_str("```csv\n");
+                        output.push_str(&taker_pressure_to_csv(&pressure[skip..]));
                         output.push_str("```\n");
-                        output.push_str(&detail);
-                        output.push_str("\n```\n");
                     }
                     Err(e) => {
                         output.push_str(&format!(
-                            "\n* Interval: {display_interval} (Error calculating Boilinger Band and Moving Average: {e})\n"
+                            "\n* Interval: {display_interval} (Error calculating Taker Pressure: {e})\n"
                         ));
-                        eprintln!(
-                            "Error calculating Boilinger Band and Moving Average for {interval_name}: {e}"
-                        );
+                        eprintln!("Error calculating Taker Pressure for {interval_name}: {e}");
                     }
-                }
-            } else {
-                output.push_str(&format!(
-                        "\n* Interval: {display_interval} (Boilinger Band data unexpectedly missing for Boilinger Band calculation)\n"
+                },
+                None => {
+                    output.push_str(&format!(
+                        "\n* Interval: {display_interval} (Kline data unexpectedly missing for Taker Pressure calculation)\n"
                     ));
-                eprintln!(
-                        "Warning: Boilinger Band data for interval {interval_name} needed for Boilinger Band but not found in map."
+                    eprintln!(
+                        "Warning: Kline data for interval {interval_name} needed for Taker Pressure but not found in map."
                     );
+                }
             }
         }
         Ok(output)
     }
 
+    /// Formats the Order Flow section based on intervals requested via `with_order_flow`.
+    /// Trades are fetched once for the longest window, a failed fetch marks every interval unavailable.
+    async fn format_order_flow_section(&self) -> Result<String> {
+        if self.order_flow_intervals.is_empty() {
+            return Ok(String::new());
+        }
+
+        let mut output = String::new();
+        output.push_str("\n**Order Flow (Taker Buy/Sell Volume):**\n");
+
+        let mut sorted_requested_order_flow = self.order_flow_intervals.clone();
+        sorted_requested_order_flow.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Each interval covers its last `limit` candles, the forming one included.
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut windows = Vec::new();
+        for (interval_name, opt_limit) in &sorted_requested_order_flow {
+            let limit = opt_limit.unwrap_or(DEFAULT_ORDER_FLOW_CANDLES) as i64;
+            let interval = interval_name.parse::<Interval>()?;
+            let current_open_time = interval.align(now.div_euclid(1000)) * 1000;
+            let start_time = current_open_time - (limit - 1) * interval.duration_ms();
+            windows.push((interval_name, opt_limit, interval, start_time));
+        }
+        let Some(start_time) = windows.iter().map(|window| window.3).min() else {
+            return Ok(output);
+        };
+
+        let trades = self
+            .source
+            .fetch_agg_trades(self.pair_symbol, start_time, self.max_trades)
+            .await
+            .and_then(|agg_trades| Trade::from_agg_trades(&agg_trades))
+            .with_context(|| {
+                format!(
+                    "Builder: Failed fetching trades for {} since {start_time}",
+                    self.pair_symbol
+                )
+            });
+        let trades = match trades {
+            Ok(trades) => trades,
+            Err(error) => {
+                eprintln!("Builder: Order flow unavailable: {error:#}");
+                for (interval_name, opt_limit, _, _) in &windows {
+                    let display_interval = match opt_limit {
+                        Some(limit) => format!("{interval_name}:{limit}"),
+                        None => interval_name.to_string(),
+                    };
+                    output.push_str(&format!(
+                        "\n* Interval: {display_interval} (Unavailable: {error:#})\n"
+                    ));
+                }
+                return Ok(output);
+            }
+        };
+        // Hitting `max_trades` means the oldest candle read is only partly covered, it's kept
+        // and marked instead of leaving the window empty.
+        let is_truncated = trades.len() >= self.max_trades;
+        let threshold = large_trade_threshold(&trades);
+
+        for (interval_name, opt_limit, interval, start_time) in &windows {
+            let display_interval = match opt_limit {
+                Some(limit) => format!("{interval_name}:{limit}"),
+                None => interval_name.to_string(),
+            };
+
+            let window_trades: Vec<Trade> = trades
+                .iter()
+                .filter(|trade| trade.time >= *start_time)
+                .copied()
+                .collect();
+            if window_trades.is_empty() {
+                output.push_str(&format!(" ({display_interval}) No trades found.\n"));
+                continue;
+            }
+
+            // Only the window holding the oldest trade read starts on a partial candle.
+            let is_partial = is_truncated && window_trades.len() == trades.len();
+            let bars = order_flow(&window_trades, *interval, threshold);
+            match bars.first() {
+                Some(oldest) if is_partial => output.push_str(&format!(
+                    "\n* Order Flow: {interval_name} (oldest candle {} partly covered, the latest {} trades only)\n",
+                    oldest.open_time, self.max_trades
+                )),
+                _ => output.push_str(&format!("\n* Order Flow: {interval_name}\n")),
+            }
+            output.push_str("```csv\n");
+            output.push_str(&order_flow_to_csv(&bars));
+            output.push_str("```\n");
+        }
+
+        let large = large_trades(&trades, threshold);
+        if !large.is_empty() {
+            let skip = large.len().saturating_sub(MAX_LARGE_TRADES);
+            output.push_str(&format!("\n* Large Trades (notional >= {threshold:.2}):\n"));
+            output.push_str("```csv\n");
+            output.push_str(&large_trades_to_csv(&large[skip..]));
+            output.push_str("```\n");
+        }
+
+        Ok(output)
+    }
+
+    /// Formats the Derivatives section based on periods requested via `with_derivatives`,
+    /// along with the snapshot of the first period when it could be fetched.
+    async fn format_derivatives_section(&self) -> Result<(String, Option<DerivativesSnapshot>)> {
+        let Some((snapshot_period, _)) = self.derivatives_periods.first() else {
+            return Ok((String::new(), None));
+        };
+
+        let mut output = String::new();
+        output.push_str("\n**Derivatives Positioning (Perpetual Futures):**\n");
+
+        let mut sorted_requested_periods = self.derivatives_periods.clone();
+        sorted_requested_periods.sort_by(|a, b| a.0.cmp(&b.0));
+        sorted_requested_periods.dedup();
+
+        let mut fetched = Vec::new();
+        for (period, opt_limit) in &sorted_requested_periods {
+            let limit = opt_limit.unwrap_or(DEFAULT_DERIVATIVES_PERIODS).max(1) as usize;
+            let metrics = self
+                .source
+                .fetch_futures_metrics(self.pair_symbol, period, limit)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Builder: Failed fetching futures metrics for {} period {period}",
+                        self.pair_symbol
+                    )
+                });
+            fetched.push((period, opt_limit, metrics));
+        }
+
+        for (period, opt_limit, metrics) in &fetched {
+            let display_period = match opt_limit {
+                Some(limit) => format!("{period}:{limit}"),
+                None => period.to_string(),
+            };
+            match metrics {
+                Ok(metrics) => {
+                    output.push_str(&format!(
+                        "\n* Open Interest and Top Trader Long/Short Ratio: {period}\n"
+                    ));
+                    output.push_str("```csv\n");
+                    output.push_str(&positioning_to_csv(metrics));
+                    output.push_str("```\n");
+                }
+                Err(error) => {
+                    eprintln!("Builder: Derivatives unavailable: {error:#}");
+                    output.push_str(&format!(
+                        "\n* Interval: {display_period} (Unavailable: {error:#})\n"
+                    ));
+                }
+            }
+        }
+
+        // Every period carries the funding settled over its window, the longest one has them all.
+        let funding_rates = fetched
+            .iter()
+            .filter_map(|(_, _, metrics)| metrics.as_ref().ok())
+            .map(|metrics| &metrics.funding_rates)
+            .max_by_key(|funding_rates| funding_rates.len());
+        if let Some(funding_rates) = funding_rates.filter(|rates| !rates.is_empty()) {
+            output.push_str("\n* Funding Rates:\n");
+            output.push_str("```csv\n");
+            output.push_str(&funding_rates_to_csv(funding_rates));
+            output.push_str("```\n");
+        }
+
+        let snapshot = fetched
+            .iter()
+            .find(|(period, _, _)| *period == snapshot_period)
+            .and_then(|(_, _, metrics)| metrics.as_ref().ok())
+            .and_then(|metrics| {
+                DerivativesSnapshot::from_metrics(metrics, snapshot_period)
+                    .map_err(|error| eprintln!("Builder: No derivatives snapshot: {error:#}"))
+                    .ok()
+            });
+        if let Some(snapshot) = &snapshot {
+            output.push_str(&format!("\n* Summary: {snapshot}\n"));
+        }
+
+        Ok((output, snapshot))
+    }
+
     // --- Public API Method ---
 
     /// **Fetches required data and formats it into a single Markdown report string.**
@@ -361,33 +1301,80 @@ impl<'a> PriceHistoryBuilder<'a> {
     /// etc., based on what was requested via `.with_klines()`, `.with_stoch_rsi()`, etc.
     /// Each section contains data formatted as CSV within Markdown code blocks.
     pub async fn build(&self) -> Result<String> {
-        // Renamed back to build()
+        Ok(self.build_with_quality_reports().await?.0)
+    }
+
+    /// Same as `build`, also returning the data-quality report of every fetched interval
+    /// when `with_quality_check` is set.
+    pub async fn build_with_quality_reports(&self) -> Result<(String, Vec<DataQualityReport>)> {
+        let (output_string, quality_reports, _) = self.build_report().await?;
+        Ok((output_string, quality_reports))
+    }
+
+    /// Same as `build`, also filling in `data_quality` and `maybe_derivatives` on `context`.
+    pub async fn build_with_context(&self, context: &mut TradingContext) -> Result<String> {
+        let (output_string, quality_reports, derivatives) = self.build_report().await?;
+        context.data_quality = quality_reports;
+        context.maybe_derivatives = derivatives.map(Box::new);
+        Ok(output_string)
+    }
+
+    async fn build_report(
+        &self,
+    ) -> Result<(String, Vec<DataQualityReport>, Option<DerivativesSnapshot>)> {
         let mut output_string = String::new();
 
         let klines_requested = !self.kline_intervals.is_empty();
         let rsi_requested = !self.stoch_rsi_intervals.is_empty();
         let bb_requested = !self.bb_intervals.is_empty();
+        let ma_requested = !self.ma_intervals.is_empty();
         let latest_bb_requested = !self.latest_bb_ma_intervals.is_empty();
+        let ema_requested = !self.ema_intervals.is_empty();
+        let macd_requested = !self.macd_intervals.is_empty();
+        let atr_requested = !self.atr_intervals.is_empty();
+        let fibonacci_requested = !self.fibonacci_intervals.is_empty();
+        let zones_requested = !self.support_resistance_intervals.is_empty();
+        let patterns_requested = !self.pattern_intervals.is_empty();
+        let divergences_requested = !self.divergence_intervals.is_empty();
+        let taker_requested = !self.taker_pressure_intervals.is_empty();
+        let order_flow_requested = !self.order_flow_intervals.is_empty();
+        let derivatives_requested = !self.derivatives_periods.is_empty();
 
         // Add checks for other indicators...
-        let any_data_requested = klines_requested || rsi_requested || bb_requested; // || other_requested ...
+        let kline_data_requested = klines_requested
+            || rsi_requested
+            || bb_requested
+            || ma_requested
+            || ema_requested
+            || macd_requested
+            || atr_requested
+            || fibonacci_requested
+            || zones_requested
+            || patterns_requested
+            || divergences_requested
+            || taker_requested; // || other_requested ...
+        let other_data_requested = order_flow_requested || derivatives_requested;
+        let any_data_requested = kline_data_requested || other_data_requested;
 
         if !any_data_requested {
             output_string.push_str("No historical data intervals specified.\n");
-            return Ok(output_string);
+            return Ok((output_string, Vec::new(), None));
         }
 
-        let kline_data_map = self.fetch_each_intervals().await?;
+        self.indicators
+            .validate()
+            .context("Builder: Invalid indicator config")?;
+        let (kline_data_map, quality_reports) = self.fetch_each_intervals().await?;
 
-        if kline_data_map.is_empty() && any_data_requested {
+        if kline_data_map.is_empty() && kline_data_requested {
             output_string
                 .push_str("Warning: No kline data could be fetched for the requested intervals.\n");
-            return Ok(output_string);
-        } else if kline_data_map.is_empty() {
+            return Ok((output_string, quality_reports, None));
+        } else if kline_data_map.is_empty() && !other_data_requested {
             // This case should ideally be caught by !any_data_requested check above,
             // but kept as a safeguard.
             output_string.push_str("No historical data intervals specified.\n");
-            return Ok(output_string);
+            return Ok((output_string, Vec::new(), None));
         }
 
         // Append formatted sections if they were requested
@@ -400,7 +1387,11 @@ impl<'a> PriceHistoryBuilder<'a> {
         }
 
         if bb_requested {
-            output_string.push_str(&self.format_bb_section(&kline_data_map)?);
+            output_string.push_str(&self.format_bb_section(&kline_data_map));
+        }
+
+        if ma_requested {
+            output_string.push_str(&self.format_ma_section(&kline_data_map));
         }
 
         println!("latest_bb_requested:{latest_bb_requested}");
@@ -408,7 +1399,54 @@ impl<'a> PriceHistoryBuilder<'a> {
             output_string.push_str(&self.format_latest_bb_ma_section(&kline_data_map)?);
         }
 
-        Ok(output_string)
+        if ema_requested {
+            output_string.push_str(&self.format_ema_section(&kline_data_map));
+        }
+
+        if macd_requested {
+            output_string.push_str(&self.format_macd_section(&kline_data_map));
+        }
+
+        if atr_requested {
+            output_string.push_str(&self.format_atr_section(&kline_data_map));
+        }
+
+        if fibonacci_requested {
+            output_string.push_str(&self.format_fibonacci_section(&kline_data_map));
+        }
+
+        if zones_requested {
+            output_string.push_str(
+                &self
+                    .format_support_resistance_section(&kline_data_map)
+                    .await?,
+            );
+        }
+
+        if patterns_requested {
+            output_string.push_str(&self.format_patterns_section(&kline_data_map));
+        }
+
+        if divergences_requested {
+            output_string.push_str(&self.format_divergences_section(&kline_data_map));
+        }
+
+        if taker_requested {
+            output_string.push_str(&self.format_taker_pressure_section(&kline_data_map)?);
+        }
+
+        if order_flow_requested {
+            output_string.push_str(&self.format_order_flow_section().await?);
+        }
+
+        let mut derivatives = None;
+        if derivatives_requested {
+            let (section, snapshot) = self.format_derivatives_section().await?;
+            output_string.push_str(&section);
+            derivatives = snapshot;
+        }
+
+        Ok((output_string, quality_reports, derivatives))
     }
 }
 
@@ -444,6 +1482,27 @@ mod tests {
         false
     }
 
+    // The recorded SOLUSDT data under tests/fixtures.
+    fn fixtures() -> Result<crate::fixture::FixtureSource> {
+        crate::fixture::FixtureSource::from_dir(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures"
+        ))
+    }
+
+    // Rows of the csv table after the first line starting with `* {header}`, column names first.
+    fn section_rows<'r>(report: &'r str, header: &str) -> Vec<&'r str> {
+        let start = report
+            .find(&format!("\n* {header}"))
+            .unwrap_or_else(|| panic!("missing {header} table in {report}"));
+        report[start..]
+            .lines()
+            .skip_while(|line| *line != "```csv")
+            .skip(1)
+            .take_while(|line| !line.starts_with("```"))
+            .collect()
+    }
+
     #[tokio::test]
     async fn test_build_basic_report() -> Result<()> {
         // Renamed test
@@ -532,6 +1591,554 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_build_report_from_fixtures() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_klines(&["1h", "4h:60"])
+            .with_stoch_rsi(&["1h"])
+            .build()
+            .await?;
+
+        assert!(result_string
+            .contains("\n* Price: 1h\n```csv\nopen_time,open,high,low,close,volume,close_time\n"));
+        assert!(result_string.contains("\n* Price: 4h\n"));
+        assert!(
+            result_string.contains("\n* Stochastic RSI: 1h\n```csv\nat,stoch_rsi_k,stoch_rsi_d\n")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_report_rejects_malformed_klines() -> Result<()> {
+        let source = fixtures()?;
+        let mut klines = source.fetch_klines("SOL_USDT", "1h", 50).await?;
+        klines[10].close_price = "not a price".to_string();
+        let source = source.with_klines("SOL_USDT", "1h", klines);
+
+        let error = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_klines(&["1h"])
+            .build()
+            .await
+            .unwrap_err();
+
+        let candle_error = error
+            .downcast_ref::<crate::CandleError>()
+            .expect("a CandleError");
+        assert!(matches!(
+            candle_error,
+            crate::CandleError::InvalidNumber {
+                field: "close_price",
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_report_with_quality_check() -> Result<()> {
+        let source = fixtures()?;
+        let mut klines = source.fetch_klines("SOL_USDT", "1h", 50).await?;
+        klines.remove(20);
+        let source = source.with_klines("SOL_USDT", "1h", klines);
+
+        let (_, reports) = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 49)
+            .with_klines(&["1h", "4h"])
+            .with_quality_check(QualityConfig::default().without_stale_check())
+            .build_with_quality_reports()
+            .await?;
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].interval, Interval::Hour1);
+        assert_eq!(reports[0].missing_candles(), 1);
+        assert_eq!(reports[0].filled, 1);
+        assert!(reports[0].usable);
+        assert!(reports[1].is_clean());
+
+        // Recorded fixtures are long past, so the default check refuses them.
+        let error = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_klines(&["1h"])
+            .with_quality_check(QualityConfig::default())
+            .build()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("stale"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_report_from_base_intervals() -> Result<()> {
+        let fixtures = fixtures()?;
+        // Only hourly data, so 4h and 1d can't be fetched directly.
+        let hourly = fixtures.fetch_klines("SOL_USDT", "1h", 500).await?;
+        let source = crate::fixture::FixtureSource::new().with_klines("SOL_USDT", "1h", hourly);
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 10)
+            .with_base_intervals(&["1h"])
+            .with_klines(&["1h", "4h", "1d:5"])
+            .build()
+            .await?;
+
+        assert_eq!(section_rows(&result_string, "Price: 1h\n").len(), 1 + 10);
+        assert_eq!(section_rows(&result_string, "Price: 4h\n").len(), 1 + 10);
+        assert_eq!(section_rows(&result_string, "Price: 1d\n").len(), 1 + 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_report_degrades_failed_intervals() -> Result<()> {
+        let fixtures = fixtures()?;
+        let hourly = fixtures.fetch_klines("SOL_USDT", "1h", 50).await?;
+        // No 1d data, so that fetch fails.
+        let source = crate::fixture::FixtureSource::new().with_klines("SOL_USDT", "1h", hourly);
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_klines(&["1h", "1d"])
+            .with_stoch_rsi(&["1d"])
+            .with_concurrency(2)
+            .build()
+            .await?;
+        assert!(result_string.contains("\n* Price: 1h\n"));
+        assert!(result_string
+            .contains("\n* Interval: 1d (Unavailable: Builder: Failed fetching klines"));
+        assert!(result_string.contains("\n**Stochastic RSI:**\n\n* Interval: 1d (Unavailable: "));
+
+        let error = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_klines(&["1h", "1d"])
+            .with_required_intervals(&["1d"])
+            .build()
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Required interval 1d unavailable"));
+
+        // A required interval that worked stays in the report, next to the failed ones.
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_klines(&["1h", "1d"])
+            .with_stoch_rsi(&["1h"])
+            .with_required_intervals(&["1h"])
+            .build()
+            .await?;
+        assert_eq!(section_rows(&result_string, "Price: 1h\n").len(), 1 + 50);
+        assert!(result_string.contains("\n* Stochastic RSI: 1h\n```csv\n"));
+        assert!(result_string
+            .contains("\n* Interval: 1d (Unavailable: Builder: Failed fetching klines"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_taker_pressure_report() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_klines(&["1h"])
+            .with_taker_columns()
+            .with_taker_pressure(&["1h:12"])
+            .build()
+            .await?;
+
+        assert!(result_string.contains(
+            "\n* Price: 1h\n```csv\nopen_time,open,high,low,close,volume,close_time,quote_volume,taker_buy_ratio,trades,trade_count_zscore\n"
+        ));
+        assert!(result_string.contains(
+            "\n**Taker Pressure:**\n\n* Taker Pressure: 1h\n```csv\nopen_time,quote_volume,taker_buy_ratio,trades,trade_count_zscore\n"
+        ));
+
+        let rows = section_rows(&result_string, "Taker Pressure: 1h\n");
+        assert_eq!(rows.len(), 1 + 12);
+        // Recorded Binance klines report every field.
+        assert!(rows.iter().all(|row| !row.contains(",,")));
+
+        // Without the flag the kline table keeps its usual columns.
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_klines(&["1h"])
+            .build()
+            .await?;
+        assert!(result_string
+            .contains("\n* Price: 1h\n```csv\nopen_time,open,high,low,close,volume,close_time\n"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_bb_and_ma_report() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_bb(&["1h:12", "4h"])
+            .with_ma(&["1h:10"])
+            .build()
+            .await?;
+
+        assert!(result_string.contains("\n**Bollinger Bands (20, 2):**\n"));
+        let bb = section_rows(&result_string, "Bollinger Bands (20, 2): 1h\n");
+        assert_eq!(bb[0], "at,close,avg,upper,lower,percent_b,bandwidth_pct");
+        assert_eq!(bb.len(), 1 + 12);
+        // Without a limit the interval gets the builder's default.
+        assert_eq!(
+            section_rows(&result_string, "Bollinger Bands (20, 2): 4h\n").len(),
+            1 + 48
+        );
+
+        assert!(result_string.contains("\n**Moving Averages (sma:7, sma:25, sma:99):**\n"));
+        let ma = section_rows(
+            &result_string,
+            "Moving Averages (sma:7, sma:25, sma:99): 1h\n",
+        );
+        assert_eq!(ma[0], "at,close,sma_7,sma_25,sma_99");
+        assert_eq!(ma.len(), 1 + 10);
+        // Enough warm-up is fetched for the longest line on every row.
+        assert!(ma
+            .iter()
+            .all(|row| !row.contains(",,") && !row.ends_with(',')));
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_bb(&["1h:5"])
+            .with_ma(&["1h:5"])
+            .with_indicator_config(
+                IndicatorConfig::default()
+                    .with_overrides([("bb", "10,2.5"), ("ma", "ema:9,sma:50")])?,
+            )
+            .build()
+            .await?;
+
+        let bb = section_rows(&result_string, "Bollinger Bands (10, 2.5): 1h\n");
+        assert_eq!(bb.len(), 1 + 5);
+        let ma = section_rows(&result_string, "Moving Averages (ema:9, sma:50): 1h\n");
+        assert_eq!(ma[0], "at,close,ema_9,sma_50");
+        assert_eq!(ma.len(), 1 + 5);
+        // Both sections end on the latest candle.
+        assert_eq!(bb[5].split(',').next(), ma[5].split(',').next());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_ema_macd_atr_report() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_ema(&["1h:12"])
+            .with_macd(&["1h:12"])
+            .with_atr(&["1h:12"])
+            .build()
+            .await?;
+
+        let table = |title: &str| section_rows(&result_string, &format!("{title}: 1h\n"));
+
+        assert!(result_string.contains("\n**EMA (9/21):**\n"));
+        let ema = table("EMA (9/21)");
+        assert_eq!(ema[0], "at,ema_9,ema_21,cross");
+        // Warm-up candles are fetched so every requested row has a reading.
+        assert_eq!(ema.len(), 1 + 12);
+
+        assert!(result_string.contains("\n**MACD (12/26/9):**\n"));
+        let macd = table("MACD (12/26/9)");
+        assert_eq!(macd[0], "at,macd,signal,histogram,cross");
+        assert_eq!(macd.len(), 1 + 12);
+
+        assert!(result_string.contains("\n**ATR (14):**\n"));
+        let atr = table("ATR (14)");
+        assert_eq!(atr[0], "at,true_range,atr,atr_pct,range_to_atr");
+        assert_eq!(atr.len(), 1 + 12 + 1);
+        assert!(atr[13].starts_with("latest_atr="));
+        assert!(atr[13].contains(", stop_buffer_1_5x="));
+
+        // All three end on the same, latest candle.
+        let last_at = |rows: &[&str]| rows[12].split(',').next().unwrap().to_string();
+        assert_eq!(last_at(&ema), last_at(&macd));
+        assert_eq!(last_at(&ema), last_at(&atr));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_fibonacci_report() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_fibonacci(&["4h:84"])
+            .with_indicator_config(IndicatorConfig::default().with_overrides([("swing", "3")])?)
+            .build()
+            .await?;
+
+        assert!(result_string.contains("\n**Fibonacci Levels (3% swings):**\n"));
+        let table = section_rows(&result_string, "Fibonacci Levels (3% swings): 4h\n");
+        assert_eq!(table[0], "open_time,kind,price,confirmed");
+        let swings = &table[1..table.len() - 2];
+        assert!(swings.len() >= 2);
+        // Highs and lows alternate and only the last one can still move.
+        for pair in swings.windows(2) {
+            assert_ne!(pair[0].split(',').nth(1), pair[1].split(',').nth(1));
+            assert!(pair[0].ends_with(",true"));
+        }
+        assert!(table[table.len() - 2].starts_with("fib_leg="));
+        assert!(table[table.len() - 1].starts_with("fib_0.382="));
+        assert!(table[table.len() - 1].contains(", fib_1.618="));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_support_resistance_report() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_support_resistance(&["1h:168", "4h:84", "1d"])
+            .with_indicator_config(IndicatorConfig::default().with_overrides([("swing", "3")])?)
+            .build()
+            .await?;
+
+        assert!(result_string.contains("\n**Support/Resistance Zones (3% swings, 1% wide):**\n"));
+        assert!(result_string.contains("\n* Zones: 1d, 1h:168, 4h:84 (current_price="));
+        let zones = section_rows(&result_string, "Zones: 1d, 1h:168, 4h:84");
+        assert_eq!(
+            zones[0],
+            "low,high,kind,touches,relative_volume,book_quantity,last_touch,score"
+        );
+        assert!(zones.len() > 1 && zones.len() <= 1 + MAX_ZONES);
+        // Highest first, resistance above support.
+        let lows: Vec<f64> = zones[1..]
+            .iter()
+            .map(|zone| zone.split(',').next().unwrap().parse().unwrap())
+            .collect();
+        assert!(lows.windows(2).all(|pair| pair[0] > pair[1]));
+        let kinds: Vec<&str> = zones[1..]
+            .iter()
+            .map(|zone| zone.split(',').nth(2).unwrap())
+            .collect();
+        let supports = kinds.iter().position(|kind| *kind == "support");
+        assert!(kinds[supports.unwrap_or(kinds.len())..]
+            .iter()
+            .all(|kind| *kind == "support"));
+        // The recorded book contributes resting quantity.
+        assert!(zones[1..]
+            .iter()
+            .any(|zone| zone.split(',').nth(5) != Some("0.000")));
+
+        // A book handed in is used instead of the recorded one.
+        let empty_book = OrderBook {
+            last_update_id: 1,
+            bids: Vec::new(),
+            asks: Vec::new(),
+        };
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_support_resistance(&["1h:168", "4h:84", "1d"])
+            .with_indicator_config(IndicatorConfig::default().with_overrides([("swing", "3")])?)
+            .with_orderbook(empty_book)
+            .build()
+            .await?;
+        assert!(section_rows(&result_string, "Zones: ")[1..]
+            .iter()
+            .all(|zone| zone.split(',').nth(5) == Some("0.000")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_patterns_report() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_patterns(&["1h:24"])
+            .build()
+            .await?;
+
+        assert!(result_string.contains("\n**Recent Candlestick Patterns:**\n"));
+        let events = section_rows(&result_string, "Recent Candlestick Patterns: 1h\n");
+        assert_eq!(events[0], "open_time,pattern,direction,close");
+
+        // Patterns completed by the last 24 candles, the 4 before them only give context.
+        let klines = source
+            .fetch_klines("SOL_USDT", "1h", 24 + PATTERN_LOOKBACK as i32)
+            .await?;
+        let since = klines[PATTERN_LOOKBACK].open_time;
+        let expected: Vec<_> = crate::patterns::find_kline_patterns(&klines)?
+            .into_iter()
+            .filter(|event| event.open_time >= since)
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(events.join("\n") + "\n", patterns_to_csv(&expected));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_divergences_report() -> Result<()> {
+        let source = fixtures()?;
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 48)
+            .with_divergences(&["1h:96"])
+            .build()
+            .await?;
+
+        assert!(result_string.contains("\n**Divergences (2% swings):**\n"));
+        let rows = section_rows(&result_string, "Divergences (2% swings): 1h\n");
+
+        // Divergences ending within the last 96 candles, the warm-up candles only feed the indicators.
+        let indicators = IndicatorConfig::default();
+        let warm_up = indicators.divergence_warm_up();
+        let klines = source
+            .fetch_klines("SOL_USDT", "1h", 96 + warm_up as i32)
+            .await?;
+        let candles = Candle::from_klines(&klines)?;
+        let since = candles[warm_up].open_time;
+        let expected: Vec<_> = find_divergences(&candles, &indicators)?
+            .into_iter()
+            .filter(|divergence| divergence.end >= since)
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(rows.join("\n") + "\n", divergences_to_csv(&expected));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_order_flow_report() -> Result<()> {
+        // One trade a minute over the last three hours, a large buy 30 minutes ago.
+        let now = chrono::Utc::now().timestamp_millis();
+        let trades: Vec<crate::AggTrade> = (0..180)
+            .rev()
+            .map(|minutes_ago: i64| crate::AggTrade {
+                id: 1000 - minutes_ago,
+                price: "150".to_string(),
+                quantity: if minutes_ago == 30 { "100" } else { "1" }.to_string(),
+                first_trade_id: 1000 - minutes_ago,
+                last_trade_id: 1000 - minutes_ago,
+                time: now - minutes_ago * 60_000,
+                is_buyer_maker: minutes_ago % 2 == 1,
+            })
+            .collect();
+        let source = crate::fixture::FixtureSource::new().with_agg_trades("SOL_USDT", trades);
+
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_order_flow(&["15m:4", "1h:2"])
+            .build()
+            .await?;
+
+        let header =
+            "open_time,buy_volume,sell_volume,delta,cvd,trades,large_buy_volume,large_sell_volume\n";
+        assert!(result_string.contains("\n**Order Flow (Taker Buy/Sell Volume):**\n"));
+        assert!(result_string.contains(&format!("\n* Order Flow: 15m\n```csv\n{header}")));
+        assert!(result_string.contains(&format!("\n* Order Flow: 1h\n```csv\n{header}")));
+        assert!(result_string.contains(",buy,150,100,15000.00\n"));
+
+        assert_eq!(
+            section_rows(&result_string, "Order Flow: 15m\n").len(),
+            1 + 4
+        );
+
+        // Capped below the window, both intervals keep the trades read and flag the oldest candle.
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_order_flow(&["15m:4", "1h:2"])
+            .with_max_trades(40)
+            .build()
+            .await?;
+        for interval in ["15m", "1h"] {
+            let rows = section_rows(&result_string, &format!("Order Flow: {interval} (oldest"));
+            assert!(rows.len() > 1);
+            let trades: usize = rows[1..]
+                .iter()
+                .map(|row| row.split(',').nth(5).unwrap().parse::<usize>().unwrap())
+                .sum();
+            assert_eq!(trades, 40);
+        }
+        assert!(result_string.contains(" partly covered, the latest 40 trades only)\n"));
+
+        // Sources without trades degrade the section.
+        let result_string =
+            PriceHistoryBuilder::from_source(&crate::fixture::FixtureSource::new(), "SOL_USDT", 50)
+                .with_order_flow(&["1h"])
+                .build()
+                .await?;
+        assert!(result_string
+            .contains("\n* Interval: 1h (Unavailable: Builder: Failed fetching trades"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_derivatives_report() -> Result<()> {
+        const HOUR: i64 = 3_600_000;
+        let open_interest = (0..30)
+            .map(|i| crate::OpenInterest {
+                symbol: "SOLUSDT".to_string(),
+                sum_open_interest: (1000 + i).to_string(),
+                sum_open_interest_value: "150000".to_string(),
+                timestamp: i * HOUR,
+            })
+            .collect();
+        let long_short_ratios = (0..30)
+            .map(|i| crate::LongShortRatio {
+                symbol: "SOLUSDT".to_string(),
+                long_short_ratio: "2.5".to_string(),
+                long_account: "0.7143".to_string(),
+                short_account: "0.2857".to_string(),
+                timestamp: i * HOUR,
+            })
+            .collect();
+        let funding_rates = (0..4)
+            .map(|i| crate::FundingRate {
+                symbol: "SOLUSDT".to_string(),
+                funding_rate: "0.0001".to_string(),
+                funding_time: i * 8 * HOUR,
+                mark_price: "150".to_string(),
+            })
+            .collect();
+        let source = crate::fixture::FixtureSource::new().with_futures_metrics(
+            "SOL_USDT",
+            "1h",
+            crate::FuturesMetrics {
+                funding_rates,
+                open_interest,
+                long_short_ratios,
+            },
+        );
+
+        let mut context = TradingContext {
+            token_symbol: "SOL".to_string(),
+            pair_symbol: "SOL_USDT".parse()?,
+            interval: "1h".to_string(),
+            current_price: 150.0,
+            maybe_oracle_price: None,
+            maybe_preps_positions: None,
+            maybe_trading_predictions: None,
+            kline_intervals: Vec::new(),
+            stoch_rsi_intervals: Vec::new(),
+            latest_bb_ma_intervals: Vec::new(),
+            data_quality: Vec::new(),
+            maybe_derivatives: None,
+        };
+        let result_string = PriceHistoryBuilder::from_source(&source, "SOL_USDT", 50)
+            .with_derivatives(&["1h:10", "4h"])
+            .build_with_context(&mut context)
+            .await?;
+
+        assert!(result_string.contains("\n**Derivatives Positioning (Perpetual Futures):**\n"));
+        assert!(result_string.contains(
+            "\n* Open Interest and Top Trader Long/Short Ratio: 1h\n```csv\ntimestamp,open_interest,open_interest_value,long_short_ratio,long_account\n"
+        ));
+        assert!(result_string
+            .contains("\n* Interval: 4h (Unavailable: Builder: Failed fetching futures metrics"));
+        // Only the funding settled over the last 10 hours.
+        assert!(result_string.contains(
+            "\n* Funding Rates:\n```csv\nfunding_time,funding_rate,mark_price\n86400000,0.0001,150\n```\n"
+        ));
+        assert!(result_string.contains("\n* Summary: funding_rate=0.0100%"));
+
+        let snapshot = context.maybe_derivatives.expect("a derivatives snapshot");
+        assert_eq!(snapshot.period, "1h");
+        assert_eq!(snapshot.open_interest, 1029.0);
+        assert_eq!(snapshot.crowding, crate::derivatives::Crowding::Longs);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_build_no_requests() -> Result<()> {
         // Renamed test
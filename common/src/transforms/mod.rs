@@ -1,2 +1,4 @@
 pub mod csv;
 pub mod numbers;
+pub mod quality;
+pub mod resample;
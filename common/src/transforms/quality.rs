@@ -0,0 +1,467 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt};
+
+use crate::{binance::dedupe_klines, market::MarketDataSource, Interval, Kline};
+
+/// How `check_and_repair` fixes a series with missing candles.
+///
+/// Duplicates and out-of-order rows are always resolved by sorting and keeping the last
+/// row seen, except with `RepairStrategy::None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairStrategy {
+    /// Only report, leave the series as it is.
+    None,
+    /// Fill each missing candle with a flat candle at the previous close and zero volume.
+    #[default]
+    ForwardFill,
+    /// Refetch the missing ranges (and a stale tail) from the source,
+    /// forward-filling whatever the source still doesn't have.
+    Refetch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityConfig {
+    pub repair: RepairStrategy,
+    /// How many candles the last candle may lag behind the current one before the series
+    /// counts as stale, `None` skips the check (e.g. for recorded fixtures).
+    pub max_stale_candles: Option<i64>,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        QualityConfig {
+            repair: RepairStrategy::default(),
+            max_stale_candles: Some(1),
+        }
+    }
+}
+
+impl QualityConfig {
+    pub fn with_repair(mut self, repair: RepairStrategy) -> Self {
+        self.repair = repair;
+        self
+    }
+
+    pub fn with_max_stale_candles(mut self, max_stale_candles: i64) -> Self {
+        self.max_stale_candles = Some(max_stale_candles);
+        self
+    }
+
+    pub fn without_stale_check(mut self) -> Self {
+        self.max_stale_candles = None;
+        self
+    }
+}
+
+/// A run of missing candles, starting at `open_time` (ms).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KlineGap {
+    pub open_time: i64,
+    pub missing: i64,
+}
+
+/// What was wrong with a kline series and what was done about it.
+///
+/// Issues are the ones found in the fetched data, `usable` tells whether the series
+/// handed on (after repair) is contiguous, ordered and fresh.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataQualityReport {
+    pub interval: Interval,
+    pub candles: usize,
+    pub gaps: Vec<KlineGap>,
+    /// Open times (ms) seen more than once.
+    pub duplicates: Vec<i64>,
+    /// Open times (ms) of rows that came before an earlier candle.
+    pub out_of_order: Vec<i64>,
+    pub last_open_time: Option<i64>,
+    pub stale: bool,
+    pub repair: RepairStrategy,
+    pub filled: usize,
+    pub refetched: usize,
+    pub usable: bool,
+}
+
+impl DataQualityReport {
+    pub fn missing_candles(&self) -> i64 {
+        self.gaps.iter().map(|gap| gap.missing).sum()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.gaps.is_empty()
+            && self.duplicates.is_empty()
+            && self.out_of_order.is_empty()
+            && !self.stale
+    }
+}
+
+impl fmt::Display for DataQualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} candles", self.interval, self.candles)?;
+        if self.is_clean() {
+            return f.write_str(", ok");
+        }
+        if !self.gaps.is_empty() {
+            write!(
+                f,
+                ", {} gaps ({} missing)",
+                self.gaps.len(),
+                self.missing_candles()
+            )?;
+        }
+        if !self.duplicates.is_empty() {
+            write!(f, ", {} duplicates", self.duplicates.len())?;
+        }
+        if !self.out_of_order.is_empty() {
+            write!(f, ", {} out of order", self.out_of_order.len())?;
+        }
+        if self.stale {
+            write!(
+                f,
+                ", stale since {}",
+                self.last_open_time.unwrap_or_default()
+            )?;
+        }
+        if self.refetched > 0 {
+            write!(f, ", {} refetched", self.refetched)?;
+        }
+        if self.filled > 0 {
+            write!(f, ", {} forward-filled", self.filled)?;
+        }
+        f.write_str(if self.usable {
+            ", usable"
+        } else {
+            ", unusable"
+        })
+    }
+}
+
+/// Checks `klines` (in the order received) for gaps, duplicates, out-of-order rows and,
+/// with `max_stale_candles`, a last candle lagging behind `now` (ms).
+pub fn check_klines(
+    klines: &[Kline],
+    interval: Interval,
+    now: i64,
+    max_stale_candles: Option<i64>,
+) -> DataQualityReport {
+    let mut duplicates = Vec::new();
+    let mut out_of_order = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut latest: Option<i64> = None;
+
+    for kline in klines {
+        if !seen.insert(kline.open_time) {
+            duplicates.push(kline.open_time);
+        } else if latest.is_some_and(|latest| kline.open_time < latest) {
+            out_of_order.push(kline.open_time);
+        }
+        latest = latest.max(Some(kline.open_time));
+    }
+
+    // Rows that arrive late still fill their slot, so gaps are looked for in open time order.
+    let open_times: Vec<i64> = seen.into_iter().collect();
+    let gaps: Vec<KlineGap> = open_times
+        .windows(2)
+        .filter_map(|pair| find_gap(interval, pair[0], pair[1]))
+        .collect();
+
+    let stale = match (latest, max_stale_candles) {
+        (Some(last_open_time), Some(max_stale_candles)) => {
+            is_stale(interval, last_open_time, now, max_stale_candles)
+        }
+        // Nothing at all is as stale as it gets.
+        (None, Some(_)) => true,
+        (_, None) => false,
+    };
+
+    let usable = gaps.is_empty() && duplicates.is_empty() && out_of_order.is_empty() && !stale;
+    DataQualityReport {
+        interval,
+        candles: klines.len(),
+        gaps,
+        duplicates,
+        out_of_order,
+        last_open_time: latest,
+        stale,
+        repair: RepairStrategy::None,
+        filled: 0,
+        refetched: 0,
+        usable,
+    }
+}
+
+fn find_gap(interval: Interval, previous: i64, open_time: i64) -> Option<KlineGap> {
+    let first_missing = interval.next_open_time(previous);
+    let mut missing = 0;
+    let mut expected = first_missing;
+    while expected < open_time {
+        missing += 1;
+        expected = interval.next_open_time(expected);
+    }
+    (missing > 0).then_some(KlineGap {
+        open_time: first_missing,
+        missing,
+    })
+}
+
+fn gap_end(interval: Interval, gap: &KlineGap) -> i64 {
+    (0..gap.missing).fold(gap.open_time, |open_time, _| {
+        interval.next_open_time(open_time)
+    })
+}
+
+fn is_stale(interval: Interval, last_open_time: i64, now: i64, max_stale_candles: i64) -> bool {
+    let current_open_time = interval.align(now.div_euclid(1000)) * 1000;
+    let lag = (0..max_stale_candles).fold(last_open_time, |open_time, _| {
+        interval.next_open_time(open_time)
+    });
+    lag < current_open_time
+}
+
+/// Sorts, dedupes and fills every missing candle with a flat one at the previous close.
+///
+/// Returns the repaired series and how many candles were filled.
+pub fn forward_fill(klines: Vec<Kline>, interval: Interval) -> (Vec<Kline>, usize) {
+    let klines = dedupe_klines(klines);
+    let mut filled: Vec<Kline> = Vec::with_capacity(klines.len());
+    let mut filled_count = 0;
+
+    for kline in klines {
+        if let Some(previous) = filled.last() {
+            let mut open_time = interval.next_open_time(previous.open_time);
+            let close_price = previous.close_price.clone();
+            while open_time < kline.open_time {
+                let next_open_time = interval.next_open_time(open_time);
+                filled.push(flat_kline(open_time, next_open_time - 1, &close_price));
+                filled_count += 1;
+                open_time = next_open_time;
+            }
+        }
+        filled.push(kline);
+    }
+
+    (filled, filled_count)
+}
+
+fn flat_kline(open_time: i64, close_time: i64, price: &str) -> Kline {
+    Kline {
+        open_time,
+        open_price: price.to_string(),
+        high_price: price.to_string(),
+        low_price: price.to_string(),
+        close_price: price.to_string(),
+        volume: "0".to_string(),
+        close_time,
+        quote_asset_volume: "0".to_string(),
+        number_of_trades: 0,
+        taker_buy_base_asset_volume: "0".to_string(),
+        taker_buy_quote_asset_volume: "0".to_string(),
+        ignore: "0".to_string(),
+    }
+}
+
+/// Checks `klines` and repairs them following `config`.
+///
+/// The report describes the data as fetched plus the repairs made, its `usable` flag
+/// reflects the returned series. Only refetching talks to `source`, a failed refetch
+/// falls back to forward-filling.
+pub async fn check_and_repair<S: MarketDataSource>(
+    source: &S,
+    pair_symbol: &str,
+    interval: Interval,
+    klines: Vec<Kline>,
+    config: &QualityConfig,
+    now: i64,
+) -> (Vec<Kline>, DataQualityReport) {
+    let mut report = check_klines(&klines, interval, now, config.max_stale_candles);
+    report.repair = config.repair;
+    if report.is_clean() || config.repair == RepairStrategy::None {
+        return (klines, report);
+    }
+
+    let mut klines = dedupe_klines(klines);
+    if config.repair == RepairStrategy::Refetch {
+        match refetch_missing(source, pair_symbol, interval, &klines, &report, now).await {
+            Ok(refetched) => {
+                let before = klines.len();
+                klines.extend(refetched);
+                klines = dedupe_klines(klines);
+                report.refetched = klines.len() - before;
+            }
+            Err(error) => {
+                eprintln!("Failed to refetch {pair_symbol} {interval} klines: {error}");
+            }
+        }
+    }
+
+    let (klines, filled) = forward_fill(klines, interval);
+    report.filled = filled;
+
+    let repaired = check_klines(&klines, interval, now, config.max_stale_candles);
+    report.usable = repaired.is_clean();
+    (klines, report)
+}
+
+async fn refetch_missing<S: MarketDataSource>(
+    source: &S,
+    pair_symbol: &str,
+    interval: Interval,
+    klines: &[Kline],
+    report: &DataQualityReport,
+    now: i64,
+) -> Result<Vec<Kline>> {
+    let mut ranges: Vec<(i64, i64)> = report
+        .gaps
+        .iter()
+        .map(|gap| (gap.open_time, gap_end(interval, gap)))
+        .collect();
+    if report.stale {
+        if let Some(last) = klines.last() {
+            ranges.push((interval.next_open_time(last.open_time), now));
+        }
+    }
+
+    let mut refetched = Vec::new();
+    for (start_time, end_time) in ranges {
+        refetched.extend(
+            source
+                .fetch_klines_range(pair_symbol, interval.as_str(), start_time, end_time)
+                .await?,
+        );
+    }
+    Ok(refetched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::FixtureSource;
+
+    const HOUR: i64 = 3_600_000;
+    // 2025-04-30T00:00:00Z
+    const START: i64 = 1_745_971_200_000;
+
+    fn kline(open_time: i64, close: &str) -> Kline {
+        let mut kline = flat_kline(open_time, open_time + HOUR - 1, close);
+        kline.volume = "10".to_string();
+        kline
+    }
+
+    fn hourly(hours: &[i64]) -> Vec<Kline> {
+        hours
+            .iter()
+            .map(|hour| kline(START + hour * HOUR, &format!("{}", 100 + hour)))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_klines() {
+        let now = START + 5 * HOUR + 60_000;
+
+        let report = check_klines(&hourly(&[0, 1, 2, 3, 4, 5]), Interval::Hour1, now, Some(1));
+        assert!(report.is_clean());
+        assert!(report.usable);
+        assert_eq!(report.to_string(), "1h: 6 candles, ok");
+
+        let report = check_klines(&hourly(&[0, 1, 1, 4, 3, 5]), Interval::Hour1, now, Some(1));
+        assert_eq!(report.duplicates, vec![START + HOUR]);
+        assert_eq!(report.out_of_order, vec![START + 3 * HOUR]);
+        // Hour 3 arrived late, only hour 2 is missing.
+        assert_eq!(
+            report.gaps,
+            vec![KlineGap {
+                open_time: START + 2 * HOUR,
+                missing: 1,
+            }]
+        );
+        assert!(!report.usable);
+
+        // The last candle is two hours behind the current one.
+        let report = check_klines(&hourly(&[0, 1, 2, 3]), Interval::Hour1, now, Some(1));
+        assert!(report.stale);
+        let report = check_klines(&hourly(&[0, 1, 2, 3]), Interval::Hour1, now, Some(2));
+        assert!(!report.stale);
+        let report = check_klines(&hourly(&[0, 1, 2, 3]), Interval::Hour1, now, None);
+        assert!(!report.stale);
+    }
+
+    #[test]
+    fn test_forward_fill() {
+        let (klines, filled) = forward_fill(hourly(&[0, 3, 1, 1]), Interval::Hour1);
+        assert_eq!(filled, 1);
+        let open_times: Vec<i64> = klines
+            .iter()
+            .map(|k| (k.open_time - START) / HOUR)
+            .collect();
+        assert_eq!(open_times, vec![0, 1, 2, 3]);
+        // The filled candle is flat at the previous close.
+        assert_eq!(klines[2].open_price, "101");
+        assert_eq!(klines[2].close_price, "101");
+        assert_eq!(klines[2].volume, "0");
+        assert_eq!(klines[2].close_time, START + 3 * HOUR - 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_and_repair() {
+        let now = START + 5 * HOUR + 60_000;
+        let source =
+            FixtureSource::new().with_klines("SOL_USDT", "1h", hourly(&[0, 1, 2, 3, 4, 5]));
+        let broken = hourly(&[0, 1, 4, 5]);
+
+        let config = QualityConfig::default().with_repair(RepairStrategy::Refetch);
+        let (klines, report) = check_and_repair(
+            &source,
+            "SOL_USDT",
+            Interval::Hour1,
+            broken.clone(),
+            &config,
+            now,
+        )
+        .await;
+        assert_eq!(klines.len(), 6);
+        assert_eq!(klines[2].close_price, "102");
+        assert_eq!(report.refetched, 2);
+        assert_eq!(report.filled, 0);
+        assert!(report.usable);
+
+        let config = QualityConfig::default();
+        let (klines, report) = check_and_repair(
+            &source,
+            "SOL_USDT",
+            Interval::Hour1,
+            broken.clone(),
+            &config,
+            now,
+        )
+        .await;
+        assert_eq!(klines.len(), 6);
+        assert_eq!(klines[2].close_price, "101");
+        assert_eq!(report.filled, 2);
+        assert!(report.usable);
+        assert_eq!(
+            report.to_string(),
+            "1h: 4 candles, 1 gaps (2 missing), 2 forward-filled, usable"
+        );
+
+        let config = QualityConfig::default().with_repair(RepairStrategy::None);
+        let (klines, report) =
+            check_and_repair(&source, "SOL_USDT", Interval::Hour1, broken, &config, now).await;
+        assert_eq!(klines.len(), 4);
+        assert!(!report.usable);
+
+        // Forward-filling can't make up for a stale series.
+        let config = QualityConfig::default();
+        let (_, report) = check_and_repair(
+            &source,
+            "SOL_USDT",
+            Interval::Hour1,
+            hourly(&[0, 1, 2]),
+            &config,
+            now,
+        )
+        .await;
+        assert!(report.stale);
+        assert!(!report.usable);
+    }
+}
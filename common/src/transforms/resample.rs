@@ -0,0 +1,186 @@
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+use crate::{Candle, Interval, Kline};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Whether candles of `to` are made of whole candles of `from`.
+///
+/// Weeks open on Monday and months on the 1st, so both need a base of a day or less.
+pub fn can_resample(from: Interval, to: Interval) -> bool {
+    if from >= to {
+        return false;
+    }
+    match to {
+        Interval::Month1 | Interval::Week1 => DAY_MS % from.duration_ms() == 0,
+        _ => to.duration_ms() % from.duration_ms() == 0,
+    }
+}
+
+/// Upper bound of `from` candles in one `to` candle, months counting as 31 days.
+pub fn candles_per(from: Interval, to: Interval) -> i64 {
+    let to_ms = match to {
+        Interval::Month1 => 31 * DAY_MS,
+        _ => to.duration_ms(),
+    };
+    (to_ms + from.duration_ms() - 1) / from.duration_ms()
+}
+
+/// Aggregates `klines` of `from` into candles of the coarser `to`.
+///
+/// Opens come from the first candle of each bucket, closes from the last, highs and lows are
+/// the extremes, and volumes, quote volumes, trade counts and taker volumes are summed
+/// (taker volumes stay empty when a venue doesn't report them).
+///
+/// Buckets missing some of their candles are dropped, except the last one which is the
+/// still-forming candle, the same way exchanges serve the current candle.
+pub fn resample_klines(klines: &[Kline], from: Interval, to: Interval) -> Result<Vec<Kline>> {
+    if from == to {
+        return Ok(klines.to_vec());
+    }
+    if !can_resample(from, to) {
+        bail!("Can't resample {from} klines into {to}");
+    }
+
+    let mut buckets: BTreeMap<i64, Vec<&Kline>> = BTreeMap::new();
+    for kline in klines {
+        let open_time = to.align(kline.open_time.div_euclid(1000)) * 1000;
+        buckets.entry(open_time).or_default().push(kline);
+    }
+
+    let last_open_time = buckets.keys().next_back().copied();
+    let mut resampled = Vec::with_capacity(buckets.len());
+    for (open_time, mut bucket) in buckets {
+        bucket.sort_by_key(|kline| kline.open_time);
+        bucket.dedup_by_key(|kline| kline.open_time);
+
+        let next_open_time = to.next_open_time(open_time);
+        let expected = (next_open_time - open_time) / from.duration_ms();
+        if (bucket.len() as i64) < expected && Some(open_time) != last_open_time {
+            continue;
+        }
+
+        resampled.push(aggregate(&bucket, open_time, next_open_time - 1)?);
+    }
+
+    Ok(resampled)
+}
+
+fn aggregate(bucket: &[&Kline], open_time: i64, close_time: i64) -> Result<Kline> {
+    let candles = bucket
+        .iter()
+        .map(|kline| Candle::try_from(*kline))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        bail!("Empty bucket at {open_time}");
+    };
+
+    let high = candles.iter().map(|c| c.high).fold(f64::MIN, f64::max);
+    let low = candles.iter().map(|c| c.low).fold(f64::MAX, f64::min);
+    let volume: f64 = candles.iter().map(|c| c.volume).sum();
+    let sum_field = |field: fn(&Kline) -> &str| -> String {
+        bucket
+            .iter()
+            .map(|kline| field(kline).parse::<f64>().ok())
+            .sum::<Option<f64>>()
+            .map(|sum| sum.to_string())
+            .unwrap_or_default()
+    };
+
+    Ok(Kline {
+        open_time,
+        open_price: first.open.to_string(),
+        high_price: high.to_string(),
+        low_price: low.to_string(),
+        close_price: last.close.to_string(),
+        volume: volume.to_string(),
+        close_time,
+        quote_asset_volume: sum_field(|kline| &kline.quote_asset_volume),
+        number_of_trades: bucket.iter().map(|kline| kline.number_of_trades).sum(),
+        taker_buy_base_asset_volume: sum_field(|kline| &kline.taker_buy_base_asset_volume),
+        taker_buy_quote_asset_volume: sum_field(|kline| &kline.taker_buy_quote_asset_volume),
+        ignore: "0".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3_600_000;
+    // 2025-04-28T00:00:00Z, a Monday
+    const START: i64 = 1_745_798_400_000;
+
+    fn kline(open_time: i64, open: f64, high: f64, low: f64, close: f64) -> Kline {
+        Kline {
+            open_time,
+            open_price: open.to_string(),
+            high_price: high.to_string(),
+            low_price: low.to_string(),
+            close_price: close.to_string(),
+            volume: "10".to_string(),
+            close_time: open_time + HOUR - 1,
+            quote_asset_volume: "1000".to_string(),
+            number_of_trades: 5,
+            taker_buy_base_asset_volume: "4".to_string(),
+            taker_buy_quote_asset_volume: String::new(),
+            ignore: "0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_can_resample() {
+        assert!(can_resample(Interval::Hour1, Interval::Hour4));
+        assert!(can_resample(Interval::Minute15, Interval::Day1));
+        assert!(can_resample(Interval::Day1, Interval::Week1));
+        assert!(can_resample(Interval::Hour8, Interval::Month1));
+        assert!(!can_resample(Interval::Hour4, Interval::Hour1));
+        assert!(!can_resample(Interval::Minute3, Interval::Minute5));
+        assert!(!can_resample(Interval::Day3, Interval::Week1));
+        assert!(!can_resample(Interval::Week1, Interval::Month1));
+        assert_eq!(candles_per(Interval::Hour1, Interval::Hour4), 4);
+        assert_eq!(candles_per(Interval::Day1, Interval::Month1), 31);
+    }
+
+    #[test]
+    fn test_resample_hourly_into_4h() {
+        // 02:00 to 13:00, the 00:00 bucket is missing its first two hours and 12:00 is forming.
+        let klines: Vec<Kline> = (2..14)
+            .map(|hour| {
+                let price = 100.0 + hour as f64;
+                kline(
+                    START + hour * HOUR,
+                    price,
+                    price + 2.0,
+                    price - 1.0,
+                    price + 0.5,
+                )
+            })
+            .collect();
+
+        let resampled = resample_klines(&klines, Interval::Hour1, Interval::Hour4).unwrap();
+        assert_eq!(resampled.len(), 3);
+
+        let full = &resampled[0];
+        assert_eq!(full.open_time, START + 4 * HOUR);
+        assert_eq!(full.close_time, START + 8 * HOUR - 1);
+        assert_eq!(full.open_price, "104");
+        assert_eq!(full.high_price, "109");
+        assert_eq!(full.low_price, "103");
+        assert_eq!(full.close_price, "107.5");
+        assert_eq!(full.volume, "40");
+        assert_eq!(full.quote_asset_volume, "4000");
+        assert_eq!(full.number_of_trades, 20);
+        assert_eq!(full.taker_buy_base_asset_volume, "16");
+        assert_eq!(full.taker_buy_quote_asset_volume, "");
+
+        // The forming candle only has two hours so far.
+        let forming = &resampled[2];
+        assert_eq!(forming.open_time, START + 12 * HOUR);
+        assert_eq!(forming.volume, "20");
+        assert_eq!(forming.close_price, "113.5");
+
+        assert!(resample_klines(&klines, Interval::Hour4, Interval::Hour1).is_err());
+    }
+}
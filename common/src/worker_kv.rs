@@ -1,45 +1,55 @@
-// Function to round down a timestamp to the nearest interval
-// ts: Unix timestamp (seconds since epoch)
-// interval_seconds: The duration of the interval in seconds
-pub fn round_down_timestamp(ts: i64, interval_seconds: i64) -> i64 {
-    if interval_seconds <= 0 {
-        // Avoid division by zero or negative intervals
-        return ts;
-    }
-    // Integer division truncates towards zero.
-    // (ts / interval) gives the number of full intervals since epoch.
-    // Multiplying back by interval gives the timestamp at the start of the current interval.
-    (ts / interval_seconds) * interval_seconds
+#[cfg(feature = "kv_cache")]
+use crate::cache::CacheStore;
+use crate::Interval;
+#[cfg(feature = "kv_cache")]
+use anyhow::{anyhow, Result};
+
+// Cloudflare KV refuses shorter expiration TTLs.
+#[cfg(feature = "kv_cache")]
+const MIN_KV_TTL_SECONDS: u64 = 60;
+
+// Function to get the rounded-down key based on the current timestamp and an Interval enum
+pub fn get_key_from_interval(ts: i64, interval: Interval) -> i64 {
+    interval.align(ts)
 }
 
-// Define the time intervals as an enum
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub enum Interval {
-    Minute5,
-    Minute15,
-    Hour1,
-    Hour4,
-    Day1,
+/// `CacheStore` backed by a Cloudflare KV namespace, shared by every worker bound to it.
+///
+/// KV keeps entries for at least 60 seconds, `CachedSource` keys carry their period so a
+/// longer life never serves data from a past candle.
+#[cfg(feature = "kv_cache")]
+pub struct KvCache {
+    store: worker::kv::KvStore,
 }
 
-impl Interval {
-    // Helper function to get the duration in seconds for each interval
-    fn duration_seconds(&self) -> i64 {
-        match self {
-            Interval::Minute5 => 5 * 60,
-            Interval::Minute15 => 15 * 60,
-            Interval::Hour1 => 60 * 60,
-            Interval::Hour4 => 4 * 60 * 60,
-            Interval::Day1 => 24 * 60 * 60,
-        }
+#[cfg(feature = "kv_cache")]
+impl KvCache {
+    pub fn new(store: worker::kv::KvStore) -> Self {
+        KvCache { store }
     }
 }
 
-// Function to get the rounded-down key based on the current timestamp and an Interval enum
-pub fn get_key_from_interval(ts: i64, interval: Interval) -> i64 {
-    let interval_seconds = interval.duration_seconds();
-    round_down_timestamp(ts, interval_seconds)
+#[cfg(feature = "kv_cache")]
+impl CacheStore for KvCache {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        self.store
+            .get(key)
+            .text()
+            .await
+            .map_err(|e| anyhow!("KV get {key} failed: {e}"))
+    }
+
+    async fn put(&self, key: &str, value: String, ttl_seconds: u64) -> Result<()> {
+        self.store
+            .put(key, value)
+            .map_err(|e| anyhow!("KV put {key} failed: {e}"))?
+            .expiration_ttl(ttl_seconds.max(MIN_KV_TTL_SECONDS))
+            .execute()
+            .await
+            .map_err(|e| anyhow!("KV put {key} failed: {e}"))
+    }
 }
+
 #[cfg(test)]
 mod test {
     use crate::{get_key_from_interval, Interval};
@@ -7,9 +7,15 @@ mod predictions;
 mod providers;
 
 use common::{
-    binance::{fetch_binance_kline_usdt, get_token_and_pair_symbol_usdt},
+    binance::BinanceClient,
+    cache::CachedSource,
+    indicator_config::IndicatorConfig,
     jup::get_preps_position,
-    ConciseKline, GraphPrediction, RefinedTradingPrediction, TradingContext, TradingPrediction,
+    market::MarketDataSource,
+    oracle::{FallbackOracle, JupiterOracle, MarketPriceOracle, PriceOracle, PythOracle},
+    quality::QualityConfig,
+    GraphPrediction, Interval, KvCache, PairSymbol, RefinedTradingPrediction, TradingContext,
+    TradingPrediction,
 };
 use worker::*;
 
@@ -25,7 +31,7 @@ pub async fn handle_root(_req: Request, _ctx: RouteContext<()>) -> worker::Resul
 pub struct PredictionRequest {
     prediction_type: PredictionType,
     gemini_api_key: String,
-    pair_symbol: String,
+    pair_symbol: PairSymbol,
     orderbook_limit: i32,
     wallet_address: Option<String>,
     interval: Option<String>,
@@ -35,6 +41,8 @@ pub struct PredictionRequest {
     kline_intervals: Option<Vec<String>>,
     stoch_rsi_intervals: Option<Vec<String>>,
     latest_bb_ma_intervals: Option<Vec<String>>,
+    quality: QualityConfig,
+    indicators: IndicatorConfig,
 }
 
 #[derive(Clone)]
@@ -46,7 +54,7 @@ impl PredictionRequestBuilder {
     pub fn new(
         prediction_type: PredictionType,
         gemini_api_key: String,
-        pair_symbol: String,
+        pair_symbol: PairSymbol,
         orderbook_limit: i32,
     ) -> Self {
         Self {
@@ -63,6 +71,8 @@ impl PredictionRequestBuilder {
                 kline_intervals: None,
                 stoch_rsi_intervals: None,
                 latest_bb_ma_intervals: None,
+                quality: QualityConfig::default(),
+                indicators: IndicatorConfig::default(),
             },
         }
     }
@@ -110,13 +120,67 @@ impl PredictionRequestBuilder {
         self
     }
 
+    /// How fetched klines are checked and repaired, stale or broken data is refused.
+    /// Defaults to forward-filling gaps and at most one candle of lag.
+    pub fn quality_check(mut self, config: QualityConfig) -> Self {
+        self.request.quality = config;
+        self
+    }
+
+    /// Indicator periods of the prompt, pass the config the chart is drawn with so both show
+    /// the same numbers.
+    pub fn indicator_config(mut self, indicators: IndicatorConfig) -> Self {
+        self.request.indicators = indicators;
+        self
+    }
+
     pub async fn predict(self) -> anyhow::Result<String, String> {
-        predict_with_gemini(self.request).await
+        predict_with_gemini(
+            &BinanceClient::default(),
+            &FallbackOracle::default(),
+            self.request,
+        )
+        .await
+    }
+
+    /// Same as `predict` but reads market data from `source` instead of live Binance.
+    /// The current price is `source`'s last price rather than the oracles.
+    pub async fn predict_with_source<S: MarketDataSource>(
+        self,
+        source: &S,
+    ) -> anyhow::Result<String, String> {
+        predict_with_gemini(
+            source,
+            &MarketPriceOracle::new(source, "source"),
+            self.request,
+        )
+        .await
+    }
+
+    /// Same as `predict_with_source` but takes the current price from `oracle`.
+    pub async fn predict_with<S: MarketDataSource, O: PriceOracle>(
+        self,
+        source: &S,
+        oracle: &O,
+    ) -> anyhow::Result<String, String> {
+        predict_with_gemini(source, oracle, self.request).await
     }
 }
 
 // --- End Builder Pattern Implementation ---
 
+// Parses and validates the `:token` route param, e.g. "SOL_USDT" or "SOL".
+fn parse_pair_symbol(token: Option<&String>) -> std::result::Result<PairSymbol, String> {
+    let token = token.ok_or("Bad Request - Missing Token")?;
+    let pair_symbol = token
+        .parse::<PairSymbol>()
+        .map_err(|e| format!("Bad Request - {e}"))?;
+    pair_symbol
+        .validate()
+        .map_err(|e| format!("Bad Request - {e}"))?;
+    Ok(pair_symbol)
+}
+
 #[event(fetch)]
 async fn fetch(req: Request, env: Env, _ctx: worker::Context) -> Result<Response> {
     console_error_panic_hook::set_once();
@@ -129,16 +193,49 @@ async fn fetch(req: Request, env: Env, _ctx: worker::Context) -> Result<Response
 
     let gemini_api_key = gemini_api_key.as_str();
 
+    // Binance endpoint, proxy, user agent and timeout come from worker vars
+    let binance_client =
+        BinanceClient::from_env_vars(|key| env.var(key).ok().map(|var| var.to_string()))
+            .map_err(|e| Error::RustError(format!("Invalid Binance config: {e}")))?;
+
+    // Klines and orderbooks are shared with the feeder for the current candle period
+    let market_source = CachedSource::new(
+        binance_client.clone(),
+        env.kv("CACHE").ok().map(KvCache::new),
+    )
+    .with_namespace("binance");
+    let market_source = &market_source;
+
+    // Pyth first, then Jupiter, then the Binance ticker
+    let price_oracle = FallbackOracle::new(vec![
+        PythOracle::default().into(),
+        JupiterOracle::default().into(),
+    ])
+    .with_source(binance_client);
+    let price_oracle = &price_oracle;
+
     let router = Router::new();
 
+    // Indicator periods, e.g. "?macd=8,21,5&bb=20,2.5", see `IndicatorConfig::with_overrides`
+    fn parse_indicator_config(req: &Request) -> std::result::Result<IndicatorConfig, String> {
+        let url = req.url().map_err(|e| format!("Bad Request - {e}"))?;
+        IndicatorConfig::default()
+            .with_overrides(url.query_pairs())
+            .map_err(|e| format!("Bad Request - {e}"))
+    }
+
     // Shared handler logic
+    #[allow(clippy::too_many_arguments)]
     async fn handle_prediction_request(
         prediction_type: PredictionType,
         gemini_api_key: &str,
+        market_source: &CachedSource<BinanceClient, Option<KvCache>>,
+        price_oracle: &FallbackOracle,
         orderbook_limit: i32,
-        pair_symbol: String,
+        pair_symbol: PairSymbol,
         maybe_wallet_address: Option<String>,
         maybe_interval: Option<String>,
+        indicators: IndicatorConfig,
     ) -> Result<Response> {
         let output_result = PredictionRequestBuilder::new(
             prediction_type, // Pass prediction_type directly
@@ -148,8 +245,9 @@ async fn fetch(req: Request, env: Env, _ctx: worker::Context) -> Result<Response
         )
         .wallet_address(maybe_wallet_address)
         .interval(maybe_interval)
+        .indicator_config(indicators)
         // Other fields default to None
-        .predict() // Call predict on the builder
+        .predict_with(market_source, price_oracle) // Call predict on the builder
         .await;
 
         match output_result {
@@ -168,75 +266,110 @@ async fn fetch(req: Request, env: Env, _ctx: worker::Context) -> Result<Response
         // Endpoint: /api/v1/suggest/:token/:wallet_address
         .get_async(
             "/api/v1/suggest/:token/:wallet_address",
-            |_req, ctx| async move {
-                let pair_symbol = match ctx.param("token") {
-                    Some(token) => token.to_owned(),
-                    None => return Response::error("Bad Request - Missing Token", 400),
+            |req, ctx| async move {
+                let pair_symbol = match parse_pair_symbol(ctx.param("token")) {
+                    Ok(pair_symbol) => pair_symbol,
+                    Err(error) => return Response::error(error, 400),
+                };
+                let indicators = match parse_indicator_config(&req) {
+                    Ok(indicators) => indicators,
+                    Err(error) => return Response::error(error, 400),
                 };
                 let maybe_wallet_address = ctx.param("wallet_address").cloned();
                 handle_prediction_request(
                     PredictionType::Trading,
                     gemini_api_key,
+                    market_source,
+                    price_oracle,
                     orderbook_limit,
                     pair_symbol,
                     maybe_wallet_address,
                     None,
+                    indicators,
                 )
                 .await
             },
         )
         // Endpoint: /api/v1/suggest/:token
-        .get_async("/api/v1/suggest/:token", |_req, ctx| async move {
-            let pair_symbol = match ctx.param("token") {
-                Some(token) => token.to_owned(),
-                None => return Response::error("Bad Request - Missing Token", 400),
+        .get_async("/api/v1/suggest/:token", |req, ctx| async move {
+            let pair_symbol = match parse_pair_symbol(ctx.param("token")) {
+                Ok(pair_symbol) => pair_symbol,
+                Err(error) => return Response::error(error, 400),
+            };
+            let indicators = match parse_indicator_config(&req) {
+                Ok(indicators) => indicators,
+                Err(error) => return Response::error(error, 400),
             };
             handle_prediction_request(
                 PredictionType::Trading,
                 gemini_api_key,
+                market_source,
+                price_oracle,
                 orderbook_limit,
                 pair_symbol,
                 None,
                 None,
+                indicators,
             )
             .await
         })
         // Endpoint: /api/v1/predict/:token/:interval
-        .get_async("/api/v1/predict/:token/:interval", |_req, ctx| async move {
-            let pair_symbol = match ctx.param("token") {
-                Some(token) => token.to_owned(),
-                None => return Response::error("Bad Request - Missing Token", 400),
+        .get_async("/api/v1/predict/:token/:interval", |req, ctx| async move {
+            let pair_symbol = match parse_pair_symbol(ctx.param("token")) {
+                Ok(pair_symbol) => pair_symbol,
+                Err(error) => return Response::error(error, 400),
+            };
+            let indicators = match parse_indicator_config(&req) {
+                Ok(indicators) => indicators,
+                Err(error) => return Response::error(error, 400),
             };
 
-            // Get interval
-            let interval = ctx.param("interval");
+            // Get interval, e.g. "1h" or "1w"
+            let interval = match ctx
+                .param("interval")
+                .map(|interval| interval.parse::<Interval>())
+            {
+                Some(Ok(interval)) => interval,
+                Some(Err(error)) => return Response::error(format!("Bad Request - {error}"), 400),
+                None => return Response::error("Bad Request - Missing Interval", 400),
+            };
 
             handle_prediction_request(
                 PredictionType::Graph,
                 gemini_api_key,
+                market_source,
+                price_oracle,
                 orderbook_limit,
                 pair_symbol,
                 None,
-                interval.cloned(),
+                Some(interval.to_string()),
+                indicators,
             )
             .await
         })
         // Endpoint: /api/v1/rebalance/:token/:wallet_address",
         .get_async(
             "/api/v1/rebalance/:token/:wallet_address",
-            |_req, ctx| async move {
-                let pair_symbol = match ctx.param("token") {
-                    Some(token) => token.to_owned(),
-                    None => return Response::error("Bad Request - Missing Token", 400),
+            |req, ctx| async move {
+                let pair_symbol = match parse_pair_symbol(ctx.param("token")) {
+                    Ok(pair_symbol) => pair_symbol,
+                    Err(error) => return Response::error(error, 400),
+                };
+                let indicators = match parse_indicator_config(&req) {
+                    Ok(indicators) => indicators,
+                    Err(error) => return Response::error(error, 400),
                 };
                 let maybe_wallet_address = ctx.param("wallet_address").cloned();
                 handle_prediction_request(
                     PredictionType::Rebalance,
                     gemini_api_key,
+                    market_source,
+                    price_oracle,
                     orderbook_limit,
                     pair_symbol,
                     maybe_wallet_address,
                     None,
+                    indicators,
                 )
                 .await
             },
@@ -245,7 +378,9 @@ async fn fetch(req: Request, env: Env, _ctx: worker::Context) -> Result<Response
         .await
 }
 
-pub async fn predict_with_gemini(
+pub async fn predict_with_gemini<S: MarketDataSource, O: PriceOracle>(
+    source: &S,
+    oracle: &O,
     // Accept the PredictionRequest struct directly
     request: PredictionRequest,
 ) -> anyhow::Result<String, String> {
@@ -258,14 +393,13 @@ pub async fn predict_with_gemini(
     };
 
     let provider = GeminiProvider::new_v1beta(&request.gemini_api_key);
-    let (token_symbol, _) = get_token_and_pair_symbol_usdt(&request.pair_symbol);
+    let token_symbol = request.pair_symbol.base.clone();
 
     // Get price
-    // TODO: more oracle?
-    let kline_data_1s = fetch_binance_kline_usdt::<ConciseKline>(&request.pair_symbol, "1s", 1)
+    let oracle_price = oracle
+        .fetch_price(&request.pair_symbol.to_string())
         .await
-        .expect("Failed to get price.");
-    let current_price = kline_data_1s[0].close;
+        .map_err(|e| format!("Failed to get price: {e}"))?;
 
     // Get position from wallet_address if provided
     let maybe_preps_positions = None;
@@ -307,20 +441,26 @@ pub async fn predict_with_gemini(
         token_symbol,
         pair_symbol: request.pair_symbol, // Move pair_symbol from request
         interval,
-        current_price,
+        current_price: oracle_price.price,
+        maybe_oracle_price: Some(oracle_price),
         maybe_preps_positions,
         maybe_trading_predictions: request.trading_predictions, // Move trading_predictions from request
         kline_intervals,
         stoch_rsi_intervals,
         latest_bb_ma_intervals,
+        data_quality: Vec::new(),
+        maybe_derivatives: None,
     };
 
     // Use request fields for get_binance_prompt
     let base_prompt = get_binance_prompt(
+        source,
         &request.prediction_type,
         &gemini_model,
         context.clone(),
         request.orderbook_limit,
+        &request.quality,
+        &request.indicators,
     )
     .await
     .map_err(|e| e.to_string())?;
@@ -390,7 +530,7 @@ mod tests {
         let result = PredictionRequestBuilder::new(
             PredictionType::Trading,
             gemini_api_key,
-            pair_symbol.to_string(),
+            pair_symbol.parse().unwrap(),
             1000,
         )
         .wallet_address(wallet_address) // Set wallet address if available
@@ -414,7 +554,7 @@ mod tests {
         let result = PredictionRequestBuilder::new(
             PredictionType::Graph,
             gemini_api_key,
-            pair_symbol.to_string(),
+            pair_symbol.parse().unwrap(),
             1000,
         )
         // No optional fields set here
@@ -450,7 +590,7 @@ mod tests {
         let result = PredictionRequestBuilder::new(
             PredictionType::Graph,
             gemini_api_key,
-            pair_symbol.to_string(),
+            pair_symbol.parse().unwrap(),
             1000,
         )
         .interval(Some("1h".to_string())) // Set custom interval
@@ -2,21 +2,65 @@ use super::prediction_types::PredictionType;
 use crate::providers::{gemini::GeminiModel, prompter::build_prompt};
 use anyhow::Context;
 use common::{
-    binance::fetch_orderbook_depth_usdt,
+    indicator_config::IndicatorConfig,
+    market::MarketDataSource,
+    quality::QualityConfig,
     transforms::csv::PriceHistoryBuilder, // Keep builder
     TradingContext,
 };
 // Removed: Kline, klines_to_csv, HashMap
 
-pub async fn get_binance_prompt(
+// 1h is resampled from 15m and 1d from 4h, so the default report takes two fetches instead of four.
+const BASE_INTERVALS: [&str; 2] = ["15m", "4h"];
+// The prediction can't go ahead without these, other intervals are left out of the prompt on failure.
+const REQUIRED_INTERVALS: [&str; 2] = ["15m", "1h"];
+// Taker flow over the last two hours and the last six, trades are too heavy for longer windows.
+const ORDER_FLOW_INTERVALS: [&str; 2] = ["15m:8", "1h:6"];
+// One trade fetch shared by both intervals, two aggTrades requests at most. On busy pairs that's
+// the last few minutes, the oldest candle read is then flagged as partly covered.
+const ORDER_FLOW_MAX_TRADES: usize = 2_000;
+// Taker buy ratio and trade count spikes over the last day.
+const TAKER_PRESSURE_INTERVALS: [&str; 1] = ["1h:24"];
+// EMA(9/21) and MACD(12/26/9) crosses the instructions trade on, the last day of 15m up to 4h.
+const EMA_INTERVALS: [&str; 3] = ["15m:24", "1h:24", "4h:24"];
+const MACD_INTERVALS: [&str; 3] = ["15m:24", "1h:24", "4h:24"];
+// ATR for the volatility spikes and 1.5x ATR stop buffers, on the intervals the instructions name.
+const ATR_INTERVALS: [&str; 2] = ["4h:24", "1d:14"];
+// Swings and Fibonacci levels over the last two weeks at 4h and the last quarter daily.
+const FIBONACCI_INTERVALS: [&str; 2] = ["4h:84", "1d:90"];
+// Support/resistance pivots over the last week hourly, two weeks at 4h and the last quarter daily.
+const SUPPORT_RESISTANCE_INTERVALS: [&str; 3] = ["1h:168", "4h:84", "1d:90"];
+// Candlestick patterns of the last half day hourly, two days at 4h and the last week daily.
+const PATTERN_INTERVALS: [&str; 3] = ["1h:12", "4h:12", "1d:7"];
+// Divergences the instructions weight, ending in the last day of 15m/1h and the last week at 4h.
+const DIVERGENCE_INTERVALS: [&str; 3] = ["15m:96", "1h:24", "4h:42"];
+// Perps positioning over the last day, hourly, and the last week at 4h.
+const DERIVATIVES_PERIODS: [&str; 2] = ["1h:24", "4h:42"];
+
+pub async fn get_binance_prompt<S: MarketDataSource>(
+    source: &S,
     prediction_type: &PredictionType,
     model: &GeminiModel,
-    context: TradingContext,
+    mut context: TradingContext,
     orderbook_limit: i32,
+    quality: &QualityConfig,
+    indicators: &IndicatorConfig,
 ) -> anyhow::Result<String> {
+    let pair_symbol = context.pair_symbol.to_string();
+
+    // --- Fetch Orderbook ---
+    // Fetched first so the support/resistance zones are clustered from the same book.
+    println!("Fetching order book data...");
+    let orderbook = source
+        .fetch_orderbook(&pair_symbol, orderbook_limit)
+        .await
+        .context("Failed to fetch orderbook depth")?;
+
     // --- Fetch Data and Build Report String using Builder ---
     println!("Fetching historical data and building report string...");
-    let builder = PriceHistoryBuilder::new(&context.pair_symbol, 100)
+    let builder = PriceHistoryBuilder::from_source(source, &pair_symbol, 100)
+        .with_base_intervals(&BASE_INTERVALS)
+        .with_required_intervals(&REQUIRED_INTERVALS)
         .with_klines(
             context
                 .kline_intervals
@@ -40,20 +84,29 @@ pub async fn get_binance_prompt(
                 .map(String::as_str)
                 .collect::<Vec<_>>()
                 .as_slice(),
-        );
+        )
+        .with_ema(&EMA_INTERVALS)
+        .with_macd(&MACD_INTERVALS)
+        .with_atr(&ATR_INTERVALS)
+        .with_fibonacci(&FIBONACCI_INTERVALS)
+        .with_support_resistance(&SUPPORT_RESISTANCE_INTERVALS)
+        .with_orderbook(orderbook.clone())
+        .with_patterns(&PATTERN_INTERVALS)
+        .with_divergences(&DIVERGENCE_INTERVALS)
+        .with_taker_pressure(&TAKER_PRESSURE_INTERVALS)
+        .with_order_flow(&ORDER_FLOW_INTERVALS)
+        .with_max_trades(ORDER_FLOW_MAX_TRADES)
+        .with_derivatives(&DERIVATIVES_PERIODS)
+        .with_indicator_config(indicators.clone())
+        .with_quality_check(quality.clone());
 
-    // Get the full report string from the builder
-    let historical_data_content: String = builder
-        .build()
+    // Get the full report string from the builder, stale or broken data is refused here.
+    // Also fills in the data quality and derivatives positioning on the context.
+    let historical_data_content = builder
+        .build_with_context(&mut context)
         .await
         .context("Failed to build historical data report string using builder")?;
 
-    // --- Fetch Orderbook ---
-    println!("Fetching order book data...");
-    let orderbook = fetch_orderbook_depth_usdt(&context.pair_symbol, orderbook_limit)
-        .await
-        .context("Failed to fetch orderbook depth")?;
-
     // --- Build Prompt ---
     println!("Building prompt for Gemini API...");
     // Pass the processed report string directly
@@ -2,7 +2,7 @@ use crate::predictions::prediction_types::PredictionType;
 
 pub const PREFIX_INSTRUCTION: &str = r#"
 - Perform technical analysis on price histories (5m, 15m, 1h, 4h, 1d) and order book volume:
-  - Use 5m, 15m, and 1h for short-term signals (intraday focus). Weight 5m higher for early reversal detection (e.g., bullish divergences); prioritize 15m for sustained momentum shifts confirmed by volume or price action.
+  - Use 5m, 15m, and 1h for short-term signals (intraday focus). Weight 5m higher for early reversal detection; prioritize 15m for sustained momentum shifts and divergences (see Divergences, 15m and up: regular ones signal reversals, hidden ones continuation, weight by strength) confirmed by volume or price action.
   - Use 4h and 1d to confirm broader trends or detect weekly patterns; weight 4h/1d higher if volume exceeds 1.5x 10-period average or short-term signals (5m, 15m, 1h) align, reducing reliance on contradictory short-term signals.
 - Detect momentum and reversals with key indicators:
   - Bullish: Stochastic RSI <30 (or rising from <20), price near or below lower Bollinger Band, rising bid volume >1.2x ask volume, EMA (9) crosses above EMA (21), or MACD line crosses above signal line.
@@ -11,8 +11,8 @@ pub const PREFIX_INSTRUCTION: &str = r#"
   - Bullish: Target 61.8%, 100%, or 161.8% extension above recent swing high if momentum confirms; consider 38.2% retracement as support for entries.
   - Bearish: Target 61.8%, 100%, or 161.8% retracement below recent swing low if volume supports; consider 38.2% extension as resistance for entries.
 - Analyze bid/ask volume and price action across all intervals:
-  - Bullish signals: Bids outpace asks by >1.2x, price-volume divergence supports upside, or buying volume spikes at support.
-  - Bearish signals: Asks outpace bids by >1.2x, price-volume divergence supports downside, or selling volume spikes at resistance.
+  - Bullish signals: Bids outpace asks by >1.2x, price-volume divergence supports upside (volume rows of Divergences), or buying volume spikes at support.
+  - Bearish signals: Asks outpace bids by >1.2x, price-volume divergence supports downside (volume rows of Divergences), or selling volume spikes at resistance.
 - Account for weekly cycles and news events:
   - Increase confidence (+0.15) for bullish signals on historically strong days (e.g., Wednesday, Monday) or post-news spikes (e.g., 8:00 PM GMT+0) if price action confirms.
   - Decrease confidence (-0.1) for trades against weekly slowdowns (e.g., Friday to Sunday) unless short-term volume >1.5x average or 5m/15m indicators strongly align.
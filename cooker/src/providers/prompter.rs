@@ -1,5 +1,4 @@
 use chrono::Utc;
-use common::binance::get_token_and_pair_symbol_usdt;
 use common::OrderBook;
 use common::TradingContext;
 
@@ -28,9 +27,42 @@ pub fn build_prompt<T>(
     let current_datetime = now_utc.format("%Y-%m-%dT%H:%M:%SZ").to_string();
     let current_timestamp = now_utc.timestamp_millis();
 
-    // Handle binance_pair_symbol
-    let pair_symbol = context.pair_symbol.clone();
-    let (token_symbol, _binance_pair_symbol) = get_token_and_pair_symbol_usdt(&pair_symbol); // Use _ if binance_pair_symbol not needed directly here
+    // Price provenance
+    let current_price_source = match &context.maybe_oracle_price {
+        Some(oracle_price) => {
+            let age = oracle_price.age_seconds(now_utc.timestamp());
+            match oracle_price.confidence {
+                Some(confidence) => format!(
+                    "{} (age={age}s, confidence=±{confidence})",
+                    oracle_price.source
+                ),
+                None => format!("{} (age={age}s)", oracle_price.source),
+            }
+        }
+        None => "unknown".to_string(),
+    };
+
+    // Data quality of the historical data, e.g. "1h: 168 candles, ok; 4h: 84 candles, ok"
+    let data_quality = if context.data_quality.is_empty() {
+        "unchecked".to_string()
+    } else {
+        context
+            .data_quality
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+
+    // Perps positioning, e.g. "funding_rate=0.0100% (avg 0.0080%), ..., crowding=neutral"
+    let derivatives = match &context.maybe_derivatives {
+        Some(snapshot) => snapshot.to_string(),
+        None => "unavailable".to_string(),
+    };
+
+    // Pair
+    let pair_symbol = context.pair_symbol.to_string();
+    let token_symbol = context.pair_symbol.base.clone();
 
     // Order Book Processing
     let (grouped_one_bids, grouped_one_asks) =
@@ -73,6 +105,9 @@ token_symbol={token_symbol}
 current_datetime={current_datetime}
 current_timestamp={current_timestamp}
 current_price={current_price}
+current_price_source={current_price_source}
+data_quality={data_quality}
+derivatives={derivatives}
 
 ## Open Positions:
 {maybe_preps_positions_string}
@@ -107,10 +142,10 @@ mod tests {
     use crate::providers::gemini::GeminiModel; // Assuming GeminiModel is defined elsewhere
     use anyhow::{Context as AnyhowContext, Result}; // Add alias for Context trait
     use common::{
-        binance::{fetch_binance_kline_usdt, fetch_orderbook_depth_usdt},
+        binance::fetch_orderbook_depth_usdt,
         jup::get_preps_position,
+        oracle::{FallbackOracle, PriceOracle},
         transforms::csv::PriceHistoryBuilder, // Import the builder
-        ConciseKline,
         TradingContext,
     };
     use std::env;
@@ -151,14 +186,9 @@ mod tests {
         let pair_symbol = format!("{token_symbol}_USDT");
         let interval = "1h".to_string(); // Example interval for context (instruction generation)
 
-        // Fetch 1-second kline data to get current price
+        // Fetch current price from the oracles
         println!("Fetching current price for {pair_symbol}...");
-        let kline_data_1s =
-            fetch_binance_kline_usdt::<ConciseKline>(&token_symbol, "1s", 1).await?;
-        let current_price = kline_data_1s
-            .first()
-            .map(|k| k.close)
-            .ok_or("Failed to get current price from 1s kline data")?; // Ensure price is available
+        let oracle_price = FallbackOracle::default().fetch_price(&pair_symbol).await?;
 
         // Load environment variables from .env file
         dotenvy::dotenv().ok(); // Load .env
@@ -176,14 +206,17 @@ mod tests {
         // Context
         let context = TradingContext {
             token_symbol: token_symbol.clone(),
-            pair_symbol: pair_symbol.clone(), // Builder uses this pair_symbol
+            pair_symbol: pair_symbol.parse()?, // Builder uses this pair_symbol
             interval: interval.clone(),
-            current_price,
+            current_price: oracle_price.price,
+            maybe_oracle_price: Some(oracle_price),
             maybe_preps_positions,
             maybe_trading_predictions: None,
             kline_intervals: ["1h:24".to_string()].to_vec(),
             stoch_rsi_intervals: ["4h".to_string()].to_vec(),
             latest_bb_ma_intervals: ["1h".to_string(), "4h".to_string()].to_vec(),
+            data_quality: Vec::new(),
+            maybe_derivatives: None,
         };
 
         // --- Generate historical data using PriceHistoryBuilder ---
@@ -262,24 +295,22 @@ mod tests {
 
         // Fetch current price
         println!("Fetching current price for {binance_pair_symbol}...");
-        let kline_data_1s =
-            fetch_binance_kline_usdt::<ConciseKline>(&binance_pair_symbol, "1s", 1).await?;
-        let current_price = kline_data_1s
-            .first()
-            .map(|k| k.close)
-            .ok_or("Failed to get current price from 1s kline data")?;
+        let oracle_price = FallbackOracle::default().fetch_price(&pair_symbol).await?;
 
         // Context (no positions needed/fetched for graph prediction example)
         let context = TradingContext {
             token_symbol: token_symbol.clone(),
-            pair_symbol: pair_symbol.clone(), // Builder uses this
+            pair_symbol: pair_symbol.parse()?, // Builder uses this
             interval: interval.clone(),
-            current_price,
+            current_price: oracle_price.price,
+            maybe_oracle_price: Some(oracle_price),
             maybe_preps_positions: None, // Explicitly None for this test
             maybe_trading_predictions: None,
             kline_intervals: ["1h:24".to_string()].to_vec(),
             stoch_rsi_intervals: ["4h".to_string()].to_vec(),
             latest_bb_ma_intervals: ["1h".to_string(), "4h".to_string()].to_vec(),
+            data_quality: Vec::new(),
+            maybe_derivatives: None,
         };
 
         // --- Generate historical data using PriceHistoryBuilder ---
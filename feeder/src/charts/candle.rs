@@ -5,9 +5,12 @@ use super::helpers::get_visible_range_and_data;
 use super::helpers::parse_kline_time;
 use super::image::draw_dashed_line_segment_mut;
 use super::indicators::draw_bollinger_detail;
+use super::indicators::draw_divergences_detail;
+use super::indicators::draw_fibonacci_detail;
 use super::indicators::draw_macd_detail;
 use super::indicators::draw_orderbook;
 use super::indicators::draw_past_signals;
+use super::indicators::draw_patterns_detail;
 use super::indicators::draw_stoch_rsi_detail;
 use super::indicators::draw_volume_detail;
 use super::labels::draw_hallow_label;
@@ -23,7 +26,8 @@ use chrono::DateTime;
 use chrono::TimeZone;
 use chrono::Utc;
 use chrono_tz::Tz;
-use common::Kline;
+use common::indicator_config::IndicatorConfig;
+use common::Candle;
 use common::LongShortSignal;
 use common::OrderBook;
 use image::ImageBuffer;
@@ -65,8 +69,8 @@ pub struct ChartMetaData {
 pub struct Chart {
     pub timezone: Tz,
     pub interval: String,
-    pub past_candle_data: Option<Vec<Kline>>,
-    pub predicted_candle: Option<Vec<Kline>>,
+    pub past_candle_data: Option<Vec<Candle>>,
+    pub predicted_candle: Option<Vec<Candle>>,
     pub metadata: ChartMetaData,
     pub font_data: Option<Vec<u8>>,
     pub points: Vec<(f32, f32)>,
@@ -78,8 +82,13 @@ pub struct Chart {
     pub label_style: Option<LabelStyle>,
     pub macd_enabled: bool,
     pub bollinger_enabled: bool,
+    pub fibonacci_enabled: bool,
+    pub support_resistance_enabled: bool,
+    pub patterns_enabled: bool,
+    pub divergences_enabled: bool,
     pub volume_enabled: bool,
     pub stoch_rsi_enabled: bool,
+    pub indicators: IndicatorConfig,
     pub signals: Option<Vec<LongShortSignal>>,
     pub past_signals: Option<Vec<LongShortSignal>>,
 }
@@ -93,13 +102,13 @@ impl Chart {
         }
     }
 
-    pub fn with_past_candle(mut self, past_candle_data: Vec<Kline>) -> Self {
+    pub fn with_past_candle(mut self, past_candle_data: Vec<Candle>) -> Self {
         self.past_candle_data = Some(past_candle_data);
         self
     }
 
     #[allow(dead_code)]
-    pub fn with_predicted_candle(mut self, predicted_candle: Vec<Kline>) -> Self {
+    pub fn with_predicted_candle(mut self, predicted_candle: Vec<Candle>) -> Self {
         self.predicted_candle = Some(predicted_candle);
         self
     }
@@ -186,6 +195,34 @@ impl Chart {
         self
     }
 
+    /// Draws the Fibonacci levels of the latest swing leg, swings need a reversal of
+    /// the config's `swing_threshold_pct`.
+    pub fn with_fibonacci(mut self) -> Self {
+        self.fibonacci_enabled = true;
+        self
+    }
+
+    /// Draws support/resistance zones from the swing pivots of the candles and the order book,
+    /// when one is set.
+    pub fn with_support_resistance(mut self) -> Self {
+        self.support_resistance_enabled = true;
+        self
+    }
+
+    /// Marks candlestick patterns on the candles completing them and names the latest ones.
+    pub fn with_patterns(mut self) -> Self {
+        self.patterns_enabled = true;
+        self
+    }
+
+    /// Connects the swings of the latest divergences between price and RSI, Stoch RSI, the MACD
+    /// histogram or volume, and names them. Swings need a reversal of the config's
+    /// `divergence_swing_pct`.
+    pub fn with_divergences(mut self) -> Self {
+        self.divergences_enabled = true;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_volume(mut self) -> Self {
         self.volume_enabled = true;
@@ -198,6 +235,13 @@ impl Chart {
         self
     }
 
+    /// Periods of the drawn indicators, pass the config the prompt was built with so both
+    /// show the same numbers.
+    pub fn with_indicator_config(mut self, indicators: IndicatorConfig) -> Self {
+        self.indicators = indicators;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_past_signals(mut self, past_signals: Vec<LongShortSignal>) -> Self {
         self.past_signals = Some(past_signals);
@@ -213,11 +257,11 @@ impl Chart {
     #[allow(clippy::type_complexity)]
     fn get_visible_time_range(
         &self,
-        all_candles: &[Kline],
+        all_candles: &[Candle],
         timezone: &Tz,
         candle_width: u32,
         chart_width: u32,
-    ) -> Result<(DateTime<Tz>, DateTime<Tz>, Vec<Kline>), Box<dyn Error>> {
+    ) -> Result<(DateTime<Tz>, DateTime<Tz>, Vec<Candle>), Box<dyn Error>> {
         let (start_visible, end_visible, visible_candles) =
             get_visible_range_and_data(all_candles, timezone, candle_width, chart_width)?;
 
@@ -234,7 +278,7 @@ impl Chart {
         &self,
         img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
         font: &FontArc,
-        visible_candles: &[Kline],
+        visible_candles: &[Candle],
         start_visible: DateTime<Tz>,
         end_visible: DateTime<Tz>,
         min_price: f32,
@@ -250,8 +294,8 @@ impl Chart {
         let mut highest_price_time: i64 = 0;
 
         for kline in visible_candles.iter() {
-            let low = kline.low_price.parse::<f32>().unwrap();
-            let high = kline.high_price.parse::<f32>().unwrap();
+            let low = kline.low as f32;
+            let high = kline.high as f32;
 
             if low < lowest_price {
                 lowest_price = low;
@@ -421,7 +465,7 @@ impl Chart {
         let timezone = &self.timezone;
 
         let mut all_candles = self.past_candle_data.clone().unwrap();
-        let last_candle = all_candles.last().expect("No data").clone();
+        let last_candle = *all_candles.last().ok_or("Candle data set is empty")?;
         let last_past_time = if let Some(predicted_candles) = self.predicted_candle.clone() {
             all_candles.extend(predicted_candles);
             all_candles
@@ -431,7 +475,7 @@ impl Chart {
         } else {
             last_candle.close_time
         };
-        let current_price = last_candle.close_price.parse::<f64>().expect("No data");
+        let current_price = last_candle.close;
 
         let past_candles = self.past_candle_data.as_deref().unwrap_or(&[]);
 
@@ -453,14 +497,7 @@ impl Chart {
 
         let prices: Vec<f32> = all_candles
             .iter()
-            .flat_map(|k| {
-                vec![
-                    k.open_price.parse::<f32>().unwrap(),
-                    k.high_price.parse::<f32>().unwrap(),
-                    k.low_price.parse::<f32>().unwrap(),
-                    k.close_price.parse::<f32>().unwrap(),
-                ]
-            })
+            .flat_map(|k| vec![k.open as f32, k.high as f32, k.low as f32, k.close as f32])
             .collect();
         let min_price = prices.iter().fold(f32::INFINITY, |a, &b| a.min(b));
         let max_price = prices.iter().fold(f32::NEG_INFINITY, |a, &b| a.max(b));
@@ -520,7 +557,13 @@ impl Chart {
         // RSI
         let mut current_y = 10.0;
         if self.stoch_rsi_enabled {
-            draw_stoch_rsi_detail(&mut cropped_img, past_candles, &font, current_y)?;
+            draw_stoch_rsi_detail(
+                &mut cropped_img,
+                past_candles,
+                &font,
+                current_y,
+                &self.indicators.stoch_rsi,
+            )?;
             current_y += root_height as f32 * 0.25
         }
 
@@ -562,7 +605,53 @@ impl Chart {
 
         draw_candle_detail(&mut cropped_img, &self, &font, root_height as f32 * 0.225)?;
         if self.bollinger_enabled {
-            draw_bollinger_detail(&mut cropped_img, past_candles, &font, current_y)?;
+            draw_bollinger_detail(
+                &mut cropped_img,
+                past_candles,
+                &font,
+                current_y,
+                &self.indicators,
+            )?;
+        }
+        if self.fibonacci_enabled {
+            // Below the MA and BB lines of the Bollinger readout.
+            let readout_lines = if self.bollinger_enabled {
+                self.indicators.moving_averages.len() + 1
+            } else {
+                0
+            };
+            draw_fibonacci_detail(
+                &mut cropped_img,
+                past_candles,
+                &font,
+                current_y + readout_lines as f32 * 25.0,
+                self.indicators.swing_threshold_pct,
+            )?;
+        }
+        if self.patterns_enabled {
+            draw_patterns_detail(
+                &mut cropped_img,
+                past_candles,
+                &font,
+                timezone,
+                chart_width as f32 - 8.0,
+                current_y,
+                3,
+            )?;
+        }
+        if self.divergences_enabled {
+            // Below the pattern readout.
+            let readout_lines = if self.patterns_enabled { 3 } else { 0 };
+            draw_divergences_detail(
+                &mut cropped_img,
+                past_candles,
+                &font,
+                timezone,
+                chart_width as f32 - 8.0,
+                current_y + readout_lines as f32 * 25.0,
+                3,
+                &self.indicators,
+            )?;
         }
 
         if self.volume_enabled || self.macd_enabled {
@@ -581,7 +670,13 @@ impl Chart {
                 current_y += section_height;
             }
             if self.macd_enabled {
-                draw_macd_detail(&mut cropped_img, past_candles, &font, current_y + 10.0)?;
+                draw_macd_detail(
+                    &mut cropped_img,
+                    past_candles,
+                    &font,
+                    current_y + 10.0,
+                    &self.indicators.macd,
+                )?;
             }
         }
 
@@ -636,8 +731,8 @@ impl Chart {
     #[allow(clippy::too_many_arguments, unused)]
     fn draw_candles(
         &self,
-        all_candles: &[Kline],
-        past_candles: &[Kline],
+        all_candles: &[Candle],
+        past_candles: &[Candle],
         timezone: &Tz,
         min_price: f32,
         max_price: f32,
@@ -704,7 +799,7 @@ pub fn draw_candlesticks<F>(
         BitMapBackend<'_>,
         Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
     >,
-    candle_data: &[Kline],
+    candle_data: &[Candle],
     timezone: &Tz,
     color_selector: F,
     last_past_time: i64,
@@ -722,10 +817,10 @@ where
 
     chart.draw_series(candle_data.iter().map(|k| {
         let time = parse_kline_time(k.open_time - 6000 * 60 * 3, timezone);
-        let open = k.open_price.parse::<f32>().unwrap();
-        let high = k.high_price.parse::<f32>().unwrap();
-        let low = k.low_price.parse::<f32>().unwrap();
-        let close = k.close_price.parse::<f32>().unwrap();
+        let open = k.open as f32;
+        let high = k.high as f32;
+        let low = k.low as f32;
+        let close = k.close as f32;
         let is_bullish = close >= open;
         let is_predicted = last_past_time < k.open_time;
         let color = color_selector(is_bullish, is_predicted);
@@ -753,10 +848,10 @@ pub fn draw_candle_detail(
 ) -> Result<(), Box<dyn Error>> {
     if let Some(past_candle_data) = &chart.past_candle_data {
         let latest_candle = past_candle_data.last().unwrap();
-        let open = latest_candle.open_price.parse::<f32>().unwrap();
-        let high = latest_candle.high_price.parse::<f32>().unwrap();
-        let low = latest_candle.low_price.parse::<f32>().unwrap();
-        let close = latest_candle.close_price.parse::<f32>().unwrap();
+        let open = latest_candle.open as f32;
+        let high = latest_candle.high as f32;
+        let low = latest_candle.low as f32;
+        let close = latest_candle.close as f32;
         let change = (close - open) / open * 100.0;
         let candle_detail = format!(
             "{} {} O {:.2} H {:.2} L {:.2} C {:.2} {} ({:.2}%)",
@@ -800,9 +895,10 @@ mod test {
         let font_data = include_bytes!("../../RobotoMono-Regular.ttf").to_vec();
 
         let limit = 24 * 10;
-        let candle_data = fetch_binance_kline_usdt::<Kline>(&binance_pair_symbol, &interval, limit)
-            .await
-            .unwrap();
+        let candle_data =
+            fetch_binance_kline_usdt::<Candle>(&binance_pair_symbol, &interval, limit)
+                .await
+                .unwrap();
 
         let orderbook = fetch_orderbook_depth_usdt(&binance_pair_symbol, 2000)
             .await
@@ -814,13 +910,13 @@ mod test {
             let last_minus_10_candle = &candle_data[candle_data.len() - 11];
 
             let long_entry_time = last_minus_10_candle.open_time;
-            let long_entry_price = last_minus_10_candle.close_price.parse::<f64>().unwrap();
+            let long_entry_price = last_minus_10_candle.close;
             let long_target_time = last_candle.open_time;
-            let long_target_price = last_candle.close_price.parse::<f64>().unwrap();
+            let long_target_price = last_candle.close;
 
             past_signals.push(LongShortSignal {
                 predicted: PredictedLongShortSignal {
-                    pair_symbol: pair_symbol.to_owned(),
+                    pair_symbol: pair_symbol.parse().unwrap(),
                     direction: "long".to_string(),
                     confidence: 0.85,
                     entry_price: long_entry_price,
@@ -850,13 +946,13 @@ mod test {
             let last_minus_20_candle = &candle_data[candle_data.len() - 21];
 
             let short_entry_time = last_minus_30_candle.open_time;
-            let short_entry_price = last_minus_30_candle.close_price.parse::<f64>().unwrap();
+            let short_entry_price = last_minus_30_candle.close;
             let short_target_time = last_minus_20_candle.open_time;
-            let short_target_price = last_minus_20_candle.close_price.parse::<f64>().unwrap();
+            let short_target_price = last_minus_20_candle.close;
 
             past_signals.push(LongShortSignal {
                 predicted: PredictedLongShortSignal {
-                    pair_symbol: pair_symbol.to_owned(),
+                    pair_symbol: pair_symbol.parse().unwrap(),
                     direction: "short".to_string(),
                     confidence: 0.82,
                     entry_price: short_entry_price,
@@ -899,7 +995,7 @@ mod test {
         let mut signals = Vec::new();
         if !candle_data.is_empty() {
             let last_candle = &candle_data[candle_data.len() - 1];
-            let last_close_price = last_candle.close_price.parse::<f64>().unwrap();
+            let last_close_price = last_candle.close;
             let last_time = last_candle.open_time;
             let hour_ms = 3_600_000;
 
@@ -910,7 +1006,7 @@ mod test {
 
             signals.push(LongShortSignal {
                 predicted: PredictedLongShortSignal {
-                    pair_symbol: pair_symbol.to_owned(),
+                    pair_symbol: pair_symbol.parse().unwrap(),
                     direction: "long".to_string(),
                     confidence: 0.9,
                     entry_price: long_entry_price,
@@ -943,7 +1039,7 @@ mod test {
 
             signals.push(LongShortSignal {
                 predicted: PredictedLongShortSignal {
-                    pair_symbol: pair_symbol.to_owned(),
+                    pair_symbol: pair_symbol.parse().unwrap(),
                     direction: "short".to_string(),
                     confidence: 0.87,
                     entry_price: short_entry_price,
@@ -996,6 +1092,10 @@ mod test {
             .with_stoch_rsi()
             .with_orderbook(orderbook)
             .with_bollinger_band()
+            .with_fibonacci()
+            .with_support_resistance()
+            .with_patterns()
+            .with_divergences()
             .with_signals(signals)
             .build()
             .unwrap();
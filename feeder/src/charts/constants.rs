@@ -13,6 +13,21 @@ pub const BB_LOWER_BOUND: RGBColor = RGBColor(255, 109, 1);
 pub const BB_MIDDLE: RGBColor = RGBColor(255, 185, 2);
 pub const BB_UPPER_BOUND_LABEL: Rgb<u8> = Rgb([34, 150, 243]);
 pub const BB_LOWER_BOUND_LABEL: Rgb<u8> = Rgb([255, 109, 1]);
+// Fibonacci
+pub const FIB_LEG: RGBColor = RGBColor(128, 128, 128);
+pub const FIB_RETRACEMENT: RGBColor = RGBColor(171, 71, 188);
+pub const FIB_EXTENSION: RGBColor = RGBColor(0, 188, 212);
+pub const FIB_RETRACEMENT_LABEL: Rgb<u8> = Rgb([171, 71, 188]);
+pub const FIB_EXTENSION_LABEL: Rgb<u8> = Rgb([0, 188, 212]);
+// Support/Resistance
+pub const SR_SUPPORT: RGBColor = B_GREEN;
+pub const SR_RESISTANCE: RGBColor = B_RED;
+pub const SR_BOOK_CLUSTERS: usize = 5;
+pub const SR_MAX_ZONES: usize = 6;
+// Divergences
+pub const DIVERGENCE_BULLISH: RGBColor = B_GREEN;
+pub const DIVERGENCE_BEARISH: RGBColor = B_RED;
+pub const DIVERGENCE_MARKS: usize = 5;
 // MCAD
 pub const MCAD: RGBColor = RGBColor(34, 150, 243);
 pub const MCAD_SIGNAL: RGBColor = RGBColor(255, 109, 1);
@@ -1,6 +1,6 @@
 use chrono::{DateTime, Duration};
 use chrono_tz::Tz;
-use common::Kline;
+use common::{Candle, Interval};
 use std::error::Error;
 
 pub fn parse_kline_time(timestamp: i64, tz: &Tz) -> DateTime<Tz> {
@@ -9,21 +9,15 @@ pub fn parse_kline_time(timestamp: i64, tz: &Tz) -> DateTime<Tz> {
         .with_timezone(tz)
 }
 
-pub fn parse_interval_duration(interval: &str) -> Duration {
-    let (value, unit) = interval.split_at(interval.len() - 1);
-    let value = value.parse::<i64>().unwrap();
-    match unit {
-        "m" => Duration::minutes(value),
-        "h" => Duration::hours(value),
-        "d" => Duration::days(value),
-        _ => panic!("Unsupported interval unit"),
-    }
+pub fn parse_interval_duration(interval: &str) -> Result<Duration, Box<dyn Error>> {
+    let interval: Interval = interval.parse()?;
+    Ok(Duration::milliseconds(interval.duration_ms()))
 }
 
-type VisibleRange = (DateTime<Tz>, DateTime<Tz>, Vec<Kline>);
+type VisibleRange = (DateTime<Tz>, DateTime<Tz>, Vec<Candle>);
 
 pub fn get_visible_range_and_data(
-    past_data: &[Kline],
+    past_data: &[Candle],
     timezone: &Tz,
     candle_width: u32,
     final_width: u32,
@@ -39,7 +33,7 @@ pub fn get_visible_range_and_data(
     let first_visible_time = parse_kline_time(past_data[start_index].open_time, timezone);
     let last_visible_time = parse_kline_time(past_data[total_candles - 1].open_time, timezone);
 
-    let visible_data: Vec<Kline> = past_data
+    let visible_data: Vec<Candle> = past_data
         .iter()
         .filter(|k| {
             let time = parse_kline_time(k.open_time, timezone);
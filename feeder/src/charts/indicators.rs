@@ -4,12 +4,18 @@ use crate::charts::helpers::parse_interval_duration;
 use ab_glyph::Font;
 use chrono::DateTime;
 use chrono_tz::Tz;
-use common::m4rs::kline_to_m4rs_candlestick;
+use common::divergence::{find_divergences, DivergenceDirection, DivergenceKind};
+use common::fibonacci::{FibKind, FibonacciLevels};
+use common::indicator_config::{BollingerConfig, IndicatorConfig, MacdConfig, StochRsiConfig};
+use common::m4rs::candle_to_m4rs_candlestick;
 use common::numbers::{group_by_fractional_part, FractionalPart};
+use common::patterns::{find_patterns, PatternDirection};
 use common::rsi::{calculate_stoch_rsi, get_latest_bb_ma};
-use common::{Kline, LongShortSignal, OrderBook};
+use common::support_resistance::{book_clusters, find_zones, pivots_from_candles, ZoneKind};
+use common::swings::find_swings;
+use common::{Candle, LongShortSignal, OrderBook};
 use image::{ImageBuffer, Rgb};
-use imageproc::drawing::draw_line_segment_mut;
+use imageproc::drawing::{draw_line_segment_mut, text_size};
 use imageproc::rect::Rect;
 use m4rs::{bolinger_band, macd, Candlestick as M4rsCandlestick};
 use plotters::coord::types::RangedCoordf32;
@@ -28,8 +34,9 @@ pub fn draw_bollinger_bands(
         BitMapBackend<'_>,
         Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
     >,
-    klines: &[Kline],
+    klines: &[Candle],
     timezone: &Tz,
+    config: &BollingerConfig,
 ) -> Result<(f32, f32), Box<dyn Error>> {
     if klines.is_empty() {
         // Handle empty case: return an error or default bounds
@@ -37,15 +44,15 @@ pub fn draw_bollinger_bands(
     }
 
     let past_m4rs_candles: Vec<M4rsCandlestick> =
-        klines.iter().map(kline_to_m4rs_candlestick).collect();
-    let past_bb_result = bolinger_band(&past_m4rs_candles, 20)?;
+        klines.iter().map(candle_to_m4rs_candlestick).collect();
+    let past_bb_result = bolinger_band(&past_m4rs_candles, config.period)?;
     let past_bb_lines: Vec<(DateTime<Tz>, f32, f32, f32)> = past_bb_result
         .iter()
         .map(|entry| {
             let t = parse_kline_time(entry.at as i64, timezone);
             let avg = entry.avg as f32;
-            let upper = (entry.avg + 2.0 * entry.sigma) as f32;
-            let lower = (entry.avg - 2.0 * entry.sigma) as f32;
+            let upper = (entry.avg + config.std_dev * entry.sigma) as f32;
+            let lower = (entry.avg - config.std_dev * entry.sigma) as f32;
             (t, avg, upper, lower)
         })
         .collect();
@@ -79,12 +86,13 @@ pub fn draw_bollinger_bands(
 
 pub fn draw_bollinger_detail(
     img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    klines: &[Kline],
+    klines: &[Candle],
     font: &impl Font,
     y: f32,
+    config: &IndicatorConfig,
 ) -> Result<(), Box<dyn Error>> {
     if !klines.is_empty() {
-        let ma_bb_detail = get_latest_bb_ma(klines)?;
+        let ma_bb_detail = get_latest_bb_ma(klines, config)?;
         let mut y_offset = y;
         for line in ma_bb_detail.lines() {
             draw_label(
@@ -103,18 +111,323 @@ pub fn draw_bollinger_detail(
     Ok(())
 }
 
+/// Draws the leg between the latest two swings and its Fibonacci levels as dashed lines from
+/// the start of the leg to `last_time`. Draws nothing until there are two swings.
+pub fn draw_fibonacci_levels(
+    chart: &mut ChartContext<
+        '_,
+        BitMapBackend<'_>,
+        Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
+    >,
+    klines: &[Candle],
+    timezone: &Tz,
+    last_time: DateTime<Tz>,
+    threshold_pct: f64,
+) -> Result<(), Box<dyn Error>> {
+    let swings = find_swings(klines, threshold_pct);
+    let Ok(fibonacci) = FibonacciLevels::latest(&swings) else {
+        return Ok(());
+    };
+
+    let start_time = parse_kline_time(fibonacci.start.open_time, timezone);
+    let end_time = parse_kline_time(fibonacci.end.open_time, timezone);
+    chart.draw_series(LineSeries::new(
+        vec![
+            (start_time, fibonacci.start.price as f32),
+            (end_time, fibonacci.end.price as f32),
+        ],
+        ShapeStyle::from(&FIB_LEG).stroke_width(1),
+    ))?;
+
+    for level in &fibonacci.levels {
+        let color = match level.kind {
+            FibKind::Retracement => FIB_RETRACEMENT,
+            FibKind::Extension => FIB_EXTENSION,
+        };
+        let price = level.price as f32;
+        chart.draw_series(DashedLineSeries::new(
+            vec![(start_time, price), (last_time, price)],
+            5,
+            5,
+            ShapeStyle::from(&color).stroke_width(1),
+        ))?;
+    }
+    Ok(())
+}
+
+/// Readout of the Fibonacci levels drawn by `draw_fibonacci_levels`, e.g. "FIB 0.618 138.20".
+pub fn draw_fibonacci_detail(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    klines: &[Candle],
+    font: &impl Font,
+    y: f32,
+    threshold_pct: f64,
+) -> Result<(), Box<dyn Error>> {
+    let swings = find_swings(klines, threshold_pct);
+    let Ok(fibonacci) = FibonacciLevels::latest(&swings) else {
+        return Ok(());
+    };
+
+    let mut y_offset = y;
+    for level in &fibonacci.levels {
+        let color = match level.kind {
+            FibKind::Retracement => FIB_RETRACEMENT_LABEL,
+            FibKind::Extension => FIB_EXTENSION_LABEL,
+        };
+        draw_label(
+            img,
+            font,
+            &format!("FIB {:.3} {:.2}", level.ratio, level.price),
+            10.0,
+            y_offset,
+            LABEL_SCALE,
+            color,
+            Some(TRANSPARENT_BLACK_50),
+        )?;
+        y_offset += 25.0;
+    }
+    Ok(())
+}
+
+/// Draws the strongest support/resistance zones of `klines` and the order book, if any, as
+/// translucent bands across the chart. A zone of a single price is drawn as wide as the
+/// config's zone tolerance.
+pub fn draw_support_resistance_zones(
+    chart: &mut ChartContext<
+        '_,
+        BitMapBackend<'_>,
+        Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
+    >,
+    klines: &[Candle],
+    orderbook: Option<&OrderBook>,
+    first_time: DateTime<Tz>,
+    last_time: DateTime<Tz>,
+    config: &IndicatorConfig,
+) -> Result<(), Box<dyn Error>> {
+    let Some(last_candle) = klines.last() else {
+        return Ok(());
+    };
+    let pivots = pivots_from_candles(klines, config.swing_threshold_pct);
+    let clusters = orderbook
+        .map(|orderbook| book_clusters(orderbook, FractionalPart::One, SR_BOOK_CLUSTERS))
+        .unwrap_or_default();
+    let zones = find_zones(
+        &pivots,
+        &clusters,
+        last_candle.close,
+        config.zone_tolerance_pct,
+        SR_MAX_ZONES,
+    );
+
+    chart.draw_series(zones.iter().map(|zone| {
+        let half_width =
+            (zone.high - zone.low).max(zone.mid() * config.zone_tolerance_pct / 100.0) / 2.0;
+        let color = match zone.kind {
+            ZoneKind::Support => SR_SUPPORT,
+            ZoneKind::Resistance => SR_RESISTANCE,
+        };
+        Rectangle::new(
+            [
+                (first_time, (zone.mid() - half_width) as f32),
+                (last_time, (zone.mid() + half_width) as f32),
+            ],
+            // Stronger zones are more opaque.
+            color.mix(0.1 + 0.3 * zone.score).filled(),
+        )
+    }))?;
+    Ok(())
+}
+
+/// Marks every candlestick pattern of `klines` at the candle completing it: an up triangle
+/// under bullish ones, a down triangle over bearish ones and a dot over neutral ones.
+pub fn draw_pattern_marks(
+    chart: &mut ChartContext<
+        '_,
+        BitMapBackend<'_>,
+        Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
+    >,
+    klines: &[Candle],
+    timezone: &Tz,
+) -> Result<(), Box<dyn Error>> {
+    let events = find_patterns(klines);
+    let marks = events.iter().filter_map(|event| {
+        let candle = klines
+            .iter()
+            .find(|candle| candle.open_time == event.open_time)?;
+        let t = parse_kline_time(event.open_time, timezone);
+        Some((event.direction, t, candle.low as f32, candle.high as f32))
+    });
+
+    let mut bullish = Vec::new();
+    let mut bearish = Vec::new();
+    let mut neutral = Vec::new();
+    for (direction, t, low, high) in marks {
+        match direction {
+            PatternDirection::Bullish => bullish.push((t, low)),
+            PatternDirection::Bearish => bearish.push((t, high)),
+            PatternDirection::Neutral => neutral.push((t, high)),
+        }
+    }
+
+    chart.draw_series(bullish.into_iter().map(|coord| {
+        EmptyElement::at(coord) + Polygon::new(vec![(0, 6), (-5, 14), (5, 14)], B_GREEN.filled())
+    }))?;
+    chart.draw_series(bearish.into_iter().map(|coord| {
+        EmptyElement::at(coord) + Polygon::new(vec![(0, -6), (-5, -14), (5, -14)], B_RED.filled())
+    }))?;
+    chart.draw_series(
+        neutral
+            .into_iter()
+            .map(|coord| EmptyElement::at(coord) + Circle::new((0, -9), 3, WHITE.filled())),
+    )?;
+    Ok(())
+}
+
+/// Names the latest `count` patterns marked by `draw_pattern_marks`, newest first and right
+/// aligned to `right_x`, e.g. "hammer 10-16 08:00".
+pub fn draw_patterns_detail(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    klines: &[Candle],
+    font: &impl Font,
+    timezone: &Tz,
+    right_x: f32,
+    y: f32,
+    count: usize,
+) -> Result<(), Box<dyn Error>> {
+    let events = find_patterns(klines);
+    let mut y_offset = y;
+    for event in events.iter().rev().take(count) {
+        let color = match event.direction {
+            PatternDirection::Bullish => NUM_GREEN,
+            PatternDirection::Bearish => NUM_RED,
+            PatternDirection::Neutral => LABEL_COLOR,
+        };
+        let time = parse_kline_time(event.open_time, timezone).format("%m-%d %H:%M");
+        let text = format!("{} {time}", event.kind);
+        let (text_width, _) = text_size(LABEL_SCALE, font, &text);
+        draw_label(
+            img,
+            font,
+            &text,
+            right_x - text_width as f32,
+            y_offset,
+            LABEL_SCALE,
+            color,
+            Some(TRANSPARENT_BLACK_50),
+        )?;
+        y_offset += 25.0;
+    }
+    Ok(())
+}
+
+/// Connects the swings of the latest `DIVERGENCE_MARKS` divergences, solid for regular and
+/// dashed for hidden ones. A swing pair diverging on several indicators is drawn once.
+pub fn draw_divergence_lines(
+    chart: &mut ChartContext<
+        '_,
+        BitMapBackend<'_>,
+        Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
+    >,
+    klines: &[Candle],
+    timezone: &Tz,
+    config: &IndicatorConfig,
+) -> Result<(), Box<dyn Error>> {
+    let Ok(mut divergences) = find_divergences(klines, config) else {
+        return Ok(());
+    };
+    divergences.dedup_by_key(|divergence| {
+        (
+            divergence.start,
+            divergence.end,
+            divergence.kind,
+            divergence.direction,
+        )
+    });
+
+    for divergence in divergences.iter().rev().take(DIVERGENCE_MARKS) {
+        let color = match divergence.direction {
+            DivergenceDirection::Bullish => DIVERGENCE_BULLISH,
+            DivergenceDirection::Bearish => DIVERGENCE_BEARISH,
+        };
+        let style = ShapeStyle::from(&color).stroke_width(2);
+        let points = vec![
+            (
+                parse_kline_time(divergence.start, timezone),
+                divergence.start_price as f32,
+            ),
+            (
+                parse_kline_time(divergence.end, timezone),
+                divergence.end_price as f32,
+            ),
+        ];
+        match divergence.kind {
+            DivergenceKind::Regular => {
+                chart.draw_series(LineSeries::new(points, style))?;
+            }
+            DivergenceKind::Hidden => {
+                chart.draw_series(DashedLineSeries::new(points, 6, 4, style))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Names the latest `count` divergences, newest first and right aligned to `right_x`,
+/// e.g. "rsi regular bearish 0.44 10-16 08:00" where 0.44 is the strength.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_divergences_detail(
+    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    klines: &[Candle],
+    font: &impl Font,
+    timezone: &Tz,
+    right_x: f32,
+    y: f32,
+    count: usize,
+    config: &IndicatorConfig,
+) -> Result<(), Box<dyn Error>> {
+    let Ok(divergences) = find_divergences(klines, config) else {
+        return Ok(());
+    };
+    let mut y_offset = y;
+    for divergence in divergences.iter().rev().take(count) {
+        let color = match divergence.direction {
+            DivergenceDirection::Bullish => NUM_GREEN,
+            DivergenceDirection::Bearish => NUM_RED,
+        };
+        let time = parse_kline_time(divergence.end, timezone).format("%m-%d %H:%M");
+        let text = format!(
+            "{} {} {} {:.2} {time}",
+            divergence.source, divergence.kind, divergence.direction, divergence.strength
+        );
+        let (text_width, _) = text_size(LABEL_SCALE, font, &text);
+        draw_label(
+            img,
+            font,
+            &text,
+            right_x - text_width as f32,
+            y_offset,
+            LABEL_SCALE,
+            color,
+            Some(TRANSPARENT_BLACK_50),
+        )?;
+        y_offset += 25.0;
+    }
+    Ok(())
+}
+
 pub fn draw_volume_bars(
     chart: &mut ChartContext<
         '_,
         BitMapBackend<'_>,
         Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
     >,
-    maybe_klines: &Option<Vec<Kline>>,
+    maybe_klines: &Option<Vec<Candle>>,
     timezone: &Tz,
     interval: &str,
     last_past_time: i64,
 ) -> Result<(), Box<dyn Error>> {
     if let Some(klines) = maybe_klines {
+        let bar_width = parse_interval_duration(interval)?;
         chart
             .configure_mesh()
             .light_line_style(BLACK)
@@ -123,10 +436,9 @@ pub fn draw_volume_bars(
             .draw()?;
         chart.draw_series(klines.iter().flat_map(|k| {
             let time: DateTime<Tz> = parse_kline_time(k.open_time, timezone);
-            let volume = k.volume.parse::<f32>().unwrap();
-            let bar_width = parse_interval_duration(interval);
-            let open = k.open_price.parse::<f32>().unwrap();
-            let close = k.close_price.parse::<f32>().unwrap();
+            let volume = k.volume as f32;
+            let open = k.open as f32;
+            let close = k.close as f32;
             let is_bullish = close >= open;
             let is_predicted = last_past_time < k.open_time;
             let fill_color: RGBColor = if is_bullish {
@@ -163,7 +475,7 @@ pub fn draw_volume_bars(
 
 pub fn draw_volume_detail(
     img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    klines: &[Kline],
+    klines: &[Candle],
     font: &impl Font,
     current_y: f32,
 ) -> Result<(), Box<dyn Error>> {
@@ -172,7 +484,7 @@ pub fn draw_volume_detail(
             .iter()
             .rev()
             .take(9)
-            .map(|k| k.volume.parse::<f32>().unwrap())
+            .map(|k| k.volume as f32)
             .sum::<f32>()
             / 9.0;
         let volume_detail = format!("Volume SMA 9 {:.2}K", volume_sma / 1000.0);
@@ -196,10 +508,11 @@ pub fn draw_macd(
         BitMapBackend<'_>,
         Cartesian2d<RangedDateTime<DateTime<Tz>>, RangedCoordf32>,
     >,
-    maybe_klines: &Option<Vec<Kline>>,
+    maybe_klines: &Option<Vec<Candle>>,
     timezone: &Tz,
     interval: &str,
     last_past_time: i64,
+    config: &MacdConfig,
 ) -> Result<(), Box<dyn Error>> {
     chart
         .configure_mesh()
@@ -210,8 +523,8 @@ pub fn draw_macd(
 
     if let Some(klines) = maybe_klines {
         let past_m4rs_candles: Vec<M4rsCandlestick> =
-            klines.iter().map(kline_to_m4rs_candlestick).collect();
-        let macd_result = macd(&past_m4rs_candles, 12, 26, 9)?;
+            klines.iter().map(candle_to_m4rs_candlestick).collect();
+        let macd_result = macd(&past_m4rs_candles, config.fast, config.slow, config.signal)?;
         let macd_lines: Vec<(DateTime<Tz>, f32, f32, f32)> = macd_result
             .iter()
             .map(|entry| {
@@ -238,7 +551,7 @@ pub fn draw_macd(
 
         let plotting_area = chart.plotting_area();
         let mut previous_h: Option<f32> = None;
-        let bar_width = parse_interval_duration(interval);
+        let bar_width = parse_interval_duration(interval)?;
 
         for (t, _, _, h) in macd_lines.iter() {
             let is_lower = previous_h.map_or_else(|| false, |prev| *h < prev);
@@ -294,17 +607,19 @@ pub fn draw_macd(
 
 pub fn draw_macd_detail(
     img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    klines: &[Kline],
+    klines: &[Candle],
     font: &impl Font,
     current_y: f32,
+    config: &MacdConfig,
 ) -> Result<(), Box<dyn Error>> {
     if !klines.is_empty() {
+        let MacdConfig { fast, slow, signal } = *config;
         let past_m4rs_candles: Vec<M4rsCandlestick> =
-            klines.iter().map(kline_to_m4rs_candlestick).collect();
-        let macd_result = macd(&past_m4rs_candles, 12, 26, 9)?;
-        let latest_macd = macd_result.last().unwrap();
+            klines.iter().map(candle_to_m4rs_candlestick).collect();
+        let macd_result = macd(&past_m4rs_candles, fast, slow, signal)?;
+        let latest_macd = macd_result.last().ok_or("Not enough candles for MACD")?;
         let macd_detail = format!(
-            "MACD 12 26 close 9 {:.2} {:.2} {:.2}",
+            "MACD {fast} {slow} close {signal} {:.2} {:.2} {:.2}",
             latest_macd.macd, latest_macd.signal, latest_macd.histogram
         );
         draw_label(
@@ -323,16 +638,29 @@ pub fn draw_macd_detail(
 
 pub fn draw_stoch_rsi_detail(
     img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    klines: &[Kline],
+    klines: &[Candle],
     font: &impl Font,
     current_y: f32,
+    config: &StochRsiConfig,
 ) -> Result<(), Box<dyn Error>> {
     if !klines.is_empty() {
+        let StochRsiConfig {
+            rsi_period,
+            stoch_period,
+            smooth_k,
+            smooth_d,
+        } = *config;
         let past_m4rs_candles: Vec<M4rsCandlestick> =
-            klines.iter().map(kline_to_m4rs_candlestick).collect();
-        let (_, stoch_rsi_k, stoch_rsi_d) = calculate_stoch_rsi(&past_m4rs_candles, 14, 14, 3, 3)?;
+            klines.iter().map(candle_to_m4rs_candlestick).collect();
+        let (_, stoch_rsi_k, stoch_rsi_d) = calculate_stoch_rsi(
+            &past_m4rs_candles,
+            rsi_period,
+            stoch_period,
+            smooth_k,
+            smooth_d,
+        )?;
         let stoch_rsi_detail = format!(
-            "Stoch RSI 14 14 3 3 {:.2} {:.2}",
+            "Stoch RSI {rsi_period} {stoch_period} {smooth_k} {smooth_d} {:.2} {:.2}",
             stoch_rsi_k.last().unwrap(),
             stoch_rsi_d.last().unwrap()
         );
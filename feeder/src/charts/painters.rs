@@ -1,17 +1,20 @@
 use super::candle::{calculate_candle_width, draw_candlesticks, Chart, LineStyle, PointStyle};
 use super::helpers::parse_kline_time;
 
-use super::indicators::{draw_bollinger_bands, draw_macd, draw_volume_bars};
+use super::indicators::{
+    draw_bollinger_bands, draw_divergence_lines, draw_fibonacci_levels, draw_macd,
+    draw_pattern_marks, draw_support_resistance_zones, draw_volume_bars,
+};
 use super::labels::draw_label;
 use crate::charts::helpers::get_visible_range_and_data;
 use ab_glyph::Font;
 use ab_glyph::ScaleFont;
 use chrono::DateTime;
 use chrono_tz::Tz;
-use common::m4rs::kline_to_m4rs_candlestick;
+use common::m4rs::candle_to_m4rs_candlestick;
 
 use common::rsi::calculate_stoch_rsi;
-use common::Kline;
+use common::Candle;
 use image::{ImageBuffer, Rgb};
 
 use imageproc::rect::Rect;
@@ -26,8 +29,8 @@ use std::error::Error;
 #[allow(clippy::too_many_arguments, unused)]
 pub fn draw_chart(
     root: &mut DrawingArea<BitMapBackend<'_>, plotters::coord::Shift>,
-    all_candle_data: &[Kline],
-    klines: &[Kline],
+    all_candle_data: &[Candle],
+    klines: &[Candle],
     timezone: &Tz,
     chart: &Chart,
     min_price: f32,
@@ -61,6 +64,18 @@ pub fn draw_chart(
     let total_candles_num = all_candle_data.len() as u8;
     let candle_width = calculate_candle_width(&top_chart, total_candles_num);
 
+    // Behind the candles.
+    if chart.support_resistance_enabled {
+        draw_support_resistance_zones(
+            &mut top_chart,
+            klines,
+            chart.orderbook_data.as_ref(),
+            first_time,
+            last_time,
+            &chart.indicators,
+        )?;
+    }
+
     draw_candlesticks(
         &mut top_chart,
         all_candle_data,
@@ -86,12 +101,34 @@ pub fn draw_chart(
 
     let (mut lower_bound, mut upper_bound) = (0.0, 0.0);
     if chart.bollinger_enabled {
-        let (new_lower_bound, new_upper_bound) =
-            draw_bollinger_bands(&mut top_chart, all_candle_data, timezone)?;
+        let (new_lower_bound, new_upper_bound) = draw_bollinger_bands(
+            &mut top_chart,
+            all_candle_data,
+            timezone,
+            &chart.indicators.bollinger,
+        )?;
         lower_bound = new_lower_bound;
         upper_bound = new_upper_bound;
     }
 
+    if chart.fibonacci_enabled {
+        draw_fibonacci_levels(
+            &mut top_chart,
+            klines,
+            timezone,
+            last_time,
+            chart.indicators.swing_threshold_pct,
+        )?;
+    }
+
+    if chart.patterns_enabled {
+        draw_pattern_marks(&mut top_chart, klines, timezone)?;
+    }
+
+    if chart.divergences_enabled {
+        draw_divergence_lines(&mut top_chart, klines, timezone, &chart.indicators)?;
+    }
+
     if let Some(stoch_rsi_area) = stoch_rsi_area {
         let (first_visible_time, last_visible_time, visible_data) =
             get_visible_range_and_data(all_candle_data, timezone, candle_width, final_width * 2)?;
@@ -101,9 +138,18 @@ pub fn draw_chart(
             .build_cartesian_2d(first_visible_time..last_visible_time, 0.0f32..100.0f32)?;
 
         // Convert visible_data to M4rsCandlestick and calculate Stoch RSI
-        let past_m4rs_candles: Vec<M4rsCandlestick> =
-            visible_data.iter().map(kline_to_m4rs_candlestick).collect();
-        let (_, stoch_rsi_k, stoch_rsi_d) = calculate_stoch_rsi(&past_m4rs_candles, 14, 14, 3, 3)?;
+        let past_m4rs_candles: Vec<M4rsCandlestick> = visible_data
+            .iter()
+            .map(candle_to_m4rs_candlestick)
+            .collect();
+        let stoch_rsi = &chart.indicators.stoch_rsi;
+        let (_, stoch_rsi_k, stoch_rsi_d) = calculate_stoch_rsi(
+            &past_m4rs_candles,
+            stoch_rsi.rsi_period,
+            stoch_rsi.stoch_period,
+            stoch_rsi.smooth_k,
+            stoch_rsi.smooth_d,
+        )?;
 
         // Align Stoch RSI values with timestamps
         let stoch_rsi_lines: Vec<(DateTime<Tz>, f32, f32)> = visible_data
@@ -194,7 +240,7 @@ pub fn draw_chart(
                 get_visible_range_and_data(all_candle_data, timezone, candle_width, final_width)?;
             let max_volume = visible_data
                 .iter()
-                .map(|k| k.volume.parse::<f32>().unwrap())
+                .map(|k| k.volume as f32)
                 .fold(0.0f32, |a, b| a.max(b));
             let mut volume_chart = ChartBuilder::on(&volume_area)
                 .margin_right(margin_right)
@@ -215,9 +261,17 @@ pub fn draw_chart(
             let (_idx, macd_area) = area_iter.next().unwrap();
             let (first_visible_time, last_visible_time, visible_data) =
                 get_visible_range_and_data(all_candle_data, timezone, candle_width, final_width)?;
-            let past_m4rs_candles: Vec<M4rsCandlestick> =
-                visible_data.iter().map(kline_to_m4rs_candlestick).collect();
-            let macd_result = macd(&past_m4rs_candles, 12, 26, 9)?;
+            let past_m4rs_candles: Vec<M4rsCandlestick> = visible_data
+                .iter()
+                .map(candle_to_m4rs_candlestick)
+                .collect();
+            let macd_config = &chart.indicators.macd;
+            let macd_result = macd(
+                &past_m4rs_candles,
+                macd_config.fast,
+                macd_config.slow,
+                macd_config.signal,
+            )?;
             let macd_values: Vec<f32> = macd_result
                 .iter()
                 .flat_map(|entry| {
@@ -245,6 +299,7 @@ pub fn draw_chart(
                 timezone,
                 &chart.interval,
                 last_past_time,
+                macd_config,
             )?;
         }
     }
@@ -256,7 +311,7 @@ pub fn draw_chart(
 pub fn draw_axis_labels(
     img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
     font: &impl Font,
-    klines: &[Kline],
+    klines: &[Candle],
     chart: &Chart,
     height: u32,
     final_width: u32,
@@ -317,7 +372,7 @@ pub fn draw_axis_labels(
     // Add current price label with refined y-position mapping
     let mut current_price_y = 0.0;
     let maybe_bounding_rect = if let Some(last_candle) = klines.last() {
-        let current_price = last_candle.close_price.parse::<f32>().unwrap();
+        let current_price = last_candle.close as f32;
         let adjusted_min_price = min_price * 0.95;
         let adjusted_max_price = max_price * 1.05;
         let price_range_adjusted = adjusted_max_price - adjusted_min_price;
@@ -356,10 +411,7 @@ pub fn draw_axis_labels(
     let mut current_y = stoch_rsi_section_height + top_section_height;
 
     if chart.volume_enabled {
-        let volumes: Vec<f32> = klines
-            .iter()
-            .map(|k| k.volume.parse::<f32>().unwrap())
-            .collect();
+        let volumes: Vec<f32> = klines.iter().map(|k| k.volume as f32).collect();
         let max_volume = volumes.iter().fold(0.0f32, |a, &b| a.max(b));
         let max_volume_display = max_volume * 1.1;
         let volume_step = max_volume_display / 2.0;
@@ -386,7 +438,7 @@ pub fn draw_axis_labels(
 
     if chart.macd_enabled {
         let past_m4rs_candles: Vec<M4rsCandlestick> =
-            klines.iter().map(kline_to_m4rs_candlestick).collect();
+            klines.iter().map(candle_to_m4rs_candlestick).collect();
         let macd_result = macd(&past_m4rs_candles, 12, 26, 9)?;
         let macd_values: Vec<f32> = macd_result
             .iter()
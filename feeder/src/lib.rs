@@ -2,9 +2,14 @@ mod charts;
 
 use charts::candle::Chart;
 use chrono_tz::Asia::Tokyo;
-use common::binance::fetch_orderbook_depth_usdt;
-use common::sources::binance::fetch_binance_kline_usdt;
-use common::Kline;
+use common::binance::BinanceClient;
+use common::cache::CachedSource;
+use common::indicator_config::IndicatorConfig;
+use common::market::MarketDataSource;
+use common::Candle;
+use common::Interval;
+use common::KvCache;
+use common::PairSymbol;
 use common::RefinedGraphPredictionResponse;
 #[cfg(feature = "service_binding")]
 use common::ServiceBinding; // Conditionally import ServiceBinding
@@ -13,9 +18,13 @@ use std::ops::Deref;
 use worker::*;
 
 // TODO: call service binding
-async fn gen_candle(pair_symbol: String, interval: String) -> anyhow::Result<Vec<Kline>> {
-    let kline_data_1m = fetch_binance_kline_usdt::<Kline>(&pair_symbol, &interval, 240).await?;
-    Ok(kline_data_1m)
+async fn gen_candle<S: MarketDataSource>(
+    source: &S,
+    pair_symbol: String,
+    interval: String,
+) -> anyhow::Result<Vec<Candle>> {
+    let kline_data_1m = source.fetch_klines(&pair_symbol, &interval, 240).await?;
+    Ok(Candle::from_klines(&kline_data_1m)?)
 }
 
 // TODO: pixel font
@@ -48,6 +57,16 @@ pub async fn handle_chart_prediction(
     is_signals: bool,
 ) -> worker::Result<Response> {
     if let Some(pair_symbol) = ctx.param("pair_symbol") {
+        // Validate pair, e.g. "SOL_USDT" or "SOL"
+        let pair_symbol: PairSymbol = match pair_symbol.parse() {
+            Ok(pair_symbol) => pair_symbol,
+            Err(error) => return Response::error(format!("Bad Request - {error}"), 400),
+        };
+        if let Err(error) = pair_symbol.validate() {
+            return Response::error(format!("Bad Request - {error}"), 400);
+        }
+        let pair_symbol = pair_symbol.to_string();
+
         // Get fetcher
         let api_url = ctx
             .env
@@ -55,14 +74,27 @@ pub async fn handle_chart_prediction(
             .expect("Expect PREDICTION_API_URL")
             .to_string();
 
-        // Get interval
-        let binding = "1h".to_string();
-        let interval = ctx.param("interval").unwrap_or(&binding);
+        // Get interval, e.g. "1h" or "1w"
+        let interval = match ctx
+            .param("interval")
+            .map_or(Ok(Interval::Hour1), |interval| interval.parse::<Interval>())
+        {
+            Ok(interval) => interval.to_string(),
+            Err(error) => return Response::error(format!("Bad Request - {error}"), 400),
+        };
+
+        // Indicator periods, e.g. "?macd=8,21,5&bb=20,2.5", see `IndicatorConfig::with_overrides`
+        let indicators = match IndicatorConfig::default().with_overrides(req.url()?.query_pairs()) {
+            Ok(indicators) => indicators,
+            Err(error) => return Response::error(format!("Bad Request - {error}"), 400),
+        };
 
         // Finalize api_url
         let relative_path = format!("{pair_symbol}/{interval}");
         let api_url_string = format!("{api_url}/{relative_path}");
-        let api_url = Url::parse(&api_url_string).unwrap();
+        let mut api_url = Url::parse(&api_url_string).unwrap();
+        // The prediction is made with the same indicator periods the chart is drawn with
+        api_url.set_query(req.url()?.query());
 
         // Get font
         let kv_store = ctx.kv("ASSETS").unwrap();
@@ -74,8 +106,15 @@ pub async fn handle_chart_prediction(
             .unwrap();
 
         // Get data
+        let binance_client =
+            BinanceClient::from_env_vars(|key| ctx.env.var(key).ok().map(|var| var.to_string()))
+                .map_err(|e| Error::RustError(format!("Invalid Binance config: {e}")))?;
+        // Klines and orderbooks are shared with the cooker for the current candle period
+        let source = CachedSource::new(binance_client, ctx.kv("CACHE").ok().map(KvCache::new))
+            .with_namespace("binance");
         let pair_symbol = pair_symbol.clone();
         let candle_data = gen_candle(
+            &source,
             pair_symbol.deref().to_string(),
             interval.deref().to_string(),
         )
@@ -93,7 +132,7 @@ pub async fn handle_chart_prediction(
         //     title: format!("{pair_symbol} {interval}"),
         // };
 
-        let orderbook = fetch_orderbook_depth_usdt(&pair_symbol, 2000).await;
+        let orderbook = source.fetch_orderbook(&pair_symbol, 2000).await;
         let orderbook = match orderbook {
             Ok(orderbook) => orderbook,
             Err(error) => {
@@ -106,7 +145,7 @@ pub async fn handle_chart_prediction(
             let prediction_result = {
                 #[cfg(not(feature = "service_binding"))]
                 {
-                    fetch_graph_prediction(api_url, &pair_symbol, interval, None)
+                    fetch_graph_prediction(api_url, &pair_symbol, &interval, None)
                         .await
                         .map_err(|e| {
                             worker::Error::RustError(format!(
@@ -150,7 +189,7 @@ pub async fn handle_chart_prediction(
         };
 
         // Get image
-        let buffer_result = Chart::new(interval, Tokyo)
+        let buffer_result = Chart::new(&interval, Tokyo)
             .with_past_candle(candle_data)
             // So sad this didn't work as expected due to poor results
             // .with_predicted_candle(predicted_klines)
@@ -161,6 +200,11 @@ pub async fn handle_chart_prediction(
             .with_stoch_rsi()
             .with_orderbook(orderbook)
             .with_bollinger_band()
+            .with_fibonacci()
+            .with_support_resistance()
+            .with_patterns()
+            .with_divergences()
+            .with_indicator_config(indicators)
             // .with_past_signals(predicted.signals)
             .with_signals(signals)
             .build();